@@ -1,9 +1,11 @@
 use std::fs::File;
-use std::io::{self, Error};
-use gif::{Frame, Encoder};
+use std::io::{BufWriter, Write};
+use gif::{Frame, Encoder, Repeat};
 use raqote::DrawTarget;
+use crate::error::{Result, RustybrotError};
 use crate::frame_handler::FrameHandler;
-use crate::mandelbrot::MandelbrotFrame;
+use crate::pixel_format::{argb_u32_to_rgba8, unpremultiply};
+use crate::mandelbrot::{continuous_iterations_for_zoom, FractalKind, MandelbrotFrame};
 
 #[derive(Clone, Copy)]
 pub struct Position {
@@ -16,22 +18,173 @@ pub struct AnimationNode {
     pub position: Position,
     pub time: f64,  // Time in seconds when this node should be reached
     pub zoom: f64,  // Zoom level at this node
+    pub iterations: Option<u32>,  // Iteration count at this node, None to follow the zoom
+}
+
+#[derive(Clone, Copy)]
+pub struct Bookmark {
+    pub position: Position,
+    pub zoom: f64,
+    pub iterations: u32,  // Iteration count the view was bookmarked with
+}
+
+// Animation nodes visiting the bookmarks in order, one hop apart
+pub fn nodes_from_bookmarks(bookmarks: &[Bookmark], hop_duration: f64) -> Vec<AnimationNode> {
+    bookmarks.iter()
+        .enumerate()
+        .map(|(i, bookmark)| AnimationNode {
+            position: bookmark.position,
+            time: i as f64 * hop_duration,
+            zoom: bookmark.zoom,
+            iterations: Some(bookmark.iterations),
+        })
+        .collect()
+}
+
+// Camera a hop frame was rendered with, time being seconds into the animation
+#[derive(Clone, Copy)]
+struct Camera {
+    time: f64,
+    position: Position,
+    zoom: f64,
+    iterations: Option<f64>,  // Continuous iteration target, see ramp_iterations
+}
+
+// Path traced by the Julia constant during a morph animation
+#[derive(Clone, Copy)]
+pub enum JuliaPath {
+    Circle { center: Position, radius: f64 },  // One full turn, loops seamlessly
+    Line { start: Position, end: Position },
+}
+
+impl JuliaPath {
+    fn point_at(&self, t: f64) -> Position {
+        match *self {
+            JuliaPath::Circle { center, radius } => {
+                let angle = t * std::f64::consts::TAU;
+                Position {
+                    x: center.x + radius * angle.cos(),
+                    y: center.y + radius * angle.sin(),
+                }
+            }
+            JuliaPath::Line { start, end } => AnimationHandler::interpolate_position(&start, &end, t),
+        }
+    }
+}
+
+// 4x4 Bayer threshold matrix used for ordered dithering
+const BAYER_4X4: [[u8; 4]; 4] = [
+    [0, 8, 2, 10],
+    [12, 4, 14, 6],
+    [3, 11, 1, 9],
+    [15, 7, 13, 5],
+];
+
+// Peak channel offset applied by the dither pattern, roughly one palette step
+const DITHER_STRENGTH: f32 = 8.0;
+
+// Frames an animation may have unless the cap is changed, a minute at 30 fps
+pub const DEFAULT_MAX_FRAMES: u32 = 1800;
+
+// Speed value between 1 and 30 for quantizing frames. Higher = faster but lower quality
+const DEFAULT_ENCODING_SPEED: i32 = 10;
+
+// Fixed GIF palette of up to 256 colors, with a 15-bit color lookup table so
+// frames can be indexed without quantizing them
+pub struct FixedPalette {
+    colors: Vec<u8>,
+    lookup: Vec<u8>,
+}
+
+impl FixedPalette {
+    pub fn new(colors: &[[u8; 3]]) -> Self {
+        let colors = &colors[..colors.len().min(256)];
+        let lookup = (0..1u32 << 15)
+            .map(|key| {
+                let r = ((key >> 10) & 0x1F) << 3 | 0x4;
+                let g = ((key >> 5) & 0x1F) << 3 | 0x4;
+                let b = (key & 0x1F) << 3 | 0x4;
+                let distance = |color: &[u8; 3]| {
+                    let dr = color[0] as i32 - r as i32;
+                    let dg = color[1] as i32 - g as i32;
+                    let db = color[2] as i32 - b as i32;
+                    dr * dr + dg * dg + db * db
+                };
+                (0..colors.len()).min_by_key(|&i| distance(&colors[i])).unwrap_or(0) as u8
+            })
+            .collect();
+
+        FixedPalette {
+            colors: colors.iter().flatten().copied().collect(),
+            lookup,
+        }
+    }
+
+    fn index_of(&self, r: u8, g: u8, b: u8) -> u8 {
+        self.lookup[((r as usize >> 3) << 10) | ((g as usize >> 3) << 5) | (b as usize >> 3)]
+    }
+}
+
+// Per-frame normalization of the coloring to the exterior's iteration
+// percentiles, keeping contrast steady as the iteration distribution shifts
+// over a flight
+#[derive(Clone, Copy)]
+pub struct AutoExposure {
+    pub low_percentile: f32,   // Exterior fraction mapped to the start of the palette
+    pub high_percentile: f32,  // Exterior fraction mapped to the end of the palette
+    pub smoothing: f32,        // Share of the previous frame's exposure kept, 0 adapts instantly
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum DitherMode {
+    None,
+    Ordered,  // Same Bayer pattern on every frame
+    Temporal, // Bayer pattern shifted each frame, deterministic in the frame index
+}
+
+// Where frames are written, chosen by the output file's extension
+enum AnimationEncoder {
+    Gif(Encoder<File>),
+    // APNG states its frame count before the first frame, so each animation
+    // rewrites the file with just its own frames
+    Apng { filename: String, writer: Option<png::Writer<BufWriter<File>>> },
 }
 
 pub struct AnimationHandler {
     width: u32,
     height: u32,
-    encoder: Encoder<File>,
+    encoder: AnimationEncoder,
     fps: u32,
     start_node: Option<AnimationNode>,
     end_node: Option<AnimationNode>,
+    dither_mode: DitherMode,
+    frame_index: u32,
+    encoding_speed: i32,
+    fixed_palette: Option<FixedPalette>,
+    auto_exposure: Option<AutoExposure>,
+    exposure: Option<(f32, f32)>,  // Smoothed iteration range of the previous frame
+    zoom_iterations: Option<u32>,  // Base iteration count scaled with each frame's zoom
+    camera_path: Option<BufWriter<File>>,  // CSV of the camera of every frame of a hop
+    max_frames: Option<u32>,  // Animations with more frames are refused, None allows any
 }
 
 impl AnimationHandler {
-    pub fn new(width: u32, height: u32, filename: &str, fps: u32) -> io::Result<Self> {
-        let file = File::create(filename)?;
-        let encoder = Encoder::new(file, width as u16, height as u16, &[])
-            .map_err(|e| Error::new(io::ErrorKind::Other, e))?;
+    // Writes a true color APNG if the filename ends in .png or .apng, else a GIF
+    pub fn new(width: u32, height: u32, filename: &str, fps: u32) -> Result<Self> {
+        if fps == 0 {
+            return Err(RustybrotError::Config("Animation fps must be at least 1".to_string()));
+        }
+        
+        let extension = filename.rsplit_once('.').map(|(_, extension)| extension.to_ascii_lowercase());
+        let encoder = if matches!(extension.as_deref(), Some("png" | "apng")) {
+            if width == 0 || height == 0 {
+                return Err(RustybrotError::Config(format!("APNG dimensions {}x{} must be at least 1 pixel", width, height)));
+            }
+            AnimationEncoder::Apng { filename: filename.to_string(), writer: None }
+        } else {
+            let (gif_width, gif_height) = Self::gif_dimensions(width, height)?;
+            AnimationEncoder::Gif(Encoder::new(File::create(filename)?, gif_width, gif_height, &[])?)
+        };
         
         Ok(AnimationHandler {
             width,
@@ -40,6 +193,15 @@ impl AnimationHandler {
             fps,
             start_node: None,
             end_node: None,
+            dither_mode: DitherMode::None,
+            frame_index: 0,
+            encoding_speed: DEFAULT_ENCODING_SPEED,
+            fixed_palette: None,
+            auto_exposure: None,
+            exposure: None,
+            zoom_iterations: None,
+            camera_path: None,
+            max_frames: Some(DEFAULT_MAX_FRAMES),
         })
     }
 
@@ -48,6 +210,7 @@ impl AnimationHandler {
             position: Position { x, y },
             time,
             zoom,
+            iterations: None,
         });
     }
 
@@ -56,9 +219,66 @@ impl AnimationHandler {
             position: Position { x, y },
             time,
             zoom,
+            iterations: None,
         });
     }
 
+    pub fn set_dither_mode(&mut self, dither_mode: DitherMode) {
+        self.dither_mode = dither_mode;
+    }
+
+    pub fn dither_mode(&self) -> DitherMode {
+        self.dither_mode
+    }
+
+    // Quantization speed from 1 (best quality) to 30 (fastest)
+    pub fn set_encoding_speed(&mut self, speed: i32) {
+        self.encoding_speed = speed.clamp(1, 30);
+    }
+
+    pub fn encoding_speed(&self) -> i32 {
+        self.encoding_speed
+    }
+
+    // With a fixed palette frames are mapped straight onto it instead of
+    // being quantized, which is much faster
+    pub fn set_fixed_palette(&mut self, fixed_palette: Option<FixedPalette>) {
+        self.fixed_palette = fixed_palette;
+    }
+
+    pub fn has_fixed_palette(&self) -> bool {
+        self.fixed_palette.is_some()
+    }
+
+    pub fn set_auto_exposure(&mut self, auto_exposure: Option<AutoExposure>) {
+        self.auto_exposure = auto_exposure;
+    }
+
+    pub fn has_auto_exposure(&self) -> bool {
+        self.auto_exposure.is_some()
+    }
+
+    // With a base count set, camera hops between nodes without their own
+    // iteration counts raise the iterations with the interpolated zoom as the
+    // viewer does, None keeps them fixed. Hops follow
+    // the continuous count rather than stepping, see ramp_iterations.
+    pub fn set_zoom_iterations(&mut self, base_iterations: Option<u32>) {
+        self.zoom_iterations = base_iterations;
+    }
+
+    // Camera hops also write one row per frame to this CSV, giving the
+    // parameters each frame was rendered with
+    pub fn set_camera_path(&mut self, filename: &str) -> Result<()> {
+        let mut writer = BufWriter::new(File::create(filename)?);
+        writeln!(writer, "frame,time,center_re,center_im,zoom,max_iterations")?;
+        self.camera_path = Some(writer);
+        Ok(())
+    }
+
+    pub fn set_max_frames(&mut self, max_frames: Option<u32>) {
+        self.max_frames = max_frames;
+    }
+
     pub fn has_start_node(&self) -> bool {
         self.start_node.is_some()
     }
@@ -67,6 +287,14 @@ impl AnimationHandler {
         self.end_node.is_some()
     }
 
+    // Straight path between the start and end node positions, if both are set
+    pub fn node_path(&self) -> Option<JuliaPath> {
+        match (self.start_node, self.end_node) {
+            (Some(start), Some(end)) => Some(JuliaPath::Line { start: start.position, end: end.position }),
+            _ => None,
+        }
+    }
+
     pub fn clear_nodes(&mut self) {
         self.start_node = None;
         self.end_node = None;
@@ -79,67 +307,335 @@ impl AnimationHandler {
         }
     }
 
-    pub fn create_animation(&mut self, frame_handler: &mut FrameHandler, mandelbrot: &mut MandelbrotFrame) -> io::Result<()> {
+    pub fn create_animation(&mut self, frame_handler: &mut FrameHandler, mandelbrot: &mut MandelbrotFrame) -> Result<()> {
         // Get the nodes and their data before the mutable borrow
-        let start_node = self.start_node.expect("Start node must be set before creating animation");
-        let end_node = self.end_node.expect("End node must be set before creating animation");
-            
+        let (Some(start_node), Some(end_node)) = (self.start_node, self.end_node) else {
+            return Err(RustybrotError::Config("Start and end nodes must be set before creating an animation".to_string()));
+        };
+        self.begin_animation(self.frame_count(end_node.time - start_node.time))?;
+        
+        self.render_hop(frame_handler, mandelbrot, start_node, end_node)?;
+        self.finish_animation()
+    }
+
+    // Flies through the nodes in order, one hop between each consecutive pair
+    pub fn create_keyframe_animation(&mut self, frame_handler: &mut FrameHandler, mandelbrot: &mut MandelbrotFrame, nodes: &[AnimationNode]) -> Result<()> {
+        self.begin_animation(nodes.windows(2).map(|hop| self.frame_count(hop[1].time - hop[0].time)).sum())?;
+        for hop in nodes.windows(2) {
+            self.render_hop(frame_handler, mandelbrot, hop[0], hop[1])?;
+        }
+        
+        self.finish_animation()
+    }
+
+    fn render_hop(&mut self, frame_handler: &mut FrameHandler, mandelbrot: &mut MandelbrotFrame, start_node: AnimationNode, end_node: AnimationNode) -> Result<()> {
         let duration = end_node.time - start_node.time;
-        let total_frames = (duration * self.fps as f64) as u32;
+        let total_frames = self.frame_count(duration) as u32;
+        let zoom_iterations = self.zoom_iterations;
         
-        for frame in 0..total_frames {
-            let t = frame as f64 / total_frames as f64;
+        self.render_frames(total_frames, frame_handler, mandelbrot, |mandelbrot, t| {
             let current_pos = Self::interpolate_position(&start_node.position, &end_node.position, t);
             let current_zoom = start_node.zoom + (end_node.zoom - start_node.zoom) * t;
             
             // Update Mandelbrot frame with interpolated position and zoom
-            mandelbrot.x_min = current_pos.x - (1.5 / current_zoom);
-            mandelbrot.x_max = current_pos.x + (1.5 / current_zoom);
-            mandelbrot.y_min = current_pos.y - (1.0 / current_zoom);
-            mandelbrot.y_max = current_pos.y + (1.0 / current_zoom);
+            mandelbrot.set_view(current_pos.x, current_pos.y, current_zoom);
+            
+            // Nodes that carry their own iteration counts are blended between,
+            // otherwise the count follows the zoom if a base count is set
+            let iterations = match (start_node.iterations, end_node.iterations) {
+                (Some(start), Some(end)) => Some(start as f64 + (end as f64 - start as f64) * t),
+                _ => zoom_iterations.map(|base_iterations| continuous_iterations_for_zoom(base_iterations, current_zoom)),
+            };
+            if let Some(iterations) = iterations {
+                mandelbrot.max_iterations = (iterations.ceil() as u32).max(1);
+            }
+            Some(Camera { time: start_node.time + duration * t, position: current_pos, zoom: current_zoom, iterations })
+        })
+    }
+
+    // Keeps the viewport fixed and moves the Julia constant along the path instead
+    pub fn create_julia_morph(&mut self, frame_handler: &mut FrameHandler, mandelbrot: &mut MandelbrotFrame, path: JuliaPath, duration: f64) -> Result<()> {
+        let total_frames = self.frame_count(duration);
+        self.begin_animation(total_frames)?;
+        let total_frames = total_frames as u32;
+        
+        // The loop extension has to precede the first frame of the file
+        if let (AnimationEncoder::Gif(encoder), 0) = (&mut self.encoder, self.frame_index) {
+            encoder.set_repeat(Repeat::Infinite)?;
+        }
+        
+        self.render_frames(total_frames, frame_handler, mandelbrot, |mandelbrot, t| {
+            let c = path.point_at(t);
+            mandelbrot.kind = FractalKind::Julia { cx: c.x, cy: c.y };
+            None
+        })?;
+        self.finish_animation()
+    }
+
+    fn frame_count(&self, duration: f64) -> u64 {
+        (duration * self.fps as f64).max(0.0) as u64
+    }
+
+    // Prints the frame count and a size estimate before rendering starts, and
+    // refuses animations over the frame cap so a slip in duration or fps
+    // can't fill the disk. An APNG file is started here, as its header
+    // holds the frame count. Exposure eases on from hop to hop, but each
+    // animation starts from its own first frame.
+    fn begin_animation(&mut self, total_frames: u64) -> Result<()> {
+        // One palette index or RGBA pixel per pixel, which compression usually shrinks
+        let bytes_per_pixel = if matches!(self.encoder, AnimationEncoder::Apng { .. }) { 4 } else { 1 };
+        let estimated_bytes = total_frames * self.width as u64 * self.height as u64 * bytes_per_pixel;
+        println!("Animation of {} frames, up to about {:.1} MB", total_frames, estimated_bytes as f64 / 1e6);
+        if let Some(max_frames) = self.max_frames.filter(|&max_frames| total_frames > max_frames as u64) {
+            return Err(RustybrotError::Config(format!(
+                "Animation of {} frames is over the cap of {} (raise it with --max-frames n, or remove it with --max-frames off)",
+                total_frames, max_frames,
+            )));
+        }
+        self.exposure = None;
+
+        if let AnimationEncoder::Apng { filename, writer } = &mut self.encoder {
+            let Ok(num_frames @ 1..) = u32::try_from(total_frames) else {
+                return Ok(());
+            };
+            let mut encoder = png::Encoder::new(BufWriter::new(File::create(&*filename)?), self.width, self.height);
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+            encoder.set_animated(num_frames, 0)?;  // Zero plays loops forever
+            encoder.set_frame_delay(1, self.fps.min(u16::MAX as u32) as u16)?;
+            *writer = Some(encoder.write_header()?);
+        }
+        Ok(())
+    }
+
+    // Completes an APNG file once all the frames begin_animation announced are written
+    fn finish_animation(&mut self) -> Result<()> {
+        if let AnimationEncoder::Apng { writer, .. } = &mut self.encoder {
+            if let Some(writer) = writer.take() {
+                writer.finish()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn render_frames<F>(&mut self, total_frames: u32, frame_handler: &mut FrameHandler, mandelbrot: &mut MandelbrotFrame, mut update_frame: F) -> Result<()>
+    where
+        F: FnMut(&mut MandelbrotFrame, f64) -> Option<Camera>,
+    {
+        // One iteration buffer reused by every frame
+        let mut iterations = vec![0.0; (mandelbrot.width * mandelbrot.height) as usize];
+        let mut orbit = Vec::new();
+        frame_handler.clear_orbit_trail();
+        
+        for frame in 0..total_frames {
+            let t = frame as f64 / total_frames as f64;
+            let camera = update_frame(mandelbrot, t);
             
             // Calculate and render the frame
-            let iterations = mandelbrot.calculate();
+            mandelbrot.calculate_into(&mut iterations)?;
+            if let Some(target) = camera.and_then(|camera| camera.iterations) {
+                Self::ramp_iterations(&mut iterations, mandelbrot.max_iterations, target as f32);
+            }
+            if let Some(auto_exposure) = self.auto_exposure {
+                self.apply_auto_exposure(auto_exposure, &mut iterations, mandelbrot.max_iterations);
+            }
             frame_handler.render_frame(&iterations, mandelbrot.max_iterations, 1);
+            if frame_handler.orbit_trail().is_some() {
+                // The Julia constant's orbit is the critical orbit of its Julia set
+                let point = match mandelbrot.kind {
+                    FractalKind::Julia { cx, cy } => (cx, cy),
+                    FractalKind::Mandelbrot => ((mandelbrot.x_min + mandelbrot.x_max) / 2.0, (mandelbrot.y_min + mandelbrot.y_max) / 2.0),
+                };
+                mandelbrot.trace_orbit(point, &mut orbit);
+                frame_handler.draw_orbit_trail(mandelbrot, &orbit);
+            }
             
-            // Calculate delay in hundredths of a second (gif delay unit)
-            let delay = (100.0 / self.fps as f64) as u16;
-            self.add_frame(frame_handler.get_draw_target(), delay)?;
+            if let (Some(writer), Some(camera)) = (&mut self.camera_path, camera) {
+                writeln!(writer, "{},{},{},{},{},{}", self.frame_index, camera.time, camera.position.x, camera.position.y,
+                         camera.zoom, mandelbrot.max_iterations)?;
+            }
+            
+            self.add_frame(frame_handler.get_draw_target())?;
             
             // Print progress
             print!("\rGenerating animation: {:.1}%", (frame as f64 / total_frames as f64) * 100.0);
         }
         println!(); // New line after progress
         
+        if let Some(writer) = &mut self.camera_path {
+            writer.flush()?;
+        }
+        
         Ok(())
     }
 
-    pub fn add_frame(&mut self, draw_target: &DrawTarget, delay: u16) -> io::Result<()> {
+    // Colors a frame iterated to the ceiling of a continuous iteration target
+    // as if iterated to the target itself. Points escaping past the target
+    // are drawn as the interior and the palette is stretched over the target,
+    // so as a hop's target rises, the escaping region grows and the bands
+    // shift a little each frame instead of popping when the count steps.
+    fn ramp_iterations(iterations: &mut [f32], max_iterations: u32, target: f32) {
+        let max_iterations = max_iterations as f32;
+        if target <= 0.0 {
+            return;
+        }
+        for value in iterations.iter_mut() {
+            *value = if *value >= target { max_iterations } else { *value * max_iterations / target };
+        }
+    }
+
+    // Stretches the frame's exterior iteration percentiles, eased from the
+    // previous frame's, across the palette. In-set points are left alone.
+    fn apply_auto_exposure(&mut self, auto_exposure: AutoExposure, iterations: &mut [f32], max_iterations: u32) {
+        let max_iterations = max_iterations as f32;
+        let mut exterior: Vec<f32> = iterations.iter().copied().filter(|&value| value < max_iterations).collect();
+        if exterior.is_empty() {
+            return;
+        }
+
+        let mut percentile = |fraction: f32| {
+            let index = ((exterior.len() - 1) as f32 * fraction.clamp(0.0, 1.0)) as usize;
+            *exterior.select_nth_unstable_by(index, |a, b| a.total_cmp(b)).1
+        };
+        let target = (percentile(auto_exposure.low_percentile), percentile(auto_exposure.high_percentile));
+
+        let (low, high) = match self.exposure {
+            Some((low, high)) => {
+                let keep = auto_exposure.smoothing.clamp(0.0, 1.0);
+                (target.0 + (low - target.0) * keep, target.1 + (high - target.1) * keep)
+            }
+            None => target,
+        };
+        self.exposure = Some((low, high));
+
+        // Exposed values stay just below max_iterations so they aren't taken as in-set
+        let range = (high - low).max(f32::EPSILON);
+        let top = (max_iterations - 1.0).max(0.0);
+        for value in iterations.iter_mut().filter(|value| **value < max_iterations) {
+            *value = ((*value - low) / range).clamp(0.0, 1.0) * top;
+        }
+    }
+
+    // GIF dimensions are stored as u16, so anything larger can't be encoded
+    fn gif_dimensions(width: u32, height: u32) -> Result<(u16, u16)> {
+        match (u16::try_from(width), u16::try_from(height)) {
+            (Ok(gif_width), Ok(gif_height)) if gif_width > 0 && gif_height > 0 => Ok((gif_width, gif_height)),
+            _ => Err(RustybrotError::Config(
+                format!("GIF dimensions {}x{} are outside the supported range of 1 to {} pixels", width, height, u16::MAX),
+            )),
+        }
+    }
+
+    // Appends a frame shown for 1/fps seconds
+    pub fn add_frame(&mut self, draw_target: &DrawTarget) -> Result<()> {
         let pixels = draw_target.get_data();
-        let mut buffer = Vec::with_capacity((self.width * self.height * 4) as usize);
+        if pixels.len() != self.width as usize * self.height as usize {
+            return Err(RustybrotError::Config(
+                format!("Frame has {} pixels but the animation is {}x{}", pixels.len(), self.width, self.height),
+            ));
+        }
+        
+        let mut buffer = argb_u32_to_rgba8(pixels);
+        
+        // True color needs no dithering or palette, and PNG stores straight alpha
+        if let AnimationEncoder::Apng { writer, .. } = &mut self.encoder {
+            let Some(writer) = writer else {
+                return Err(RustybrotError::Config("APNG frames can only be added while creating an animation".to_string()));
+            };
+            let straight: Vec<u8> = buffer.chunks_exact(4).flat_map(unpremultiply).collect();
+            writer.write_image_data(&straight)?;
+            self.frame_index += 1;
+            return Ok(());
+        }
         
-        // Convert ARGB to RGB palette
-        for pixel in pixels.iter() {
-            let b = (pixel & 0xFF) as u8;
-            let g = ((pixel >> 8) & 0xFF) as u8;
-            let r = ((pixel >> 16) & 0xFF) as u8;
-            buffer.push(r);
-            buffer.push(g);
-            buffer.push(b);
-            buffer.push(255); // Alpha
-        }
-
-        let mut frame = Frame::from_rgba_speed(
-            self.width as u16,
-            self.height as u16,
-            &mut buffer,
-            10, // Speed value between 1 and 30. Higher = faster but lower quality
-        );
-        frame.delay = delay; // In hundredths of a second
+        let (gif_width, gif_height) = Self::gif_dimensions(self.width, self.height)?;
+        // Temporal dithering walks the pattern through all 16 offsets in turn
+        let (offset_x, offset_y) = match self.dither_mode {
+            DitherMode::Temporal => ((self.frame_index % 4) as usize, (self.frame_index / 4 % 4) as usize),
+            _ => (0, 0),
+        };
+
+        // Dither the RGB channels, alpha stays opaque. GIF has no partial alpha,
+        // so keeping the premultiplied channels shows translucent pixels over black.
+        for (i, rgba) in buffer.chunks_exact_mut(4).enumerate() {
+            let dither = if self.dither_mode == DitherMode::None {
+                0.0
+            } else {
+                let x = i % self.width as usize + offset_x;
+                let y = i / self.width as usize + offset_y;
+                ((BAYER_4X4[y % 4][x % 4] as f32 + 0.5) / 16.0 - 0.5) * DITHER_STRENGTH
+            };
+            for channel in rgba.iter_mut().take(3) {
+                *channel = (*channel as f32 + dither).clamp(0.0, 255.0) as u8;
+            }
+            rgba[3] = 255;
+        }
+
+        let mut frame = match &self.fixed_palette {
+            Some(palette) => {
+                let indices: Vec<u8> = buffer.chunks_exact(4)
+                    .map(|rgba| palette.index_of(rgba[0], rgba[1], rgba[2]))
+                    .collect();
+                Frame::from_palette_pixels(gif_width, gif_height, &indices, &palette.colors, None)
+            }
+            None => Frame::from_rgba_speed(gif_width, gif_height, &mut buffer, self.encoding_speed),
+        };
+        frame.delay = gif_frame_delay(self.frame_index, self.fps);
         
-        self.encoder.write_frame(&frame)
-            .map_err(|e| Error::new(io::ErrorKind::Other, e))?;
+        if let AnimationEncoder::Gif(encoder) = &mut self.encoder {
+            encoder.write_frame(&frame)?;
+        }
+        self.frame_index += 1;
         
         Ok(())
     }
-} 
\ No newline at end of file
+}
+
+// Delay of the frame at index in hundredths of a second, GIF's unit. Each
+// frame's end time is rounded rather than its length, so the delays add up
+// to the animation's duration at any fps.
+fn gif_frame_delay(index: u32, fps: u32) -> u16 {
+    let end_time = |frame: u32| (100.0 * frame as f64 / fps as f64).round() as u64;
+    (end_time(index + 1) - end_time(index)).min(u16::MAX as u64) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(x: f64, y: f64, time: f64, zoom: f64) -> AnimationNode {
+        AnimationNode { position: Position { x, y }, time, zoom, iterations: Some(200) }
+    }
+
+    // Exposure after flying through the nodes, kept entirely from frame to frame
+    fn final_exposure(nodes: &[AnimationNode]) -> Option<(f32, f32)> {
+        let (width, height) = (32, 24);
+        let filename = std::env::temp_dir().join(format!("rustybrot-exposure-{}-{}.gif", std::process::id(), nodes.len()));
+        let mut animation_handler = AnimationHandler::new(width, height, filename.to_str().unwrap(), 2).unwrap();
+        animation_handler.set_auto_exposure(Some(AutoExposure { low_percentile: 0.02, high_percentile: 0.98, smoothing: 1.0 }));
+        let mut frame_handler = FrameHandler::new(width, height);
+        let mut mandelbrot = MandelbrotFrame::new(width, height);
+        animation_handler.create_keyframe_animation(&mut frame_handler, &mut mandelbrot, nodes).unwrap();
+        std::fs::remove_file(filename).unwrap();
+        animation_handler.exposure
+    }
+
+    #[test]
+    fn auto_exposure_carries_across_hops() {
+        let seahorse = node(-0.745, 0.11, 0.0, 50.0);
+        let whole_set = node(-0.5, 0.0, 1.0, 1.0);
+        let one_hop = final_exposure(&[seahorse, whole_set]);
+        let two_hops = final_exposure(&[seahorse, whole_set, node(-0.1, 0.8, 2.0, 4.0)]);
+        assert!(one_hop.is_some());
+        assert_eq!(two_hops, one_hop, "the second hop should keep the first frame's exposure");
+    }
+
+    #[test]
+    fn gif_frame_delays_add_up_to_the_duration() {
+        // A minute at 30 fps, whose frames are 3 and 4 hundredths long
+        let delays: Vec<u16> = (0..DEFAULT_MAX_FRAMES).map(|index| gif_frame_delay(index, 30)).collect();
+        assert_eq!(delays.iter().map(|&delay| delay as u32).sum::<u32>(), 6000);
+        assert!(delays.iter().all(|&delay| delay == 3 || delay == 4));
+        assert_eq!((0..3600).map(|index| gif_frame_delay(index, 60) as u32).sum::<u32>(), 6000);
+    }
+}
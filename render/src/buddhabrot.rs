@@ -0,0 +1,82 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+use rayon::prelude::*;
+use crate::mandelbrot::{halton, Formula, MandelbrotFrame};
+
+// Square c is sampled from, every point outside it escapes on the first iteration
+const SAMPLE_HALF_SIZE: f64 = 2.0;
+
+// Counts how often the orbits of escaping points pass through each pixel of the
+// frame's view. The c values are Halton (2, 3) points over the sampled square,
+// so a given sample count always gives the same image.
+pub fn accumulate(frame: &MandelbrotFrame, samples: u32) -> Vec<u32> {
+    let width = frame.width as usize;
+    let height = frame.height as usize;
+    let density: Vec<AtomicU32> = (0..width * height).map(|_| AtomicU32::new(0)).collect();
+    let skip_bulbs = bulbs_never_escape(frame);
+
+    (0..samples).into_par_iter().for_each_init(Vec::new, |orbit, i| {
+        let c = (
+            (halton(i, 2) * 2.0 - 1.0) * SAMPLE_HALF_SIZE,
+            (halton(i, 3) * 2.0 - 1.0) * SAMPLE_HALF_SIZE,
+        );
+        if (skip_bulbs && in_main_bulbs(c)) || !frame.trace_orbit(c, orbit) {
+            return;
+        }
+
+        // The first point is c itself, which would only trace out the sampled square
+        for &(re, im) in orbit.iter().skip(1) {
+            let (x, y) = frame.complex_to_grid(re, im);
+            if x >= 0.0 && y >= 0.0 && (x as usize) < width && (y as usize) < height {
+                density[y as usize * width + x as usize].fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    });
+
+    density.into_iter().map(AtomicU32::into_inner).collect()
+}
+
+// The bulbs are only known not to escape for z^2 + c with orbits starting at zero
+fn bulbs_never_escape(frame: &MandelbrotFrame) -> bool {
+    frame.initial_z == (0.0, 0.0) && frame.formula == Formula::Mandelbrot && frame.power == 2 && frame.recurrence.is_none()
+}
+
+// Points in the main cardioid or the period 2 bulb never escape, so they are
+// skipped rather than iterated to max_iterations
+fn in_main_bulbs((re, im): (f64, f64)) -> bool {
+    let q = (re - 0.25) * (re - 0.25) + im * im;
+    q * (q + re - 0.25) <= 0.25 * im * im || (re + 1.0) * (re + 1.0) + im * im <= 1.0 / 16.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bulbs_are_only_skipped_where_they_never_escape() {
+        let bulb_points: Vec<(f64, f64)> = (0..=40).flat_map(|i| (0..=20).map(move |j| (-1.25 + i as f64 * 0.04, j as f64 * 0.04)))
+            .filter(|&point| in_main_bulbs(point))
+            .collect();
+        let mut orbit = Vec::new();
+        let mut skipped = 0;
+        for (formula, power, initial_z) in [
+            (Formula::Mandelbrot, 2, (0.0, 0.0)),
+            (Formula::Mandelbrot, 3, (0.0, 0.0)),
+            (Formula::BurningShip, 2, (0.0, 0.0)),
+            (Formula::Tricorn, 2, (0.0, 0.0)),
+            (Formula::Mandelbrot, 2, (0.5, 0.5)),
+        ] {
+            let mut frame = MandelbrotFrame::new(8, 8);
+            frame.max_iterations = 500;
+            (frame.formula, frame.power, frame.initial_z) = (formula, power, initial_z);
+            let escaping = bulb_points.iter().filter(|&&point| frame.trace_orbit(point, &mut orbit)).count();
+            if bulbs_never_escape(&frame) {
+                skipped += 1;
+                assert_eq!(escaping, 0, "{:?} power {} from {:?} skips escaping points", formula, power, initial_z);
+            } else {
+                assert!(escaping > 0, "{:?} power {} from {:?} needn't iterate the bulbs", formula, power, initial_z);
+            }
+        }
+        assert_eq!(skipped, 1);
+    }
+}
+
@@ -0,0 +1,31 @@
+use std::io::{self, Error};
+use crate::error::Result;
+#[cfg(feature = "clipboard")]
+use std::cell::RefCell;
+
+// The clipboard is kept open for the viewer's lifetime. On X11 and Wayland the
+// copied text is served by its owner, so it would vanish if closed after copying.
+#[cfg(feature = "clipboard")]
+thread_local! {
+    static CLIPBOARD: RefCell<Option<arboard::Clipboard>> = const { RefCell::new(None) };
+}
+
+// Copies text to the system clipboard
+#[cfg(feature = "clipboard")]
+pub fn copy(text: &str) -> Result<()> {
+    let unavailable = |e: arboard::Error| Error::new(io::ErrorKind::NotFound, format!("No clipboard available ({})", e));
+    CLIPBOARD.with(|clipboard| {
+        let mut clipboard = clipboard.borrow_mut();
+        let opened = match clipboard.take() {
+            Some(opened) => opened,
+            None => arboard::Clipboard::new().map_err(unavailable)?,
+        };
+        clipboard.insert(opened).set_text(text).map_err(unavailable)?;
+        Ok(())
+    })
+}
+
+#[cfg(not(feature = "clipboard"))]
+pub fn copy(_text: &str) -> Result<()> {
+    Err(Error::new(io::ErrorKind::Unsupported, "Built without the clipboard feature").into())
+}
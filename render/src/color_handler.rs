@@ -1,8 +1,188 @@
+use std::fs;
+use std::io;
 use raqote::SolidSource;
+use serde::{Deserialize, Serialize};
+use crate::error::{Result, RustybrotError};
 
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorScheme {
+    Hsv,      // Hue wheel over the iteration count, in-set points black
+    Heatmap,  // Debug view of compute cost: raw iteration count, in-set points hottest
+}
+
+impl ColorScheme {
+    pub fn from_name(name: &str) -> Option<ColorScheme> {
+        match name.to_ascii_lowercase().as_str() {
+            "hsv" => Some(ColorScheme::Hsv),
+            "heatmap" => Some(ColorScheme::Heatmap),
+            _ => None,
+        }
+    }
+}
+
+// Coloring by the external angle, the argument of z at escape
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AngleStyle {
+    Hue,     // Angle around the hue wheel at full brightness
+    Shaded,  // Hue from the angle, brightness from the iteration count
+}
+
+impl AngleStyle {
+    pub fn from_name(name: &str) -> Option<AngleStyle> {
+        match name.to_ascii_lowercase().as_str() {
+            "hue" => Some(AngleStyle::Hue),
+            "shaded" => Some(AngleStyle::Shaded),
+            _ => None,
+        }
+    }
+}
+
+// Stop lists the escaping points of the hsv scheme are colored from, blended
+// between along the normalized iteration count
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Palette {
+    Rainbow,    // The hue wheel, stop for stop
+    Fire,       // Black through red and yellow to white
+    Ocean,      // Deep blue through cyan to white
+    Grayscale,  // Black to white
+    Ultra,      // Navy, white and gold, after Ultra Fractal's default gradient
+}
+
+impl Palette {
+    pub fn from_name(name: &str) -> Option<Palette> {
+        match name.to_ascii_lowercase().as_str() {
+            "rainbow" => Some(Palette::Rainbow),
+            "fire" => Some(Palette::Fire),
+            "ocean" => Some(Palette::Ocean),
+            "grayscale" => Some(Palette::Grayscale),
+            "ultra" => Some(Palette::Ultra),
+            _ => None,
+        }
+    }
+
+    fn stops(&self) -> &'static [GradientStop] {
+        match self {
+            Palette::Rainbow => &RAINBOW_STOPS,
+            Palette::Fire => &FIRE_STOPS,
+            Palette::Ocean => &OCEAN_STOPS,
+            Palette::Grayscale => &GRAYSCALE_STOPS,
+            Palette::Ultra => &ULTRA_STOPS,
+        }
+    }
+
+    // Color of in-set points
+    fn interior(&self) -> [f32; 3] {
+        match self {
+            Palette::Ocean => rgb(0, 4, 16),
+            _ => [0.0, 0.0, 0.0],
+        }
+    }
+}
+
+const fn rgb(r: u8, g: u8, b: u8) -> [f32; 3] {
+    [r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0]
+}
+
+// How a gradient blends from a stop to the next one
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Blend {
+    Rgb,    // Straight line between the colors, which greys out between complementary ones
+    Hsv,    // Hue the short way around the wheel, saturation and value linearly
+    Cubic,  // Catmull-Rom spline through the neighboring stops too, per channel
+}
+
+impl Blend {
+    pub fn from_name(name: &str) -> Option<Blend> {
+        match name.to_ascii_lowercase().as_str() {
+            "rgb" => Some(Blend::Rgb),
+            "hsv" => Some(Blend::Hsv),
+            "cubic" => Some(Blend::Cubic),
+            _ => None,
+        }
+    }
+}
+
+// A color at a position from 0 to 1 along a gradient, channels in 0..1, with
+// how the gradient blends on to the next stop
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct GradientStop {
+    pub position: f32,
+    pub color: [f32; 3],
+    pub blend: Blend,
+}
+
+const fn stop(position: f32, color: [f32; 3]) -> GradientStop {
+    GradientStop { position, color, blend: Blend::Rgb }
+}
+
+// Every 60 degrees of the hue wheel at full saturation and value, between
+// which the wheel is linear in RGB
+const RAINBOW_STOPS: [GradientStop; 7] = [
+    stop(0.0, [1.0, 0.0, 0.0]),
+    stop(1.0 / 6.0, [1.0, 1.0, 0.0]),
+    stop(2.0 / 6.0, [0.0, 1.0, 0.0]),
+    stop(3.0 / 6.0, [0.0, 1.0, 1.0]),
+    stop(4.0 / 6.0, [0.0, 0.0, 1.0]),
+    stop(5.0 / 6.0, [1.0, 0.0, 1.0]),
+    stop(1.0, [1.0, 0.0, 0.0]),
+];
+
+const FIRE_STOPS: [GradientStop; 5] = [
+    stop(0.0, rgb(0, 0, 0)),
+    stop(0.25, rgb(128, 0, 0)),
+    stop(0.5, rgb(255, 64, 0)),
+    stop(0.75, rgb(255, 200, 0)),
+    stop(1.0, rgb(255, 255, 255)),
+];
+
+const OCEAN_STOPS: [GradientStop; 5] = [
+    stop(0.0, rgb(0, 7, 30)),
+    stop(0.3, rgb(0, 60, 120)),
+    stop(0.6, rgb(0, 160, 200)),
+    stop(0.85, rgb(120, 230, 240)),
+    stop(1.0, rgb(255, 255, 255)),
+];
+
+const GRAYSCALE_STOPS: [GradientStop; 2] = [
+    stop(0.0, rgb(0, 0, 0)),
+    stop(1.0, rgb(255, 255, 255)),
+];
+
+// Ends on its first color so cycled repeats join up
+const ULTRA_STOPS: [GradientStop; 6] = [
+    stop(0.0, rgb(0, 7, 100)),
+    stop(0.16, rgb(32, 107, 203)),
+    stop(0.42, rgb(237, 255, 255)),
+    stop(0.6425, rgb(255, 170, 0)),
+    stop(0.8575, rgb(0, 2, 0)),
+    stop(1.0, rgb(0, 7, 100)),
+];
+
+// Darkest shade of the shaded angle style, reached by the fastest escapes
+const ANGLE_MIN_SHADE: f32 = 0.35;
+
+// Cost gradient for the heatmap, from cheap to expensive
+const HEATMAP_STOPS: [(f32, f32, f32); 5] = [
+    (0.0, 0.0, 0.0),
+    (0.0, 0.0, 1.0),
+    (1.0, 0.0, 0.0),
+    (1.0, 1.0, 0.0),
+    (1.0, 1.0, 1.0),
+];
+
+#[derive(Clone)]
 pub struct ColorHandler {
     saturation: f32,
     value: f32,
+    scheme: ColorScheme,
+    palette: Palette,
+    transparent_interior: bool,  // In-set points get alpha 0 rather than the scheme's color
+    cycle_length: Option<f32>,  // Iterations per repeat of the palette, None spreads one repeat over max_iterations
+    gradient: Option<Vec<GradientStop>>,  // Stops read from the palette file, replacing the palette
+    palette_file: Option<String>,
 }
 
 impl ColorHandler {
@@ -10,28 +190,264 @@ impl ColorHandler {
         ColorHandler {
             saturation: 1.0,
             value: 1.0,
+            scheme: ColorScheme::Hsv,
+            palette: Palette::Rainbow,
+            transparent_interior: false,
+            cycle_length: None,
+            gradient: None,
+            palette_file: None,
+        }
+    }
+
+    pub fn set_scheme(&mut self, scheme: ColorScheme) {
+        self.scheme = scheme;
+    }
+
+    pub fn scheme(&self) -> ColorScheme {
+        self.scheme
+    }
+
+    // Choosing a palette stops using a loaded palette file's gradient, which
+    // reload_palette_file brings back
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.palette = palette;
+        self.gradient = None;
+    }
+
+    pub fn selected_palette(&self) -> Palette {
+        self.palette
+    }
+
+    pub fn set_transparent_interior(&mut self, transparent_interior: bool) {
+        self.transparent_interior = transparent_interior;
+    }
+
+    pub fn transparent_interior(&self) -> bool {
+        self.transparent_interior
+    }
+
+    // Repeats the palette every so many iterations of the continuous count, so
+    // the bands keep their width whatever max_iterations is. The heatmap is a
+    // cost view and isn't cycled.
+    pub fn set_cycle_length(&mut self, cycle_length: Option<f32>) {
+        self.cycle_length = cycle_length.filter(|length| *length > 0.0);
+    }
+
+    pub fn cycle_length(&self) -> Option<f32> {
+        self.cycle_length
+    }
+
+    // Handler coloring escaping points from a gradient file, as
+    // load_palette_file reads them. An empty file leaves the default palette.
+    pub fn from_gradient_file(path: &str) -> io::Result<ColorHandler> {
+        let mut color_handler = ColorHandler::new();
+        let stops = Self::read_gradient_file(path)?;
+        if !stops.is_empty() {
+            color_handler.gradient = Some(stops);
+        }
+        color_handler.palette_file = Some(path.to_string());
+        Ok(color_handler)
+    }
+
+    // Colors escaping points from a gradient file of `position r g b` lines,
+    // positions running from 0 to 1 along the palette and channels from 0
+    // to 255, optionally followed by the blend on to the next stop, rgb (the
+    // default), hsv or cubic. If the file can't be read or parsed, say because an editor is
+    // halfway through saving it, the palette in use is kept.
+    pub fn load_palette_file(&mut self, path: &str) -> Result<()> {
+        let stops = Self::read_gradient_file(path)?;
+        if stops.is_empty() {
+            return Err(RustybrotError::Config(format!("{} has no color stops", path)));
+        }
+        self.gradient = Some(stops);
+        self.palette_file = Some(path.to_string());
+        Ok(())
+    }
+
+    // A gradient file's stops sorted by position, lines starting with `#`
+    // being comments
+    fn read_gradient_file(path: &str) -> io::Result<Vec<GradientStop>> {
+        let text = fs::read_to_string(path)?;
+        let mut stops = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let blend = match words[..] {
+                [_, _, _, _] => Some(Blend::Rgb),
+                [_, _, _, _, name] => Blend::from_name(name),
+                _ => None,
+            };
+            let values: Vec<f32> = words.iter().take(4).filter_map(|value| value.parse().ok()).collect();
+            match (&values[..], blend) {
+                (&[position, r, g, b], Some(blend)) if (0.0..=1.0).contains(&position) && [r, g, b].iter().all(|c| (0.0..=255.0).contains(c)) => {
+                    stops.push(GradientStop { position, color: [r / 255.0, g / 255.0, b / 255.0], blend });
+                }
+                _ => return Err(io::Error::new(io::ErrorKind::InvalidData, format!(
+                    "{} line {}: expected `position r g b [rgb|hsv|cubic]` with position 0..1 and channels 0..255, got {:?}", path, number + 1, line))),
+            }
+        }
+        stops.sort_by(|a, b| a.position.total_cmp(&b.position));
+        Ok(stops)
+    }
+
+    // Stops replacing the palette, sorted by position with channels in 0..1,
+    // such as a project file carries
+    pub fn set_gradient(&mut self, stops: Option<Vec<GradientStop>>) {
+        self.gradient = stops.filter(|stops| !stops.is_empty());
+    }
+
+    pub fn gradient(&self) -> Option<&[GradientStop]> {
+        self.gradient.as_deref()
+    }
+
+    // Reads the last loaded palette file again, to pick up edits made to it
+    pub fn reload_palette_file(&mut self) -> Result<()> {
+        let path = self.palette_file.clone()
+            .ok_or_else(|| RustybrotError::Config("No palette file loaded, pass --palette path".to_string()))?;
+        self.load_palette_file(&path)
+    }
+
+    pub fn palette_file(&self) -> Option<&str> {
+        self.palette_file.as_deref()
+    }
+
+    pub fn get_color(&self, iterations: f32, max_iterations: u32) -> SolidSource {
+        if self.transparent_interior && iterations >= max_iterations as f32 {
+            return SolidSource::from_unpremultiplied_argb(0, 0, 0, 0);
         }
+
+        let [r, g, b] = self.get_color_precise(iterations, max_iterations);
+        let channel = |value: f32| (value * 255.0) as u8;
+        SolidSource::from_unpremultiplied_argb(255, channel(r), channel(g), channel(b))
     }
 
-    pub fn get_color(&self, iterations: u32, max_iterations: u32) -> SolidSource {
-        if iterations == max_iterations {
-            // Point is in the set - color it black
-            SolidSource::from_unpremultiplied_argb(255, 0, 0, 0)
+    // Unquantized color with channels in 0..1, for high bit depth output
+    pub fn get_color_precise(&self, iterations: f32, max_iterations: u32) -> [f32; 3] {
+        let color = if self.scheme == ColorScheme::Heatmap {
+            Self::heatmap_color(iterations, max_iterations)
+        } else if iterations >= max_iterations as f32 {
+            self.palette.interior()
         } else {
             // Point is outside the set - create a color based on iterations
-            let hue = (iterations as f32 / max_iterations as f32) * 360.0;
-            let (r, g, b) = Self::hsv_to_rgb(hue, self.saturation, self.value);
-            SolidSource::from_unpremultiplied_argb(255, r, g, b)
+            let position = match self.cycle_length {
+                Some(length) => (iterations / length).rem_euclid(1.0),
+                None => iterations / max_iterations as f32,
+            };
+            let stops = self.gradient.as_deref().unwrap_or(self.palette.stops());
+            Self::gradient_color(stops, position)
+        };
+
+        // Debug builds panic on non-finite channels, release builds clamp them to black
+        debug_assert!(
+            color.iter().all(|channel| channel.is_finite()),
+            "Non-finite color {:?} for iteration value {} of {}", color, iterations, max_iterations
+        );
+        color.map(|channel| if channel.is_finite() { channel } else { 0.0 })
+    }
+
+    // Color of a point escaping at the given angle in turns, in-set points keep
+    // the interior color of get_color
+    pub fn get_angle_color(&self, angle: f32, iterations: f32, max_iterations: u32, style: AngleStyle) -> SolidSource {
+        if iterations >= max_iterations as f32 {
+            return self.get_color(iterations, max_iterations);
         }
+
+        let shade = match style {
+            AngleStyle::Hue => 1.0,
+            AngleStyle::Shaded => {
+                let depth = iterations.max(0.0).ln_1p() / (max_iterations.max(1) as f32).ln_1p();
+                ANGLE_MIN_SHADE + (1.0 - ANGLE_MIN_SHADE) * depth
+            }
+        };
+        let [r, g, b] = Self::hsv_to_rgb(angle.rem_euclid(1.0) * 360.0, self.saturation, self.value * shade);
+        let channel = |value: f32| (value * 255.0) as u8;
+        SolidSource::from_unpremultiplied_argb(255, channel(r), channel(g), channel(b))
     }
 
-    fn hsv_to_rgb(h: f32, s: f32, v: f32) -> (u8, u8, u8) {
+    // The scheme sampled at 256 evenly spaced iteration counts, the last one in the set
+    pub fn palette(&self) -> Vec<[u8; 3]> {
+        (0..256)
+            .map(|i| {
+                let color = self.get_color(i as f32, 255);
+                [color.r, color.g, color.b]
+            })
+            .collect()
+    }
+
+    // Blend between the stops either side of the position, as the first of
+    // them says, the end stops' colors extending to the ends of the palette
+    fn gradient_color(stops: &[GradientStop], position: f32) -> [f32; 3] {
+        let next = stops.partition_point(|stop| stop.position <= position);
+        match (next.checked_sub(1).map(|i| stops[i]), stops.get(next)) {
+            (Some(from), Some(to)) => {
+                let t = (position - from.position) / (to.position - from.position);
+                match from.blend {
+                    Blend::Rgb => std::array::from_fn(|channel| from.color[channel] + (to.color[channel] - from.color[channel]) * t),
+                    Blend::Hsv => {
+                        let [h1, s1, v1] = Self::rgb_to_hsv(from.color);
+                        let [h2, s2, v2] = Self::rgb_to_hsv(to.color);
+                        // Hue turns through at most half the wheel
+                        let turn = (h2 - h1 + 180.0).rem_euclid(360.0) - 180.0;
+                        Self::hsv_to_rgb((h1 + turn * t).rem_euclid(360.0), s1 + (s2 - s1) * t, v1 + (v2 - v1) * t)
+                    }
+                    Blend::Cubic => {
+                        // The end stops stand in for the missing neighbors
+                        let before = stops[next.saturating_sub(2)].color;
+                        let after = stops[(next + 1).min(stops.len() - 1)].color;
+                        std::array::from_fn(|channel| {
+                            let (p0, p1, p2, p3) = (before[channel], from.color[channel], to.color[channel], after[channel]);
+                            let value = 0.5 * (2.0 * p1 + (p2 - p0) * t + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t * t
+                                + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t * t * t);
+                            value.clamp(0.0, 1.0)
+                        })
+                    }
+                }
+            }
+            (Some(stop), None) | (None, Some(&stop)) => stop.color,
+            (None, None) => [0.0; 3],
+        }
+    }
+
+    fn heatmap_color(iterations: f32, max_iterations: u32) -> [f32; 3] {
+        // Log scale so the cheap exterior isn't crushed into a single color
+        let cost = iterations.ln_1p() / (max_iterations.max(1) as f32).ln_1p();
+        let position = cost.clamp(0.0, 1.0) * (HEATMAP_STOPS.len() - 1) as f32;
+        let index = (position as usize).min(HEATMAP_STOPS.len() - 2);
+        let t = position - index as f32;
+
+        let (r1, g1, b1) = HEATMAP_STOPS[index];
+        let (r2, g2, b2) = HEATMAP_STOPS[index + 1];
+        let channel = |a: f32, b: f32| a + (b - a) * t;
+        [channel(r1, r2), channel(g1, g2), channel(b1, b2)]
+    }
+
+    // Hue in degrees, saturation and value in 0..1
+    fn rgb_to_hsv([r, g, b]: [f32; 3]) -> [f32; 3] {
+        let max = r.max(g).max(b);
+        let chroma = max - r.min(g).min(b);
+        let hue = if chroma == 0.0 {
+            0.0
+        } else if max == r {
+            60.0 * ((g - b) / chroma).rem_euclid(6.0)
+        } else if max == g {
+            60.0 * ((b - r) / chroma + 2.0)
+        } else {
+            60.0 * ((r - g) / chroma + 4.0)
+        };
+        [hue, if max == 0.0 { 0.0 } else { chroma / max }, max]
+    }
+
+    fn hsv_to_rgb(h: f32, s: f32, v: f32) -> [f32; 3] {
         let c = v * s;
         let h_prime = h / 60.0;
         let x = c * (1.0 - (h_prime % 2.0 - 1.0).abs());
         let m = v - c;
 
-        let (r, g, b) = match h_prime as i32 {
+        // A full turn, 360 degrees, is red again
+        let (r, g, b) = match h_prime as i32 % 6 {
             0 => (c, x, 0.0),
             1 => (x, c, 0.0),
             2 => (0.0, c, x),
@@ -41,10 +457,95 @@ impl ColorHandler {
             _ => (0.0, 0.0, 0.0),
         };
 
-        (
-            ((r + m) * 255.0) as u8,
-            ((g + m) * 255.0) as u8,
-            ((b + m) * 255.0) as u8,
-        )
+        [r + m, g + m, b + m]
     }
-} 
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blue_to_yellow(blend: Blend) -> [f32; 3] {
+        let stops = [GradientStop { position: 0.0, color: [0.0, 0.0, 1.0], blend }, stop(1.0, [1.0, 1.0, 0.0])];
+        ColorHandler::gradient_color(&stops, 0.5)
+    }
+
+    #[test]
+    fn hsv_blends_keep_complementary_midpoints_saturated() {
+        // RGB meets in the middle at grey
+        let rgb_midpoint = blue_to_yellow(Blend::Rgb);
+        assert_eq!(rgb_midpoint, [0.5, 0.5, 0.5]);
+        assert_eq!(ColorHandler::rgb_to_hsv(rgb_midpoint)[1], 0.0);
+
+        // HSV turns the hue halfway round at full saturation and value
+        let [hue, saturation, value] = ColorHandler::rgb_to_hsv(blue_to_yellow(Blend::Hsv));
+        assert!((hue - 150.0).abs() < 1e-3 || (hue - 330.0).abs() < 1e-3, "hue {}", hue);
+        assert_eq!((saturation, value), (1.0, 1.0));
+    }
+
+    #[test]
+    fn cubic_blends_pass_through_their_stops() {
+        let stops = [
+            GradientStop { position: 0.0, color: [0.0, 0.0, 0.0], blend: Blend::Cubic },
+            GradientStop { position: 0.5, color: [1.0, 0.5, 0.0], blend: Blend::Cubic },
+            stop(1.0, [1.0, 1.0, 1.0]),
+        ];
+        assert_eq!(ColorHandler::gradient_color(&stops, 0.0), [0.0, 0.0, 0.0]);
+        assert_eq!(ColorHandler::gradient_color(&stops, 0.5), [1.0, 0.5, 0.0]);
+        let between = ColorHandler::gradient_color(&stops, 0.25);
+        assert!(between.iter().all(|channel| (0.0..=1.0).contains(channel)));
+        assert_ne!(between, ColorHandler::gradient_color(&[stop(0.0, [0.0; 3]), stop(0.5, [1.0, 0.5, 0.0])], 0.25));
+    }
+
+    #[test]
+    fn gradient_files_read_each_stops_blend() {
+        let path = std::env::temp_dir().join(format!("rustybrot-gradient-{}.txt", std::process::id()));
+        let path = path.to_str().unwrap();
+        fs::write(path, "# blue to yellow\n0 0 0 255 hsv\n1 255 255 0\n").unwrap();
+        let stops = ColorHandler::read_gradient_file(path);
+        fs::write(path, "0 0 0 255 spline\n").unwrap();
+        let unknown = ColorHandler::read_gradient_file(path);
+        fs::remove_file(path).unwrap();
+
+        let stops = stops.unwrap();
+        assert_eq!((stops[0].blend, stops[1].blend), (Blend::Hsv, Blend::Rgb));
+        assert!(unknown.is_err());
+    }
+
+    #[test]
+    fn gradient_files_round_trip() {
+        let path = std::env::temp_dir().join(format!("rustybrot-round-trip-{}.grad", std::process::id()));
+        let path = path.to_str().unwrap();
+        let stops = [stop(0.0, rgb(20, 8, 60)), stop(0.5, rgb(230, 80, 90)), stop(1.0, rgb(255, 240, 200))];
+        // Written out of order, with the channels back in 0..255
+        let lines: Vec<String> = [2, 0, 1].iter()
+            .map(|&i| format!("{} {}", stops[i].position, stops[i].color.map(|channel| (channel * 255.0).round().to_string()).join(" ")))
+            .collect();
+        fs::write(path, lines.join("\n")).unwrap();
+        let loaded = ColorHandler::from_gradient_file(path);
+        fs::write(path, "0.5 255 0\n").unwrap();
+        let invalid = ColorHandler::from_gradient_file(path);
+        fs::write(path, "# nothing but a comment\n").unwrap();
+        let empty = ColorHandler::from_gradient_file(path);
+        fs::remove_file(path).unwrap();
+
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded.gradient(), Some(&stops[..]));
+        assert_eq!(loaded.palette_file(), Some(path));
+        assert_eq!(loaded.get_color_precise(50.0, 100), stops[1].color);
+
+        let error = invalid.err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().contains("line 1"), "{}", error);
+
+        let empty = empty.unwrap();
+        assert_eq!(empty.gradient(), None);
+        assert_eq!(empty.palette(), ColorHandler::new().palette());
+    }
+
+    #[test]
+    fn the_sample_gradient_file_loads() {
+        let sunset = ColorHandler::from_gradient_file(concat!(env!("CARGO_MANIFEST_DIR"), "/palettes/sunset.grad")).unwrap();
+        assert_eq!(sunset.gradient().map(|stops| stops.len()), Some(5));
+    }
+}
@@ -0,0 +1,62 @@
+// Connected regions of the in-set mask, such as the main cardioid and the
+// satellite bulbs visible in a view
+#[derive(Clone, Copy, Debug)]
+pub struct Component {
+    pub min_x: usize,
+    pub min_y: usize,
+    pub max_x: usize,  // Inclusive
+    pub max_y: usize,  // Inclusive
+    pub area: usize,   // Pixel count
+}
+
+// Flood fills every 4-connected region of set pixels, largest first. Bulbs
+// only touch their parent at a single point, which rarely survives as a
+// 4-connected path at pixel resolution, so they come out as separate components.
+pub fn find_components(mask: &[bool], width: usize, height: usize) -> Vec<Component> {
+    let mut visited = vec![false; mask.len()];
+    let mut components = Vec::new();
+    let mut stack = Vec::new();
+
+    for start in 0..width * height {
+        if !mask[start] || visited[start] {
+            continue;
+        }
+
+        visited[start] = true;
+        stack.push(start);
+        let mut component = Component {
+            min_x: start % width,
+            min_y: start / width,
+            max_x: start % width,
+            max_y: start / width,
+            area: 0,
+        };
+
+        while let Some(index) = stack.pop() {
+            let (x, y) = (index % width, index / width);
+            component.min_x = component.min_x.min(x);
+            component.min_y = component.min_y.min(y);
+            component.max_x = component.max_x.max(x);
+            component.max_y = component.max_y.max(y);
+            component.area += 1;
+
+            let neighbors = [
+                (x > 0).then(|| index - 1),
+                (x + 1 < width).then(|| index + 1),
+                (y > 0).then(|| index - width),
+                (y + 1 < height).then(|| index + width),
+            ];
+            for neighbor in neighbors.into_iter().flatten() {
+                if mask[neighbor] && !visited[neighbor] {
+                    visited[neighbor] = true;
+                    stack.push(neighbor);
+                }
+            }
+        }
+
+        components.push(component);
+    }
+
+    components.sort_by_key(|component| std::cmp::Reverse(component.area));
+    components
+}
@@ -0,0 +1,173 @@
+// Per-channel tone curves for grading finished frames, like a photo editor's
+// curves tool. Each curve maps input intensity to output intensity, both in
+// 0..1, through control points joined by a monotone cubic spline, so tones
+// stay in order between the points instead of overshooting them.
+#[derive(Clone, PartialEq, Debug)]
+pub struct ColorCurves {
+    pub red: Vec<(f32, f32)>,
+    pub green: Vec<(f32, f32)>,
+    pub blue: Vec<(f32, f32)>,
+}
+
+// Named gradings for the command line and the viewer's curves key
+pub const CURVE_PRESETS: [&str; 3] = ["warm", "cool", "contrast"];
+
+impl Default for ColorCurves {
+    // Identity, every channel passing through unchanged
+    fn default() -> Self {
+        let identity = vec![(0.0, 0.0), (1.0, 1.0)];
+        ColorCurves { red: identity.clone(), green: identity.clone(), blue: identity }
+    }
+}
+
+impl ColorCurves {
+    pub fn from_preset(name: &str) -> Option<ColorCurves> {
+        let identity = ColorCurves::default();
+        match name.to_lowercase().as_str() {
+            "identity" => Some(identity),
+            "warm" => Some(ColorCurves {
+                red: vec![(0.0, 0.0), (0.5, 0.56), (1.0, 1.0)],
+                blue: vec![(0.0, 0.0), (0.5, 0.44), (1.0, 0.94)],
+                ..identity
+            }),
+            "cool" => Some(ColorCurves {
+                red: vec![(0.0, 0.0), (0.5, 0.44), (1.0, 0.94)],
+                blue: vec![(0.0, 0.0), (0.5, 0.56), (1.0, 1.0)],
+                ..identity
+            }),
+            "contrast" => {
+                let s_curve = vec![(0.0, 0.0), (0.25, 0.18), (0.75, 0.82), (1.0, 1.0)];
+                Some(ColorCurves { red: s_curve.clone(), green: s_curve.clone(), blue: s_curve })
+            }
+            _ => None,
+        }
+    }
+
+    // A preset name, or `channel=x:y,x:y,...` curves separated by semicolons,
+    // where the channel is r, g, b or rgb for all three. Channels left out
+    // keep the identity curve.
+    pub fn parse(spec: &str) -> Option<ColorCurves> {
+        if let Some(curves) = Self::from_preset(spec) {
+            return Some(curves);
+        }
+
+        let mut curves = ColorCurves::default();
+        for channel_spec in spec.split(';').filter(|part| !part.trim().is_empty()) {
+            let (channel, points) = channel_spec.split_once('=')?;
+            let points = points.split(',')
+                .map(|point| {
+                    let (x, y) = point.split_once(':')?;
+                    let (x, y): (f32, f32) = (x.trim().parse().ok()?, y.trim().parse().ok()?);
+                    ((0.0..=1.0).contains(&x) && (0.0..=1.0).contains(&y)).then_some((x, y))
+                })
+                .collect::<Option<Vec<_>>>()?;
+            if points.len() < 2 {
+                return None;
+            }
+
+            match channel.trim().to_lowercase().as_str() {
+                "r" => curves.red = points,
+                "g" => curves.green = points,
+                "b" => curves.blue = points,
+                "rgb" => {
+                    curves.red = points.clone();
+                    curves.green = points.clone();
+                    curves.blue = points;
+                }
+                _ => return None,
+            }
+        }
+        Some(curves)
+    }
+
+    // Inverse of parse, listing every channel's points
+    pub fn to_spec(&self) -> String {
+        let points = |points: &[(f32, f32)]| points.iter().map(|(x, y)| format!("{}:{}", x, y)).collect::<Vec<_>>().join(",");
+        format!("r={};g={};b={}", points(&self.red), points(&self.green), points(&self.blue))
+    }
+
+    pub fn is_identity(&self) -> bool {
+        self.lookup_tables().iter().all(|table| table.iter().enumerate().all(|(i, &value)| value as usize == i))
+    }
+
+    // Output intensity of each channel for every 8-bit input, which is how
+    // frames are graded
+    pub fn lookup_tables(&self) -> [[u8; 256]; 3] {
+        [&self.red, &self.green, &self.blue].map(|points| {
+            let spline = Spline::new(points);
+            std::array::from_fn(|i| (spline.evaluate(i as f32 / 255.0) * 255.0).round() as u8)
+        })
+    }
+
+    // Grades RGB colors with channels in 0..1 at full precision, for high bit
+    // depth output
+    pub fn precise_grader(&self) -> impl Fn([f32; 3]) -> [f32; 3] + Sync {
+        let splines = [&self.red, &self.green, &self.blue].map(|points| Spline::new(points));
+        move |color| std::array::from_fn(|channel| splines[channel].evaluate(color[channel]))
+    }
+}
+
+// Monotone cubic Hermite spline through control points, with Fritsch-Carlson
+// tangents. Inputs outside the points take the nearest end's value.
+struct Spline {
+    points: Vec<(f32, f32)>,
+    tangents: Vec<f32>,
+}
+
+impl Spline {
+    fn new(points: &[(f32, f32)]) -> Spline {
+        let mut points = points.to_vec();
+        points.sort_by(|a, b| a.0.total_cmp(&b.0));
+        points.dedup_by(|b, a| a.0 == b.0);
+
+        let secants: Vec<f32> = points.windows(2)
+            .map(|pair| (pair[1].1 - pair[0].1) / (pair[1].0 - pair[0].0))
+            .collect();
+        let mut tangents: Vec<f32> = (0..points.len())
+            .map(|i| match (i.checked_sub(1).map(|j| secants[j]), secants.get(i).copied()) {
+                (Some(before), Some(after)) if before * after > 0.0 => (before + after) / 2.0,
+                (Some(_), Some(_)) => 0.0,
+                (Some(slope), None) | (None, Some(slope)) => slope,
+                (None, None) => 0.0,
+            })
+            .collect();
+        // Scale tangents down where they would carry the curve past a point
+        for (i, &secant) in secants.iter().enumerate() {
+            if secant == 0.0 {
+                tangents[i] = 0.0;
+                tangents[i + 1] = 0.0;
+                continue;
+            }
+            let (alpha, beta) = (tangents[i] / secant, tangents[i + 1] / secant);
+            let length = (alpha * alpha + beta * beta).sqrt();
+            if length > 3.0 {
+                tangents[i] = 3.0 * alpha / length * secant;
+                tangents[i + 1] = 3.0 * beta / length * secant;
+            }
+        }
+        Spline { points, tangents }
+    }
+
+    fn evaluate(&self, x: f32) -> f32 {
+        let (Some(first), Some(last)) = (self.points.first(), self.points.last()) else {
+            return x;
+        };
+        if x <= first.0 {
+            return first.1;
+        }
+        if x >= last.0 {
+            return last.1;
+        }
+
+        let i = self.points.partition_point(|point| point.0 <= x) - 1;
+        let ((x0, y0), (x1, y1)) = (self.points[i], self.points[i + 1]);
+        let h = x1 - x0;
+        let t = (x - x0) / h;
+        let (t2, t3) = (t * t, t * t * t);
+        let y = (2.0 * t3 - 3.0 * t2 + 1.0) * y0
+            + (t3 - 2.0 * t2 + t) * h * self.tangents[i]
+            + (-2.0 * t3 + 3.0 * t2) * y1
+            + (t3 - t2) * h * self.tangents[i + 1];
+        y.clamp(0.0, 1.0)
+    }
+}
@@ -0,0 +1,75 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use crate::key_value;
+use crate::mandelbrot::RenderError;
+
+pub type Result<T> = std::result::Result<T, RustybrotError>;
+
+// Everything that can go wrong rendering, writing or setting up a render, so
+// callers handle failures as values instead of the crate panicking
+#[derive(Debug)]
+pub enum RustybrotError {
+    Render(RenderError),
+    Encoding(Box<dyn Error + Send + Sync>),  // An image, animation or saved buffer couldn't be encoded or decoded
+    Io(io::Error),
+    Config(String),  // Invalid options, view, location or project contents
+}
+
+impl fmt::Display for RustybrotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RustybrotError::Render(error) => write!(f, "{}", error),
+            RustybrotError::Encoding(error) => write!(f, "{}", error),
+            RustybrotError::Io(error) => write!(f, "{}", error),
+            RustybrotError::Config(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl Error for RustybrotError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            RustybrotError::Render(error) => Some(error),
+            RustybrotError::Encoding(error) => Some(error.as_ref()),
+            RustybrotError::Io(error) => Some(error),
+            RustybrotError::Config(_) => None,
+        }
+    }
+}
+
+impl From<RenderError> for RustybrotError {
+    fn from(error: RenderError) -> Self {
+        RustybrotError::Render(error)
+    }
+}
+
+impl From<io::Error> for RustybrotError {
+    fn from(error: io::Error) -> Self {
+        RustybrotError::Io(error)
+    }
+}
+
+impl From<key_value::Error> for RustybrotError {
+    fn from(error: key_value::Error) -> Self {
+        RustybrotError::Config(error.to_string())
+    }
+}
+
+impl From<gif::EncodingError> for RustybrotError {
+    fn from(error: gif::EncodingError) -> Self {
+        RustybrotError::Encoding(Box::new(error))
+    }
+}
+
+impl From<png::EncodingError> for RustybrotError {
+    fn from(error: png::EncodingError) -> Self {
+        RustybrotError::Encoding(Box::new(error))
+    }
+}
+
+impl From<exr::error::Error> for RustybrotError {
+    fn from(error: exr::error::Error) -> Self {
+        RustybrotError::Encoding(Box::new(error))
+    }
+}
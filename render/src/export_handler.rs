@@ -0,0 +1,196 @@
+use std::fs::File;
+use std::io::BufWriter;
+use raqote::DrawTarget;
+use crate::error::{Result, RustybrotError};
+use crate::pixel_format::{argb_u32_to_rgba8, unpremultiply, AlphaMode};
+
+// Largest change sharpening may make to a channel, so the black interior
+// doesn't pick up bright halos along the set boundary
+const MAX_SHARPEN_DELTA: f32 = 48.0;
+
+#[derive(Clone, Copy)]
+pub struct Sharpen {
+    pub radius: f32,  // Gaussian sigma of the blur, in pixels
+    pub amount: f32,  // Strength of the unsharp mask, 0 leaves the image unchanged
+}
+
+pub struct ExportHandler {
+    width: u32,
+    height: u32,
+    sharpen: Option<Sharpen>,
+    alpha_mode: AlphaMode,
+}
+
+impl ExportHandler {
+    pub fn new(width: u32, height: u32) -> Self {
+        ExportHandler {
+            width,
+            height,
+            sharpen: None,
+            alpha_mode: AlphaMode::Straight,
+        }
+    }
+
+    pub fn set_sharpen(&mut self, sharpen: Option<Sharpen>) {
+        self.sharpen = sharpen;
+    }
+
+    pub fn sharpen(&self) -> Option<Sharpen> {
+        self.sharpen
+    }
+
+    pub fn set_alpha_mode(&mut self, alpha_mode: AlphaMode) {
+        self.alpha_mode = alpha_mode;
+    }
+
+    // Written as RGB unless some pixels are translucent, such as a transparent
+    // interior, in which case an alpha channel is added
+    pub fn export_png(&self, draw_target: &DrawTarget, filename: &str) -> Result<()> {
+        self.export_rgba8(&argb_u32_to_rgba8(draw_target.get_data()), filename)
+    }
+
+    // As export_png, for premultiplied RGBA8 pixels such as a thumbnail's
+    pub fn export_rgba8(&self, pixels: &[u8], filename: &str) -> Result<()> {
+        let opaque = pixels.chunks_exact(4).all(|rgba| rgba[3] == 255);
+        let channels = if opaque { 3 } else { 4 };
+
+        // The draw target is premultiplied, opaque pixels are the same either way
+        let alpha_mode = self.alpha_mode;
+        let values = pixels.chunks_exact(4)
+            .flat_map(|rgba| {
+                let rgba = match alpha_mode {
+                    AlphaMode::Straight => unpremultiply(rgba),
+                    AlphaMode::Premultiplied => [rgba[0], rgba[1], rgba[2], rgba[3]],
+                };
+                rgba.map(|channel| channel as f32).into_iter().take(channels)
+            })
+            .collect();
+
+        self.write_png(values, channels, png::BitDepth::Eight, filename)
+    }
+
+    // 16 bits per channel export of colors with channels in 0..1, avoiding the
+    // banding of 8-bit output on smooth gradients
+    pub fn export_png16(&self, colors: &[[f32; 3]], filename: &str) -> Result<()> {
+        let values = colors.iter().flatten().map(|channel| channel * 255.0).collect();
+        self.write_png(values, 3, png::BitDepth::Sixteen, filename)
+    }
+
+    // Writes RGB or RGBA channel values on a 0..255 scale at the given bit depth
+    fn write_png(&self, mut values: Vec<f32>, channels: usize, depth: png::BitDepth, filename: &str) -> Result<()> {
+        if values.len() != (self.width * self.height) as usize * channels {
+            return Err(RustybrotError::Config("Image size doesn't match the export dimensions".to_string()));
+        }
+
+        if let Some(sharpen) = self.sharpen {
+            self.unsharp_mask(&mut values, channels, sharpen);
+        }
+
+        let buffer: Vec<u8> = match depth {
+            png::BitDepth::Sixteen => values.iter()
+                .flat_map(|&value| ((value.clamp(0.0, 255.0) / 255.0 * 65535.0).round() as u16).to_be_bytes())
+                .collect(),
+            _ => values.iter().map(|&value| value.clamp(0.0, 255.0) as u8).collect(),
+        };
+
+        let file = File::create(filename)?;
+        let mut encoder = png::Encoder::new(BufWriter::new(file), self.width, self.height);
+        encoder.set_color(if channels == 4 { png::ColorType::Rgba } else { png::ColorType::Rgb });
+        encoder.set_depth(depth);
+
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&buffer)?;
+
+        Ok(())
+    }
+
+    // Sharpens the color channels of pixels `channels` values apart, leaving any alpha as is
+    fn unsharp_mask(&self, values: &mut [f32], channels: usize, sharpen: Sharpen) {
+        let width = self.width as usize;
+        let height = self.height as usize;
+
+        // Normalized 1D Gaussian kernel, applied horizontally then vertically
+        let sigma = sharpen.radius.max(0.1);
+        let reach = (sigma * 3.0).ceil() as isize;
+        let mut kernel: Vec<f32> = (-reach..=reach)
+            .map(|i| (-(i * i) as f32 / (2.0 * sigma * sigma)).exp())
+            .collect();
+        let total: f32 = kernel.iter().sum();
+        kernel.iter_mut().for_each(|weight| *weight /= total);
+
+        let blur_pass = |source: &[f32], horizontal: bool| -> Vec<f32> {
+            let mut blurred = vec![0.0; source.len()];
+            for y in 0..height {
+                for x in 0..width {
+                    for channel in 0..3 {
+                        let mut sum = 0.0;
+                        for (k, weight) in kernel.iter().enumerate() {
+                            let offset = k as isize - reach;
+                            let (sx, sy) = if horizontal {
+                                ((x as isize + offset).clamp(0, width as isize - 1) as usize, y)
+                            } else {
+                                (x, (y as isize + offset).clamp(0, height as isize - 1) as usize)
+                            };
+                            sum += source[(sy * width + sx) * channels + channel] * weight;
+                        }
+                        blurred[(y * width + x) * channels + channel] = sum;
+                    }
+                }
+            }
+            blurred
+        };
+
+        let blurred = blur_pass(&blur_pass(values, true), false);
+
+        for (value, blurred) in values.iter_mut().zip(blurred).enumerate().filter(|(i, _)| i % channels < 3).map(|(_, pair)| pair) {
+            let delta = ((*value - blurred) * sharpen.amount)
+                .clamp(-MAX_SHARPEN_DELTA, MAX_SHARPEN_DELTA);
+            *value = (*value + delta).clamp(0.0, 255.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame_handler::FrameHandler;
+
+    // RGBA bytes of a PNG file
+    fn read_png(filename: &str) -> Vec<u8> {
+        let mut reader = png::Decoder::new(File::open(filename).unwrap()).read_info().unwrap();
+        let mut bytes = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut bytes).unwrap();
+        assert_eq!(info.color_type, png::ColorType::Rgba);
+        bytes.truncate(info.buffer_size());
+        bytes
+    }
+
+    #[test]
+    fn semi_transparent_pixels_export_in_either_alpha_convention() {
+        // Half the samples in the transparent interior, half escaping, so the pixel is half covered
+        let mut frame_handler = FrameHandler::new(1, 1);
+        frame_handler.color_handler_mut().set_transparent_interior(true);
+        frame_handler.render_supersampled(&[vec![100.0], vec![30.0]], 100);
+        let straight = frame_handler.color_handler().get_color(30.0, 100);
+        let straight = [straight.r, straight.g, straight.b];
+
+        let filename = std::env::temp_dir().join(format!("rustybrot-alpha-{}.png", std::process::id()));
+        let filename = filename.to_str().unwrap();
+        let mut export_handler = ExportHandler::new(1, 1);
+        for alpha_mode in [AlphaMode::Straight, AlphaMode::Premultiplied] {
+            export_handler.set_alpha_mode(alpha_mode);
+            export_handler.export_png(frame_handler.get_draw_target(), filename).unwrap();
+            let rgba = read_png(filename);
+            assert!((127..=128).contains(&rgba[3]), "alpha {}", rgba[3]);
+
+            for (channel, straight) in rgba[..3].iter().zip(straight) {
+                let expected = match alpha_mode {
+                    AlphaMode::Straight => straight as f32,
+                    AlphaMode::Premultiplied => straight as f32 * rgba[3] as f32 / 255.0,
+                };
+                assert!((*channel as f32 - expected).abs() <= 2.0, "{:?} exported {:?} for {:?}", alpha_mode, rgba, straight);
+            }
+        }
+        std::fs::remove_file(filename).unwrap();
+    }
+}
@@ -0,0 +1,66 @@
+use ::exr::prelude::{AnyChannel, AnyChannels, Encoding, FlatSamples, Image, Layer, LayerAttributes, WritableImage};
+use crate::error::{Result, RustybrotError};
+
+// Writes named f32 fields of the same size as the channels of an
+// uncompressed scanline OpenEXR image, for grading and compositing raw
+// values in VFX tools rather than a tonemapped PNG. EXR lists channels by
+// name, so they're sorted.
+pub fn write_exr(path: &str, width: u32, height: u32, channels: &[(&str, &[f32])]) -> Result<()> {
+    let pixels = width as usize * height as usize;
+    if width == 0 || height == 0 || channels.iter().any(|(_, values)| values.len() != pixels) {
+        return Err(RustybrotError::Config(format!("Every EXR channel needs {}x{} values", width, height)));
+    }
+
+    let channels = channels.iter()
+        .map(|(name, values)| AnyChannel::new(*name, FlatSamples::F32(values.to_vec())))
+        .collect();
+    let layer = Layer::new((width as usize, height as usize), LayerAttributes::default(), Encoding::UNCOMPRESSED, AnyChannels::sort(channels));
+    Ok(Image::from_layer(layer).write().to_file(path)?)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use ::exr::prelude::{read, ReadChannels, ReadLayers};
+    use std::collections::HashMap;
+    use std::fs;
+    use std::io::Cursor;
+
+    // Reads every channel of an EXR file's first layer with the exr crate's
+    // own reader, as f32 values row by row
+    pub(crate) fn read_exr(bytes: &[u8]) -> (u32, u32, HashMap<String, Vec<f32>>) {
+        let image = read().no_deep_data().largest_resolution_level().all_channels().first_valid_layer().all_attributes()
+            .from_buffered(Cursor::new(bytes))
+            .unwrap();
+        let layer = image.layer_data;
+        let channels = layer.channel_data.list.into_iter()
+            .map(|channel| (channel.name.to_string(), channel.sample_data.values_as_f32().collect()))
+            .collect();
+        (layer.size.width() as u32, layer.size.height() as u32, channels)
+    }
+
+    #[test]
+    fn written_channels_read_back() {
+        let iterations: Vec<f32> = (0..12).map(|i| i as f32 * 1.5).collect();
+        let distance: Vec<f32> = (0..12).map(|i| 1.0 / (i + 1) as f32).collect();
+        let angle = vec![0.25; 12];
+        let path = std::env::temp_dir().join(format!("rustybrot-exr-{}.exr", std::process::id()));
+        let path = path.to_str().unwrap();
+        write_exr(path, 4, 3, &[("iterations", &iterations), ("distance", &distance), ("angle", &angle)]).unwrap();
+        let bytes = fs::read(path).unwrap();
+        fs::remove_file(path).unwrap();
+
+        let (width, height, channels) = read_exr(&bytes);
+        assert_eq!((width, height, channels.len()), (4, 3, 3));
+        assert_eq!(channels["iterations"], iterations);
+        assert_eq!(channels["distance"], distance);
+        assert_eq!(channels["angle"], angle);
+    }
+
+    #[test]
+    fn channels_of_the_wrong_size_are_refused() {
+        let path = std::env::temp_dir().join(format!("rustybrot-exr-refused-{}.exr", std::process::id()));
+        assert!(write_exr(path.to_str().unwrap(), 4, 3, &[("iterations", &[0.0; 11])]).is_err());
+        assert!(!path.exists());
+    }
+}
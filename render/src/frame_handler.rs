@@ -1,12 +1,177 @@
+use std::borrow::Cow;
+use std::collections::VecDeque;
 use raqote::*;
-use crate::color_handler::ColorHandler;
+use crate::color_handler::{AngleStyle, ColorHandler, ColorScheme};
+use crate::curves::ColorCurves;
+use crate::error::Result;
+use crate::mandelbrot::{is_letterbox_bar, MandelbrotFrame, PrecisionHeadroom};
+use crate::pixel_format::{argb_u32_to_rgba8, rgba8_to_argb_u32, unpremultiply};
+use crate::temporal::TemporalAccumulator;
 use rayon::prelude::*;
 
+// Target distance between gridlines, in pixels
+const GRID_TARGET_SPACING: f64 = 120.0;
+
+// 3x5 bitmap glyphs for grid labels, one row per entry with the left column as the high bit
+const GLYPH_SCALE: f32 = 2.0;
+const GLYPHS: [(char, [u8; 5]); 16] = [
+    ('0', [0b111, 0b101, 0b101, 0b101, 0b111]),
+    ('1', [0b010, 0b110, 0b010, 0b010, 0b111]),
+    ('2', [0b111, 0b001, 0b111, 0b100, 0b111]),
+    ('3', [0b111, 0b001, 0b111, 0b001, 0b111]),
+    ('4', [0b101, 0b101, 0b111, 0b001, 0b001]),
+    ('5', [0b111, 0b100, 0b111, 0b001, 0b111]),
+    ('6', [0b111, 0b100, 0b111, 0b101, 0b111]),
+    ('7', [0b111, 0b001, 0b001, 0b001, 0b001]),
+    ('8', [0b111, 0b101, 0b111, 0b101, 0b111]),
+    ('9', [0b111, 0b101, 0b111, 0b001, 0b111]),
+    ('.', [0b000, 0b000, 0b000, 0b000, 0b010]),
+    ('-', [0b000, 0b000, 0b111, 0b000, 0b000]),
+    ('+', [0b000, 0b010, 0b111, 0b010, 0b000]),
+    ('e', [0b000, 0b111, 0b111, 0b100, 0b111]),
+    ('i', [0b010, 0b000, 0b010, 0b010, 0b010]),
+    ('p', [0b000, 0b111, 0b101, 0b111, 0b100]),
+];
+
+// Size of each precision gauge bar and its margin from the bottom-right corner
+const GAUGE_WIDTH: f32 = 120.0;
+const GAUGE_HEIGHT: f32 = 6.0;
+const GAUGE_MARGIN: f32 = 8.0;
+
+// Size of the iteration histogram overlay in the bottom-left corner, and its bin count
+const HISTOGRAM_WIDTH: f32 = 192.0;
+const HISTOGRAM_HEIGHT: f32 = 64.0;
+pub const HISTOGRAM_BINS: usize = 64;
+
+// Longer side of a preview thumbnail, in pixels
+pub const THUMBNAIL_SIZE: u32 = 64;
+
+// Estimated distance from the set, in pixels, within which distance estimate
+// coloring darkens the exterior toward black
+const DISTANCE_SHADE_PIXELS: f32 = 2.0;
+
+// Low resolution render of a frame, see render_thumbnail
+pub struct Thumbnail {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,  // Premultiplied RGBA8, see pixel_format
+}
+
+// Line-art rendering of the set boundary found by edge detection
+#[derive(Clone, Copy)]
+pub struct EdgeStyle {
+    pub threshold: f32,  // Sobel gradient of the normalized iteration count drawn as a line
+    pub line_color: SolidSource,
+    pub background: SolidSource,
+}
+
+// How render_density maps hit counts to brightness
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum DensityScale {
+    Sqrt,  // For counts within a few orders of magnitude of each other
+    Log,   // For counts piled up on a few pixels, such as inverse iteration's at the tips
+}
+
+// How escape values are spread over the palette
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ColoringMode {
+    Linear,     // Palette position proportional to the escape value
+    Histogram,  // Palette position by rank among the frame's escape values, see Equalization
+    DistanceEstimate,  // Linear, darkened near the set by its distance estimate, see render_distance_estimates
+}
+
+impl ColoringMode {
+    pub fn from_name(name: &str) -> Option<ColoringMode> {
+        match name.to_ascii_lowercase().as_str() {
+            "linear" => Some(ColoringMode::Linear),
+            "histogram" => Some(ColoringMode::Histogram),
+            "distanceestimate" => Some(ColoringMode::DistanceEstimate),
+            _ => None,
+        }
+    }
+}
+
+// Most iteration buckets counted for histogram coloring, so huge iteration
+// caps share buckets rather than allocating one per iteration
+const MAX_EQUALIZATION_BUCKETS: usize = 1 << 16;
+
+// Cumulative distribution of a frame's escape values over iteration buckets.
+// A value maps to the fraction of escaping pixels below it, interpolated
+// within its bucket, so each stretch of the palette covers as many pixels as
+// any other instead of most of it going to the few near the boundary.
+struct Equalization {
+    cumulative: Vec<f32>,  // Fraction of escaping values below each bucket, then 1
+    max_iterations: f32,
+}
+
+impl Equalization {
+    // Counts every escaping value of the buffers, None if none escape
+    fn new(buffers: &[&[f32]], max_iterations: u32) -> Option<Equalization> {
+        let buckets = (max_iterations as usize).clamp(1, MAX_EQUALIZATION_BUCKETS);
+        let max_iterations = max_iterations as f32;
+        let mut counts = vec![0u32; buckets];
+        for &value in buffers.iter().flat_map(|buffer| buffer.iter()).filter(|&&value| value < max_iterations) {
+            counts[Self::bucket(value, max_iterations, buckets)] += 1;
+        }
+
+        let total = counts.iter().map(|&count| count as u64).sum::<u64>();
+        if total == 0 {
+            return None;
+        }
+        let mut below = 0;
+        let mut cumulative = Vec::with_capacity(buckets + 1);
+        for count in counts {
+            cumulative.push(below as f32 / total as f32);
+            below += count as u64;
+        }
+        cumulative.push(1.0);
+        Some(Equalization { cumulative, max_iterations })
+    }
+
+    fn bucket(value: f32, max_iterations: f32, buckets: usize) -> usize {
+        ((value.max(0.0) / max_iterations * buckets as f32) as usize).min(buckets - 1)
+    }
+
+    // The value's rank as an escape value of the same palette position, in-set
+    // values unchanged
+    fn apply(&self, value: f32) -> f32 {
+        if value >= self.max_iterations {
+            return value;
+        }
+        let buckets = self.cumulative.len() - 1;
+        let position = value.max(0.0) / self.max_iterations * buckets as f32;
+        let bucket = Self::bucket(value, self.max_iterations, buckets);
+        let within = (position - bucket as f32).clamp(0.0, 1.0);
+        let rank = self.cumulative[bucket] + (self.cumulative[bucket + 1] - self.cumulative[bucket]) * within;
+        rank * self.max_iterations
+    }
+}
+
+// Orbits drawn over earlier animation frames, fading as later ones are added
+struct OrbitTrail {
+    decay: f32,  // Fraction of the trail's opacity kept from one frame to the next
+    target: DrawTarget,
+}
+
+// Tone curves with their lookup table for each 8-bit channel value
+#[derive(Clone)]
+struct Grading {
+    curves: ColorCurves,
+    tables: [[u8; 256]; 3],
+}
+
 pub struct FrameHandler {
     width: u32,
     height: u32,
     draw_target: DrawTarget,
     color_handler: ColorHandler,
+    coloring_mode: ColoringMode,
+    edge_style: Option<EdgeStyle>,
+    iteration_smoothing: f32,  // Blend toward the low-passed iteration field, 0 disables it
+    boundary_glow: Option<f32>,  // Falloff distance in pixels of the glow into the set
+    temporal: Option<TemporalAccumulator>,  // History blended into moving frames, None disables it
+    orbit_trail: Option<OrbitTrail>,
+    grading: Option<Grading>,  // Tone curves applied to finished frames, None leaves colors as rendered
 }
 
 impl FrameHandler {
@@ -16,14 +181,138 @@ impl FrameHandler {
             height,
             draw_target: DrawTarget::new(width as i32, height as i32),
             color_handler: ColorHandler::new(),
+            coloring_mode: ColoringMode::Linear,
+            edge_style: None,
+            iteration_smoothing: 0.0,
+            boundary_glow: None,
+            temporal: None,
+            orbit_trail: None,
+            grading: None,
         }
     }
 
-    pub fn render_frame(&mut self, iterations: &[u32], max_iterations: u32, sample_step: u32) {
-        let pixels = self.draw_target.get_data_mut();
+    pub fn set_coloring_mode(&mut self, coloring_mode: ColoringMode) {
+        self.coloring_mode = coloring_mode;
+    }
+
+    pub fn coloring_mode(&self) -> ColoringMode {
+        self.coloring_mode
+    }
+
+    pub fn set_edge_style(&mut self, edge_style: Option<EdgeStyle>) {
+        self.edge_style = edge_style;
+    }
+
+    pub fn edge_style(&self) -> Option<EdgeStyle> {
+        self.edge_style
+    }
+
+    pub fn set_iteration_smoothing(&mut self, strength: f32) {
+        self.iteration_smoothing = strength.clamp(0.0, 1.0);
+    }
+
+    pub fn iteration_smoothing(&self) -> f32 {
+        self.iteration_smoothing
+    }
+
+    // In-set pixels within the radius take on their nearest escaping
+    // neighbor's color, fading to the interior color with distance
+    pub fn set_boundary_glow(&mut self, radius: Option<f32>) {
+        self.boundary_glow = radius.filter(|radius| *radius > 0.0);
+    }
+
+    pub fn boundary_glow(&self) -> Option<f32> {
+        self.boundary_glow
+    }
+
+    pub fn set_temporal_accumulation(&mut self, enabled: bool) {
+        self.temporal = enabled.then(|| TemporalAccumulator::new(self.width, self.height));
+    }
+
+    pub fn temporal_accumulation(&self) -> bool {
+        self.temporal.is_some()
+    }
+
+    // Curves grade every coloring mode's output, after the palette and glow.
+    // Identity curves are dropped so ungraded frames skip the pass.
+    pub fn set_color_curves(&mut self, curves: Option<ColorCurves>) {
+        self.grading = curves.filter(|curves| !curves.is_identity())
+            .map(|curves| Grading { tables: curves.lookup_tables(), curves });
+    }
+
+    // Orbits drawn with draw_orbit_trail leave a trail, multiplied by the decay
+    // each frame, None draws only the current orbit
+    pub fn set_orbit_trail(&mut self, decay: Option<f32>) {
+        self.orbit_trail = decay.map(|decay| OrbitTrail {
+            decay: decay.clamp(0.0, 1.0),
+            target: DrawTarget::new(self.width as i32, self.height as i32),
+        });
+    }
+
+    pub fn orbit_trail(&self) -> Option<f32> {
+        self.orbit_trail.as_ref().map(|trail| trail.decay)
+    }
+
+    // Clears the trail, so the next orbit drawn starts a new one
+    pub fn clear_orbit_trail(&mut self) {
+        if let Some(trail) = &mut self.orbit_trail {
+            trail.target.clear(SolidSource { r: 0, g: 0, b: 0, a: 0 });
+        }
+    }
+
+    // Fades the trail, strokes the orbit onto it and composites it over the
+    // frame just rendered for the view. Does nothing while trails are off.
+    pub fn draw_orbit_trail(&mut self, view: &MandelbrotFrame, orbit: &[(f64, f64)]) {
+        let (width, height) = (self.width as i32, self.height as i32);
+        let (scale_x, scale_y) = (self.width as f32 / view.width as f32, self.height as f32 / view.height as f32);
+        let Some(trail) = &mut self.orbit_trail else {
+            return;
+        };
+
+        // Channels are premultiplied, so scaling all four fades without shifting color
+        for pixel in trail.target.get_data_mut() {
+            let channels = pixel.to_be_bytes().map(|channel| (channel as f32 * trail.decay) as u8);
+            *pixel = u32::from_be_bytes(channels);
+        }
+
+        let mut path = PathBuilder::new();
+        for (i, &(re, im)) in orbit.iter().enumerate() {
+            let (px, py) = view.complex_to_grid(re, im);
+            let (x, y) = (px * scale_x, py * scale_y);
+            if i == 0 { path.move_to(x, y) } else { path.line_to(x, y) }
+        }
+        let ink = Source::Solid(SolidSource { r: 255, g: 255, b: 255, a: 255 });
+        trail.target.stroke(&path.finish(), &ink, &StrokeStyle { width: 1.5, ..StrokeStyle::default() }, &DrawOptions::new());
+
+        let image = Image { width, height, data: trail.target.get_data() };
+        self.draw_target.draw_image_at(0.0, 0.0, &image, &DrawOptions::new());
+    }
+
+    // Blends the frame just rendered for the view with the ones before it
+    // while panning. Still frames drop the history so they stay sharp.
+    pub fn accumulate_motion(&mut self, view: &MandelbrotFrame, in_motion: bool) {
+        let Some(temporal) = &mut self.temporal else {
+            return;
+        };
+        if in_motion {
+            temporal.blend(view, self.draw_target.get_data_mut());
+        } else {
+            temporal.reset();
+        }
+    }
+
+    pub fn render_frame(&mut self, iterations: &[f32], max_iterations: u32, sample_step: u32) {
         let width = self.width as usize;
         let sampled_width = (self.width / sample_step) as usize;
         let sampled_height = (self.height / sample_step) as usize;
+        let iterations = self.low_pass_iterations(iterations, max_iterations, sampled_width, sampled_height);
+        let iterations = iterations.as_ref();
+        // Histogram coloring counts the whole frame before any pixel is colored
+        let equalization = self.equalization(&[iterations], max_iterations);
+        let pixels = self.draw_target.get_data_mut();
+        let edges = self.edge_style.map(|style| {
+            (style, Self::detect_edges(iterations, max_iterations, sampled_width, sampled_height, style.threshold))
+        });
         
         // Process each row in parallel
         pixels.chunks_mut(width)
@@ -38,14 +327,700 @@ impl FrameHandler {
                     let sample_x = (x / sample_step as usize).min(sampled_width - 1);
                     let idx = sample_y * sampled_width + sample_x;
                     
-                    let iterations = iterations[idx];
-                    let color = self.color_handler.get_color(iterations, max_iterations);
+                    let color = match &edges {
+                        Some((style, edge_mask)) if edge_mask[idx] => style.line_color,
+                        Some((style, _)) => style.background,
+                        None => self.color_handler.get_color(Self::equalize(&equalization, iterations[idx]), max_iterations),
+                    };
                     *pixel = color.to_u32();
                 }
             });
+        
+        if let (Some(radius), None) = (self.boundary_glow, self.edge_style) {
+            let in_set = self.in_set_pixels(iterations, max_iterations, sample_step);
+            self.apply_boundary_glow(&in_set, radius);
+        }
+        self.apply_color_curves();
+    }
+
+    // Renders the frame's view at thumbnail size, colored as this handler
+    // colors its frames, for a caller to show while the full frame is
+    // calculated on another thread. It takes a fraction of the full render's
+    // time, as its cost scales with the pixel count.
+    pub fn render_thumbnail(&self, frame: &MandelbrotFrame, max_size: u32) -> Result<Thumbnail> {
+        let mut thumbnail = frame.thumbnail(max_size);
+        let mut iterations = vec![0.0; (thumbnail.width * thumbnail.height) as usize];
+        thumbnail.calculate_into(&mut iterations)?;
+
+        let mut frame_handler = FrameHandler::new(thumbnail.width, thumbnail.height);
+        frame_handler.color_handler = self.color_handler.clone();
+        frame_handler.coloring_mode = self.coloring_mode;
+        frame_handler.edge_style = self.edge_style;
+        frame_handler.iteration_smoothing = self.iteration_smoothing;
+        // The glow radius is in output pixels, so it shrinks with them
+        frame_handler.boundary_glow = self.boundary_glow.map(|radius| radius * thumbnail.width as f32 / self.width as f32);
+        frame_handler.grading = self.grading.clone();
+        frame_handler.render_frame(&iterations, thumbnail.max_iterations, 1);
+
+        Ok(Thumbnail {
+            width: thumbnail.width,
+            height: thumbnail.height,
+            rgba: argb_u32_to_rgba8(frame_handler.draw_target.get_data()),
+        })
+    }
+
+    // Colors each pixel by the external angle its orbit escaped at, upscaling
+    // sampled buffers like render_frame
+    pub fn render_escape_angles(&mut self, iterations: &[f32], angles: &[f32], max_iterations: u32, sample_step: u32, style: AngleStyle) {
+        let width = self.width as usize;
+        let sampled_width = (self.width / sample_step) as usize;
+        let sampled_height = (self.height / sample_step) as usize;
+        let color_handler = &self.color_handler;
+
+        self.draw_target.get_data_mut()
+            .par_chunks_mut(width)
+            .enumerate()
+            .for_each(|(y, row)| {
+                let sample_y = (y / sample_step as usize).min(sampled_height - 1);
+                for (x, pixel) in row.iter_mut().enumerate() {
+                    let sample_x = (x / sample_step as usize).min(sampled_width - 1);
+                    let idx = sample_y * sampled_width + sample_x;
+                    *pixel = color_handler.get_angle_color(angles[idx], iterations[idx], max_iterations, style).to_u32();
+                }
+            });
+        self.apply_color_curves();
+    }
+
+    // Colors each pixel from the scheme, darkened toward black as its
+    // estimated distance from the set falls below DISTANCE_SHADE_PIXELS, so
+    // filaments thinner than a pixel still show as crisp dark lines. Upscales
+    // sampled buffers like render_frame.
+    pub fn render_distance_estimates(&mut self, iterations: &[f32], distances: &[f32], max_iterations: u32, sample_step: u32) {
+        let width = self.width as usize;
+        let sampled_width = (self.width / sample_step) as usize;
+        let sampled_height = (self.height / sample_step) as usize;
+        let color_handler = &self.color_handler;
+
+        self.draw_target.get_data_mut()
+            .par_chunks_mut(width)
+            .enumerate()
+            .for_each(|(y, row)| {
+                let sample_y = (y / sample_step as usize).min(sampled_height - 1);
+                for (x, pixel) in row.iter_mut().enumerate() {
+                    let sample_x = (x / sample_step as usize).min(sampled_width - 1);
+                    let idx = sample_y * sampled_width + sample_x;
+                    let color = color_handler.get_color(iterations[idx], max_iterations);
+                    if iterations[idx] >= max_iterations as f32 {
+                        *pixel = color.to_u32();
+                        continue;
+                    }
+                    let shade = (distances[idx] / DISTANCE_SHADE_PIXELS).clamp(0.0, 1.0);
+                    let channel = |value: u8| (value as f32 * shade) as u8;
+                    *pixel = SolidSource::from_unpremultiplied_argb(color.a, channel(color.r), channel(color.g), channel(color.b)).to_u32();
+                }
+            });
+        self.apply_color_curves();
+    }
+
+    // Sobel edge detector over the iteration field, marking samples whose
+    // gradient magnitude reaches the threshold. Letterbox bars take the
+    // value of the sample beside them, like the frame's border, so the
+    // bar's edge isn't drawn as the set's.
+    fn detect_edges(iterations: &[f32], max_iterations: u32, width: usize, height: usize, threshold: f32) -> Vec<bool> {
+        let value = |x: isize, y: isize, center: usize| {
+            let x = x.clamp(0, width as isize - 1) as usize;
+            let y = y.clamp(0, height as isize - 1) as usize;
+            let sample = iterations[y * width + x];
+            let sample = if is_letterbox_bar(sample, max_iterations) { iterations[center] } else { sample };
+            sample / max_iterations as f32
+        };
+
+        (0..width * height)
+            .into_par_iter()
+            .map(|i| {
+                if is_letterbox_bar(iterations[i], max_iterations) {
+                    return false;
+                }
+                let value = |x: isize, y: isize| value(x, y, i);
+                let (x, y) = ((i % width) as isize, (i / width) as isize);
+                let gx = value(x + 1, y - 1) + 2.0 * value(x + 1, y) + value(x + 1, y + 1)
+                    - value(x - 1, y - 1) - 2.0 * value(x - 1, y) - value(x - 1, y + 1);
+                let gy = value(x - 1, y + 1) + 2.0 * value(x, y + 1) + value(x + 1, y + 1)
+                    - value(x - 1, y - 1) - 2.0 * value(x, y - 1) - value(x + 1, y - 1);
+                (gx * gx + gy * gy).sqrt() >= threshold
+            })
+            .collect()
+    }
+
+    // Colors every supersample buffer and averages the colors per pixel, each
+    // buffer's edges found on its own in line-art mode
+    pub fn render_supersampled(&mut self, sample_iterations: &[Vec<f32>], max_iterations: u32) {
+        let width = self.width as usize;
+        let height = self.height as usize;
+        let sample_iterations: Vec<Cow<[f32]>> = sample_iterations.iter()
+            .map(|iterations| self.low_pass_iterations(iterations, max_iterations, width, height))
+            .collect();
+        let buffers: Vec<&[f32]> = sample_iterations.iter().map(|iterations| iterations.as_ref()).collect();
+        let equalization = self.equalization(&buffers, max_iterations);
+        let edges: Option<(EdgeStyle, Vec<Vec<bool>>)> = self.edge_style.map(|style| {
+            (style, buffers.iter().map(|iterations| Self::detect_edges(iterations, max_iterations, width, height, style.threshold)).collect())
+        });
+        let pixels = self.draw_target.get_data_mut();
+        let sample_count = sample_iterations.len() as u32;
+
+        pixels.par_chunks_mut(width)
+            .enumerate()
+            .for_each(|(y, row)| {
+                for (x, pixel) in row.iter_mut().enumerate() {
+                    let idx = y * width + x;
+                    let (mut a, mut r, mut g, mut b) = (0, 0, 0, 0);
+                    for (sample, iterations) in sample_iterations.iter().enumerate() {
+                        let color = match &edges {
+                            Some((style, edge_masks)) if edge_masks[sample][idx] => style.line_color,
+                            Some((style, _)) => style.background,
+                            None => self.color_handler.get_color(Self::equalize(&equalization, iterations[idx]), max_iterations),
+                        };
+                        a += color.a as u32;
+                        r += color.r as u32;
+                        g += color.g as u32;
+                        b += color.b as u32;
+                    }
+                    *pixel = (a / sample_count) << 24 | (r / sample_count) << 16 | (g / sample_count) << 8 | (b / sample_count);
+                }
+            });
+
+        // Pixels only partly in the set already blend toward the exterior
+        if let (Some(radius), None) = (self.boundary_glow, self.edge_style) {
+            let in_set: Vec<bool> = (0..width * height)
+                .map(|i| sample_iterations.iter().all(|iterations| {
+                    iterations[i] >= max_iterations as f32 && !is_letterbox_bar(iterations[i], max_iterations)
+                }))
+                .collect();
+            self.apply_boundary_glow(&in_set, radius);
+        }
+        self.apply_color_curves();
+    }
+
+    // Grayscale hit counts such as a Buddhabrot's, scaled against the densest
+    // pixel so faint trajectories stay visible
+    pub fn render_density(&mut self, density: &[u32], scale: DensityScale) {
+        let densest = density.iter().copied().max().unwrap_or(0).max(1) as f32;
+        let brightness = |count: u32| match scale {
+            DensityScale::Sqrt => (count as f32 / densest).sqrt(),
+            DensityScale::Log => (count as f32).ln_1p() / densest.ln_1p(),
+        };
+
+        self.draw_target.get_data_mut()
+            .par_iter_mut()
+            .zip(density)
+            .for_each(|(pixel, &count)| {
+                let value = (brightness(count) * 255.0) as u32;
+                *pixel = 0xFF00_0000 | value << 16 | value << 8 | value;
+            });
+        self.apply_color_curves();
+    }
+
+    // Per-pixel differences between two renders, such as from diff_render.
+    // Equal pixels are black, and any difference at all shows as at least dim
+    // red, brightening through yellow to white toward the largest one.
+    pub fn render_difference(&mut self, difference: &[f32], sample_step: u32) {
+        let width = self.width as usize;
+        let sampled_width = (self.width / sample_step) as usize;
+        let largest = difference.iter().copied().fold(0.0, f32::max);
+        let scale = largest.ln_1p().max(f32::MIN_POSITIVE);
+
+        self.draw_target.get_data_mut()
+            .par_iter_mut()
+            .enumerate()
+            .for_each(|(i, pixel)| {
+                let (x, y) = (i % width / sample_step as usize, i / width / sample_step as usize);
+                let delta = difference.get(y * sampled_width + x).copied().unwrap_or(0.0);
+                if delta == 0.0 {
+                    *pixel = 0xFF00_0000;
+                    return;
+                }
+                let t = 3.0 * delta.ln_1p() / scale;
+                let channel = |value: f32| (value.clamp(0.0, 1.0) * 255.0) as u32;
+                *pixel = 0xFF00_0000 | channel(0.35 + 0.65 * t) << 16 | channel(t - 1.0) << 8 | channel(t - 2.0);
+            });
+    }
+
+    // Full precision colors with channels in 0..1, averaging the colors of the
+    // supersample buffers and upscaling sampled buffers like render_frame.
+    // Used for high bit depth export, so the draw target is left untouched.
+    pub fn render_precise(&self, sample_iterations: &[Vec<f32>], max_iterations: u32, sample_step: u32) -> Vec<[f32; 3]> {
+        let width = self.width as usize;
+        let sampled_width = (self.width / sample_step) as usize;
+        let sampled_height = (self.height / sample_step) as usize;
+        let sample_iterations: Vec<Cow<[f32]>> = sample_iterations.iter()
+            .map(|iterations| self.low_pass_iterations(iterations, max_iterations, sampled_width, sampled_height))
+            .collect();
+        let buffers: Vec<&[f32]> = sample_iterations.iter().map(|iterations| iterations.as_ref()).collect();
+        let equalization = self.equalization(&buffers, max_iterations);
+        let sample_count = sample_iterations.len() as f32;
+        let color_handler = &self.color_handler;
+        let grade = self.grading.as_ref().map(|grading| grading.curves.precise_grader());
+
+        (0..width * self.height as usize)
+            .into_par_iter()
+            .map(|i| {
+                let sample_x = (i % width / sample_step as usize).min(sampled_width - 1);
+                let sample_y = (i / width / sample_step as usize).min(sampled_height - 1);
+                let idx = sample_y * sampled_width + sample_x;
+
+                let mut color = [0.0; 3];
+                for iterations in &sample_iterations {
+                    let sample = color_handler.get_color_precise(Self::equalize(&equalization, iterations[idx]), max_iterations);
+                    color.iter_mut().zip(sample).for_each(|(channel, value)| *channel += value / sample_count);
+                }
+                match &grade {
+                    Some(grade) => grade(color),
+                    None => color,
+                }
+            })
+            .collect()
+    }
+
+    // The histogram of the buffers to color by in histogram mode. The heatmap
+    // shows compute cost, so it stays linear.
+    fn equalization(&self, buffers: &[&[f32]], max_iterations: u32) -> Option<Equalization> {
+        if self.coloring_mode != ColoringMode::Histogram || self.color_handler.scheme() == ColorScheme::Heatmap {
+            return None;
+        }
+        Equalization::new(buffers, max_iterations)
+    }
+
+    fn equalize(equalization: &Option<Equalization>, value: f32) -> f32 {
+        equalization.as_ref().map_or(value, |equalization| equalization.apply(value))
+    }
+
+    // 3x3 Gaussian low-pass of the iteration field, blended in by the smoothing
+    // strength, so palette bands step across a couple of pixels instead of one.
+    // In-set samples are neither changed nor averaged in, keeping the interior
+    // and the exterior distinct.
+    fn low_pass_iterations<'a>(&self, iterations: &'a [f32], max_iterations: u32, width: usize, height: usize) -> Cow<'a, [f32]> {
+        const KERNEL: [[f32; 3]; 3] = [[1.0, 2.0, 1.0], [2.0, 4.0, 2.0], [1.0, 2.0, 1.0]];
+
+        if self.iteration_smoothing <= 0.0 {
+            return Cow::Borrowed(iterations);
+        }
+
+        let strength = self.iteration_smoothing;
+        let max_iterations = max_iterations as f32;
+        let smoothed = (0..width * height)
+            .into_par_iter()
+            .map(|i| {
+                let value = iterations[i];
+                if value >= max_iterations {
+                    return value;
+                }
+
+                let (x, y) = (i % width, i / width);
+                let (mut sum, mut total) = (0.0, 0.0);
+                for (ky, kernel_row) in KERNEL.iter().enumerate() {
+                    for (kx, weight) in kernel_row.iter().enumerate() {
+                        let (sx, sy) = ((x + kx).wrapping_sub(1), (y + ky).wrapping_sub(1));
+                        if sx >= width || sy >= height || iterations[sy * width + sx] >= max_iterations {
+                            continue;
+                        }
+                        sum += iterations[sy * width + sx] * weight;
+                        total += weight;
+                    }
+                }
+
+                value + (sum / total - value) * strength
+            })
+            .collect();
+
+        Cow::Owned(smoothed)
+    }
+
+    // Light 3x3 Gaussian blur used to soften banding on high-precision frames.
+    // In-set pixels are left untouched and never sampled, so the black interior
+    // does not bleed into the exterior and the set boundary stays sharp.
+    pub fn smooth_frame(&mut self, iterations: &[f32], max_iterations: u32, sample_step: u32) {
+        const KERNEL: [[u32; 3]; 3] = [[1, 2, 1], [2, 4, 2], [1, 2, 1]];
+
+        let width = self.width as usize;
+        let height = self.height as usize;
+
+        // Letterbox bars are held like the interior
+        let in_set = self.sampled_pixels(iterations, sample_step, |value| value >= max_iterations as f32);
+        let source = argb_u32_to_rgba8(self.draw_target.get_data());
+        let mut smoothed = source.clone();
+
+        smoothed.par_chunks_mut(width * 4)
+            .enumerate()
+            .for_each(|(y, row)| {
+                for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+                    if in_set[y * width + x] {
+                        continue;
+                    }
+
+                    let (mut sum, mut total) = ([0; 3], 0);
+                    for (ky, kernel_row) in KERNEL.iter().enumerate() {
+                        for (kx, weight) in kernel_row.iter().enumerate() {
+                            let (sx, sy) = ((x + kx).wrapping_sub(1), (y + ky).wrapping_sub(1));
+                            if sx >= width || sy >= height || in_set[sy * width + sx] {
+                                continue;
+                            }
+
+                            let neighbor = (sy * width + sx) * 4;
+                            for (channel, sum) in sum.iter_mut().enumerate() {
+                                *sum += source[neighbor + channel] as u32 * weight;
+                            }
+                            total += weight;
+                        }
+                    }
+
+                    for (channel, sum) in sum.iter().enumerate() {
+                        pixel[channel] = (sum / total) as u8;
+                    }
+                }
+            });
+
+        self.draw_target.get_data_mut().copy_from_slice(&rgba8_to_argb_u32(&smoothed));
+    }
+
+    // Per pixel in-set flags for an iteration buffer sampled every sample_step
+    // pixels, letterbox bars not counted
+    fn in_set_pixels(&self, iterations: &[f32], max_iterations: u32, sample_step: u32) -> Vec<bool> {
+        self.sampled_pixels(iterations, sample_step, |value| {
+            value >= max_iterations as f32 && !is_letterbox_bar(value, max_iterations)
+        })
+    }
+
+    // Per pixel flags for the samples of an iteration buffer sampled every
+    // sample_step pixels that satisfy the predicate
+    fn sampled_pixels(&self, iterations: &[f32], sample_step: u32, predicate: impl Fn(f32) -> bool) -> Vec<bool> {
+        let width = self.width as usize;
+        let sampled_width = (self.width / sample_step) as usize;
+        let sampled_height = (self.height / sample_step) as usize;
+
+        (0..width * self.height as usize)
+            .map(|i| {
+                let sample_x = (i % width / sample_step as usize).min(sampled_width - 1);
+                let sample_y = (i / width / sample_step as usize).min(sampled_height - 1);
+                predicate(iterations[sample_y * sampled_width + sample_x])
+            })
+            .collect()
+    }
+
+    // Softens the seam at the set boundary. A breadth-first sweep inward from
+    // the escaping pixels bordering the set finds each in-set pixel's nearest
+    // escaping pixel, whose color is blended in with a quadratic falloff.
+    fn apply_boundary_glow(&mut self, in_set: &[bool], radius: f32) {
+        let width = self.width as usize;
+        let height = self.height as usize;
+        let neighbors = |i: usize| {
+            let (x, y) = (i % width, i / width);
+            (0..9).filter_map(move |k| {
+                let (nx, ny) = ((x + k % 3).wrapping_sub(1), (y + k / 3).wrapping_sub(1));
+                (k != 4 && nx < width && ny < height).then_some(ny * width + nx)
+            })
+        };
+        let distance = |a: usize, b: usize| {
+            let dx = (a % width) as f32 - (b % width) as f32;
+            let dy = (a / width) as f32 - (b / width) as f32;
+            (dx * dx + dy * dy).sqrt()
+        };
+
+        let mut nearest: Vec<Option<usize>> = vec![None; width * height];
+        let mut queue = VecDeque::new();
+        for i in (0..width * height).filter(|&i| !in_set[i]) {
+            nearest[i] = Some(i);
+            if neighbors(i).any(|n| in_set[n]) {
+                queue.push_back(i);
+            }
+        }
+        while let Some(i) = queue.pop_front() {
+            let origin = nearest[i].unwrap_or(i);
+            for n in neighbors(i) {
+                if in_set[n] && nearest[n].is_none() && distance(origin, n) <= radius {
+                    nearest[n] = Some(origin);
+                    queue.push_back(n);
+                }
+            }
+        }
+
+        let source = argb_u32_to_rgba8(self.draw_target.get_data());
+        let mut glowing = source.clone();
+        glowing.par_chunks_mut(4)
+            .enumerate()
+            .for_each(|(i, pixel)| {
+                let Some(origin) = nearest[i].filter(|_| in_set[i]) else {
+                    return;
+                };
+                let falloff = (1.0 - distance(origin, i) / radius).max(0.0).powi(2);
+                // Alpha blends too, so a transparent interior fades in with the glow
+                for (channel, value) in pixel.iter_mut().enumerate() {
+                    let inside = source[i * 4 + channel] as f32;
+                    let outside = source[origin * 4 + channel] as f32;
+                    *value = (inside + (outside - inside) * falloff) as u8;
+                }
+            });
+
+        self.draw_target.get_data_mut().copy_from_slice(&rgba8_to_argb_u32(&glowing));
+    }
+
+    // Grades the straight color of each pixel, so partly transparent pixels
+    // such as a glow into a transparent interior keep their opacity
+    fn apply_color_curves(&mut self) {
+        let Some(grading) = &self.grading else {
+            return;
+        };
+        self.draw_target.get_data_mut()
+            .par_iter_mut()
+            .for_each(|pixel| {
+                let [a, r, g, b] = pixel.to_be_bytes();
+                if a == 0 {
+                    return;
+                }
+                let straight = unpremultiply(&[r, g, b, a]);
+                let channel = |i: usize| ((grading.tables[i][straight[i] as usize] as u32 * a as u32 + 127) / 255) as u8;
+                *pixel = u32::from_be_bytes([a, channel(0), channel(1), channel(2)]);
+            });
+    }
+
+    // Copy of the current frame for drawing overlays onto
+    pub fn snapshot(&self) -> DrawTarget {
+        DrawTarget::from_vec(self.width as i32, self.height as i32, self.draw_target.get_data().to_vec())
+    }
+
+    // Bars of an iteration_histogram, log scaled so sparse bins stay visible.
+    // Escape bins take the scheme's color for their iteration count, and the
+    // in-set bin on the right is drawn white.
+    pub fn draw_histogram(&self, target: &mut DrawTarget, histogram: &[u32], max_iterations: u32) {
+        let background = Source::Solid(SolidSource::from_unpremultiplied_argb(160, 64, 64, 64));
+        let in_set = Source::Solid(SolidSource { r: 255, g: 255, b: 255, a: 255 });
+        let options = DrawOptions::new();
+        let x = GAUGE_MARGIN;
+        let bottom = target.height() as f32 - GAUGE_MARGIN;
+        target.fill_rect(x - 1.0, bottom - HISTOGRAM_HEIGHT - 1.0, HISTOGRAM_WIDTH + 2.0, HISTOGRAM_HEIGHT + 2.0, &background, &options);
+
+        let tallest = (histogram.iter().copied().max().unwrap_or(0) as f32).ln_1p().max(f32::MIN_POSITIVE);
+        let bar_width = HISTOGRAM_WIDTH / histogram.len().max(1) as f32;
+        let escape_bins = histogram.len().saturating_sub(1);
+        for (i, &count) in histogram.iter().enumerate() {
+            let height = HISTOGRAM_HEIGHT * (count as f32).ln_1p() / tallest;
+            let source = if i < escape_bins {
+                let iterations = (i as f32 + 0.5) / escape_bins as f32 * max_iterations as f32;
+                Source::Solid(self.color_handler.get_color(iterations, max_iterations))
+            } else {
+                in_set.clone()
+            };
+            target.fill_rect(x + i as f32 * bar_width, bottom - height, bar_width, height, &source, &options);
+        }
+    }
+
+    // Two bars in the bottom-right corner, the upper one filling as the zoom
+    // uses up f64 and the lower one as it uses up BigFloat, green to red
+    pub fn draw_precision_gauge(target: &mut DrawTarget, headroom: PrecisionHeadroom) {
+        let background = Source::Solid(SolidSource::from_unpremultiplied_argb(160, 64, 64, 64));
+        let options = DrawOptions::new();
+        let x = target.width() as f32 - GAUGE_WIDTH - GAUGE_MARGIN;
+        let bottom = target.height() as f32 - GAUGE_MARGIN;
+
+        for (i, used) in [headroom.f64_used, headroom.high_precision_used].into_iter().enumerate() {
+            let y = bottom - (2 - i) as f32 * (GAUGE_HEIGHT + 3.0);
+            target.fill_rect(x - 1.0, y - 1.0, GAUGE_WIDTH + 2.0, GAUGE_HEIGHT + 2.0, &background, &options);
+
+            let fill = SolidSource {
+                r: (255.0 * (used * 2.0).min(1.0)) as u8,
+                g: (255.0 * (2.0 - used * 2.0).min(1.0)) as u8,
+                b: 0,
+                a: 255,
+            };
+            target.fill_rect(x, y, GAUGE_WIDTH * used, GAUGE_HEIGHT, &Source::Solid(fill), &options);
+        }
+    }
+
+    // Copy of the current frame with labelled gridlines and the real and
+    // imaginary axes drawn over it, for the view described by the frame
+    pub fn draw_grid(&self, view: &MandelbrotFrame) -> DrawTarget {
+        let mut target = self.snapshot();
+        let scale_x = self.width as f32 / view.width as f32;
+        let scale_y = self.height as f32 / view.height as f32;
+        let to_screen = |re: f64, im: f64| {
+            let (px, py) = view.complex_to_grid(re, im);
+            (px * scale_x, py * scale_y)
+        };
+
+        let spacing = Self::grid_spacing((view.x_max - view.x_min) * GRID_TARGET_SPACING / self.width as f64);
+        let decimals = (-spacing.log10().floor()).max(0.0) as usize;
+        let gridline = Source::Solid(SolidSource::from_unpremultiplied_argb(96, 255, 255, 255));
+        let axis = Source::Solid(SolidSource { r: 255, g: 255, b: 255, a: 255 });
+        let options = DrawOptions::new();
+
+        // Lines span the part of the plane on screen, which a rotated view
+        // extends past its unrotated bounds. Labels sit on the screen edges, so
+        // they are only drawn while the lines are axis aligned.
+        let corners = [(0, 0), (view.width, 0), (0, view.height), (view.width, view.height)]
+            .map(|(px, py)| view.pixel_to_complex(px, py));
+        let (re_min, re_max) = corners.iter().fold((f64::MAX, f64::MIN), |(lo, hi), &(re, _)| (lo.min(re), hi.max(re)));
+        let (im_min, im_max) = corners.iter().fold((f64::MAX, f64::MIN), |(lo, hi), &(_, im)| (lo.min(im), hi.max(im)));
+        let labelled = view.rotation_radians == 0.0;
+
+        let first_re = (re_min / spacing).ceil() as i64;
+        let last_re = (re_max / spacing).floor() as i64;
+        for k in first_re..=last_re {
+            let re = k as f64 * spacing;
+            let (start, end) = (to_screen(re, im_min), to_screen(re, im_max));
+            let mut path = PathBuilder::new();
+            path.move_to(start.0, start.1);
+            path.line_to(end.0, end.1);
+            let source = if k == 0 { &axis } else { &gridline };
+            target.stroke(&path.finish(), source, &StrokeStyle { width: if k == 0 { 2.0 } else { 1.0 }, ..StrokeStyle::default() }, &options);
+            if labelled {
+                Self::draw_label(&mut target, &format!("{:.*}", decimals, re), start.0 + 3.0, self.height as f32 - 5.0 * GLYPH_SCALE - 4.0);
+            }
+        }
+
+        let first_im = (im_min / spacing).ceil() as i64;
+        let last_im = (im_max / spacing).floor() as i64;
+        for k in first_im..=last_im {
+            let im = k as f64 * spacing;
+            let (start, end) = (to_screen(re_min, im), to_screen(re_max, im));
+            let mut path = PathBuilder::new();
+            path.move_to(start.0, start.1);
+            path.line_to(end.0, end.1);
+            let source = if k == 0 { &axis } else { &gridline };
+            target.stroke(&path.finish(), source, &StrokeStyle { width: if k == 0 { 2.0 } else { 1.0 }, ..StrokeStyle::default() }, &options);
+            if labelled {
+                Self::draw_label(&mut target, &format!("{:.*}i", decimals, im), 3.0, start.1 + 3.0);
+            }
+        }
+
+        target
+    }
+
+    // Marks the points of an attracting cycle and labels the queried point with
+    // the cycle's period, for the view described by the frame
+    pub fn draw_cycle(&self, target: &mut DrawTarget, view: &MandelbrotFrame, point: (f64, f64), cycle: &[(f64, f64)]) {
+        let scale_x = self.width as f32 / view.width as f32;
+        let scale_y = self.height as f32 / view.height as f32;
+        let to_screen = |(re, im): (f64, f64)| {
+            let (px, py) = view.complex_to_grid(re, im);
+            (px * scale_x, py * scale_y)
+        };
+        let shadow = Source::Solid(SolidSource { r: 0, g: 0, b: 0, a: 255 });
+        let marker = Source::Solid(SolidSource { r: 255, g: 255, b: 255, a: 255 });
+        let options = DrawOptions::new();
+
+        for &cycle_point in cycle {
+            let (x, y) = to_screen(cycle_point);
+            target.fill_rect(x - 2.0, y - 2.0, 5.0, 5.0, &shadow, &options);
+            target.fill_rect(x - 1.0, y - 1.0, 3.0, 3.0, &marker, &options);
+        }
+
+        let (x, y) = to_screen(point);
+        Self::draw_label(target, &format!("p{}", cycle.len()), x + 4.0, y - 5.0 * GLYPH_SCALE - 4.0);
+    }
+
+    // Smallest 1, 2 or 5 times a power of ten that is at least the raw spacing
+    fn grid_spacing(raw: f64) -> f64 {
+        let magnitude = 10f64.powf(raw.log10().floor());
+        [1.0, 2.0, 5.0, 10.0].iter()
+            .map(|step| step * magnitude)
+            .find(|&spacing| spacing >= raw)
+            .unwrap_or(10.0 * magnitude)
+    }
+
+    fn draw_label(target: &mut DrawTarget, text: &str, x: f32, y: f32) {
+        let shadow = Source::Solid(SolidSource { r: 0, g: 0, b: 0, a: 255 });
+        let ink = Source::Solid(SolidSource { r: 255, g: 255, b: 255, a: 255 });
+        let options = DrawOptions::new();
+
+        for (i, character) in text.chars().enumerate() {
+            let Some((_, rows)) = GLYPHS.iter().find(|(glyph, _)| *glyph == character) else {
+                continue;
+            };
+            let glyph_x = x + i as f32 * 4.0 * GLYPH_SCALE;
+            for (row, bits) in rows.iter().enumerate() {
+                for column in 0..3 {
+                    if bits & (0b100 >> column) == 0 {
+                        continue;
+                    }
+                    let px = glyph_x + column as f32 * GLYPH_SCALE;
+                    let py = y + row as f32 * GLYPH_SCALE;
+                    target.fill_rect(px + 1.0, py + 1.0, GLYPH_SCALE, GLYPH_SCALE, &shadow, &options);
+                    target.fill_rect(px, py, GLYPH_SCALE, GLYPH_SCALE, &ink, &options);
+                }
+            }
+        }
+    }
+
+    pub fn color_handler(&self) -> &ColorHandler {
+        &self.color_handler
+    }
+
+    pub fn set_color_handler(&mut self, color_handler: ColorHandler) {
+        self.color_handler = color_handler;
+    }
+
+    pub fn color_handler_mut(&mut self) -> &mut ColorHandler {
+        &mut self.color_handler
     }
 
     pub fn get_draw_target(&self) -> &DrawTarget {
         &self.draw_target
     }
-} 
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mandelbrot::letterbox_bar_value;
+
+    #[test]
+    fn letterbox_bars_are_neither_edges_nor_interior() {
+        // A bar down the left, the exterior in the middle and the set on the right
+        let (width, height, max_iterations) = (12, 4, 100);
+        let iterations: Vec<f32> = (0..width * height)
+            .map(|i| match i % width {
+                0..=3 => letterbox_bar_value(max_iterations),
+                4..=7 => 5.0,
+                _ => max_iterations as f32,
+            })
+            .collect();
+
+        let edges = FrameHandler::detect_edges(&iterations, max_iterations, width, height, 0.1);
+        let edge_columns: Vec<usize> = (0..width).filter(|&x| edges[x]).collect();
+        assert_eq!(edge_columns, [7, 8], "only the set's edge should be drawn");
+
+        let frame_handler = FrameHandler::new(width as u32, height as u32);
+        let in_set = frame_handler.in_set_pixels(&iterations, max_iterations, 1);
+        let in_set_columns: Vec<usize> = (0..width).filter(|&x| in_set[x]).collect();
+        assert_eq!(in_set_columns, [8, 9, 10, 11]);
+    }
+
+    #[test]
+    fn supersampled_line_art_matches_a_single_sample_and_gets_no_glow() {
+        // The exterior on the left and the set on the right
+        let (width, height, max_iterations) = (12, 4, 100);
+        let iterations: Vec<f32> = (0..width * height)
+            .map(|i| if i % width < 6 { 5.0 } else { max_iterations as f32 })
+            .collect();
+        let style = EdgeStyle {
+            threshold: 0.1,
+            line_color: SolidSource::from_unpremultiplied_argb(255, 255, 255, 255),
+            background: SolidSource::from_unpremultiplied_argb(255, 0, 0, 0),
+        };
+        let render = |supersampled: bool| {
+            let mut frame_handler = FrameHandler::new(width as u32, height as u32);
+            frame_handler.set_edge_style(Some(style));
+            frame_handler.set_boundary_glow(Some(3.0));
+            if supersampled {
+                frame_handler.render_supersampled(&[iterations.clone(), iterations.clone()], max_iterations);
+            } else {
+                frame_handler.render_frame(&iterations, max_iterations, 1);
+            }
+            frame_handler.get_draw_target().get_data().to_vec()
+        };
+
+        let single = render(false);
+        assert!(single.iter().all(|&pixel| pixel == style.line_color.to_u32() || pixel == style.background.to_u32()));
+        assert_eq!(render(true), single);
+    }
+}
+
@@ -0,0 +1,91 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+use rayon::prelude::*;
+use crate::error::{Result, RustybrotError};
+use crate::mandelbrot::{halton, FractalKind, MandelbrotFrame};
+
+// Square the walks start from, any point is drawn onto the Julia set
+const START_HALF_SIZE: f64 = 2.0;
+
+// Inverse iterations a walk takes before its points are plotted, by which
+// point it has converged onto the Julia set from wherever it started
+const WARMUP_ITERATIONS: u32 = 16;
+
+// Inverse iteration method. The Julia set repels forward orbits, so it
+// attracts orbits of the inverse map z -> ±sqrt(z - c), and walks of that map
+// trace the set out as a point cloud. Each walk starts at a Halton (2, 3)
+// point, takes max_iterations steps choosing a square root at random from a
+// seed of its index, and counts every point after its warm-up into the pixel
+// it lands on. A given walk count always gives the same image.
+pub fn accumulate(frame: &MandelbrotFrame, walks: u32) -> Result<Vec<u32>> {
+    let FractalKind::Julia { cx, cy } = frame.kind else {
+        return Err(RustybrotError::Config("The inverse iteration method draws Julia sets, pass --julia re,im".to_string()));
+    };
+    let width = frame.width as usize;
+    let height = frame.height as usize;
+    let density: Vec<AtomicU32> = (0..width * height).map(|_| AtomicU32::new(0)).collect();
+
+    (0..walks).into_par_iter().for_each(|i| {
+        let mut z = (
+            (halton(i, 2) * 2.0 - 1.0) * START_HALF_SIZE,
+            (halton(i, 3) * 2.0 - 1.0) * START_HALF_SIZE,
+        );
+        let mut branches = Branches::new(i);
+
+        for step in 0..WARMUP_ITERATIONS + frame.max_iterations {
+            z = principal_sqrt((z.0 - cx, z.1 - cy));
+            if branches.take_negative() {
+                z = (-z.0, -z.1);
+            }
+            if step < WARMUP_ITERATIONS {
+                continue;
+            }
+
+            let (x, y) = frame.complex_to_grid(z.0, z.1);
+            if x >= 0.0 && y >= 0.0 && (x as usize) < width && (y as usize) < height {
+                density[y as usize * width + x as usize].fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    });
+
+    Ok(density.into_iter().map(AtomicU32::into_inner).collect())
+}
+
+// Square root with a non-negative real part
+fn principal_sqrt((re, im): (f64, f64)) -> (f64, f64) {
+    let modulus = re.hypot(im);
+    let root_re = ((modulus + re) / 2.0).sqrt();
+    let root_im = ((modulus - re) / 2.0).sqrt();
+    (root_re, if im < 0.0 { -root_im } else { root_im })
+}
+
+// Which root each step of a walk takes, from a xorshift generator seeded by
+// splitmix64 so neighboring walk indices don't start out correlated
+struct Branches {
+    state: u64,
+    bits: u64,
+    remaining: u32,
+}
+
+impl Branches {
+    fn new(walk: u32) -> Branches {
+        let mut seed = (walk as u64).wrapping_add(0x9E37_79B9_7F4A_7C15);
+        seed = (seed ^ (seed >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        seed = (seed ^ (seed >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        Branches { state: (seed ^ (seed >> 31)) | 1, bits: 0, remaining: 0 }
+    }
+
+    // Whether the next step takes the negated root
+    fn take_negative(&mut self) -> bool {
+        if self.remaining == 0 {
+            self.state ^= self.state << 13;
+            self.state ^= self.state >> 7;
+            self.state ^= self.state << 17;
+            self.bits = self.state;
+            self.remaining = 64;
+        }
+        let bit = self.bits & 1 == 1;
+        self.bits >>= 1;
+        self.remaining -= 1;
+        bit
+    }
+}
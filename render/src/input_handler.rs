@@ -0,0 +1,1023 @@
+use crate::animation_handler::{nodes_from_bookmarks, AnimationHandler, AutoExposure, Bookmark};
+use crate::animation_handler::{DitherMode, FixedPalette, JuliaPath, Position};
+use crate::clipboard;
+use crate::color_handler::{ColorScheme, Palette};
+use crate::curves::{ColorCurves, CURVE_PRESETS};
+use crate::error::Result;
+use crate::export_handler::{ExportHandler, Sharpen};
+use crate::frame_handler::{ColoringMode, EdgeStyle, FrameHandler};
+use crate::location::Location;
+use crate::mandelbrot::{iterations_for_zoom, zoom_detail_multiplier, AntiAliasing, AspectPolicy, Formula, FractalKind, MandelbrotFrame};
+use crate::mandelbrot::{Recurrence, SamplePattern, StandardFloat};
+use crate::options::Options;
+use crate::project::{load_project, save_project, Project};
+use crate::view_state::ViewState;
+use crate::viewer_handler::ViewerHandler;
+use minifb::Key;
+use raqote::SolidSource;
+use std::time::Instant;
+
+// Base iteration counts selected by the number keys 1-5
+const ITERATION_PRESETS: [u32; 5] = [100, 500, 2000, 10000, 50000];
+const PRESET_KEYS: [Key; 5] = [Key::Key1, Key::Key2, Key::Key3, Key::Key4, Key::Key5];
+
+// Palette cycle lengths in iterations stepped through by 9, after spreading the palette over max iterations
+const CYCLE_LENGTHS: [f32; 5] = [8.0, 16.0, 32.0, 64.0, 128.0];
+
+// Keys that only change colors, so holding them doesn't count as input that
+// recalculates the view. Shift alone changes nothing, it's Shift+9's modifier.
+pub const RECOLOR_KEYS: [Key; 6] = [Key::F2, Key::Key9, Key::P, Key::Backspace, Key::LeftShift, Key::RightShift];
+
+// Named locations visited in turn by the tour key: (name, center, zoom, base iterations)
+const TOUR: [(&str, (f64, f64), f64, u32); 5] = [
+    ("Needle (antenna)", (-1.9, 0.0), 5.0, 200),
+    ("Seahorse valley", (-0.75, 0.1), 30.0, 300),
+    ("Elephant valley", (0.28, 0.008), 25.0, 300),
+    ("Triple spiral", (-0.088, 0.654), 30.0, 500),
+    ("Minibrot", (-1.7549, 0.0), 60.0, 300),
+];
+
+// Julia constant path for the morph animation: the classic circle of radius 0.7885
+const JULIA_MORPH_PATH: JuliaPath = JuliaPath::Circle {
+    center: Position { x: 0.0, y: 0.0 },
+    radius: 0.7885,
+};
+const JULIA_MORPH_DURATION: f64 = 6.0;
+
+// Seconds spent flying between consecutive bookmarks, adjustable at runtime
+const DEFAULT_HOP_DURATION: f64 = 3.0;
+const HOP_DURATION_STEP: f64 = 0.5;
+
+// Movement speed at zoom 1, the fine movement multiplier, and the fraction
+// of the remaining difference the speed closes each frame after toggling
+const BASE_SPEED: f64 = 0.02;
+const FINE_MOVEMENT_SCALE: f64 = 0.1;
+const SPEED_EASING: f64 = 0.2;
+
+// Zoom factor of one scroll wheel step
+const SCROLL_ZOOM_STEP: f64 = 1.25;
+
+// Radians the view turns per frame while a rotation key is held
+const ROTATION_SPEED: f64 = 0.02;
+
+// Iteration cap of moving frames toggled by 7 when --preview-iterations isn't given
+const DEFAULT_PREVIEW_ITERATION_CAP: u32 = 250;
+
+// Pixels either side of the set's edge that boundary-only anti-aliasing supersamples
+const DEFAULT_AA_BOUNDARY_RADIUS: u32 = 1;
+
+// Example custom recurrences, cycled in place of z^2 + c
+const CUSTOM_RECURRENCES: [(&str, Recurrence); 3] = [
+    ("z^2 + c sin(z)", |z, c| z * z + c * z.sin()),
+    ("z^3 + c", |z, c| z * z * z + c),
+    ("sin(z) c", |z, c| z.sin() * c),
+];
+
+// Auto-exposure used for animations when enabled
+const ANIMATION_EXPOSURE: AutoExposure = AutoExposure {
+    low_percentile: 0.02,
+    high_percentile: 0.98,
+    smoothing: 0.8,
+};
+
+// Opacity an animation's orbit trail keeps per frame when no decay is given
+const ORBIT_TRAIL_DECAY: f32 = 0.9;
+
+// Change in escape smoothing strength per key press
+const ESCAPE_SMOOTHING_STEP: f32 = 0.25;
+
+// Unsharp mask applied to exported stills when sharpening is enabled
+const EXPORT_SHARPEN: Sharpen = Sharpen { radius: 1.0, amount: 0.8 };
+
+// Line-art style used by the boundary edge detection mode
+const BOUNDARY_STYLE: EdgeStyle = EdgeStyle {
+    threshold: 0.05,
+    line_color: SolidSource { r: 0, g: 0, b: 0, a: 255 },
+    background: SolidSource { r: 255, g: 255, b: 255, a: 255 },
+};
+
+// Bounding box that holds the Julia sets of c in the Mandelbrot set, which
+// are centered on the origin and within the escape radius
+const JULIA_REAL_RANGE: (f64, f64) = (-2.0, 2.0);
+const JULIA_IMAG_RANGE: (f64, f64) = (-1.5, 1.5);
+
+// Center and zoom that frame the whole set in a width x height output, limited
+// by whichever axis of the region the aspect policy shows is tighter for the
+// set's aspect ratio
+fn fit_whole_set(kind: FractalKind, formula: Formula, width: u32, height: u32, aspect_policy: AspectPolicy) -> (f64, f64, f64) {
+    let (real_range, imag_range) = match kind {
+        FractalKind::Mandelbrot => {
+            let (x_min, x_max, y_min, y_max) = formula.bounds();
+            ((x_min, x_max), (y_min, y_max))
+        }
+        FractalKind::Julia { .. } => (JULIA_REAL_RANGE, JULIA_IMAG_RANGE),
+    };
+    let center_x = (real_range.0 + real_range.1) / 2.0;
+    let center_y = (imag_range.0 + imag_range.1) / 2.0;
+    let mut view = MandelbrotFrame::new(width, height);
+    view.aspect_policy = aspect_policy;
+    view.set_view(0.0, 0.0, 1.0);
+    let (half_width, half_height) = view.visible_half_size();
+    let zoom_x = 2.0 * half_width / (real_range.1 - real_range.0);
+    let zoom_y = 2.0 * half_height / (imag_range.1 - imag_range.0);
+    (center_x, center_y, zoom_x.min(zoom_y))
+}
+
+fn view_iterations(base_iterations: u32, zoom: f64, fixed_iterations: bool) -> u32 {
+    if fixed_iterations { base_iterations } else { iterations_for_zoom(base_iterations, zoom) }
+}
+
+// Plain magnification while it is still readable, scientific notation beyond
+pub fn format_zoom(zoom: f64) -> String {
+    if zoom < 1e4 {
+        format!("{:.1}x", zoom)
+    } else {
+        format!("{:.2e}x", zoom)
+    }
+}
+
+pub fn print_controls() {
+    println!("Controls:");
+    println!("Arrow keys: Move around");
+    println!("Left click: Center the view on the clicked point");
+    println!("Shift+click: Show the period and cycle of the bulb under the cursor");
+    println!("+/-: Zoom in/out");
+    println!("Scroll wheel: Zoom toward the cursor or center (Ctrl+scroll: the other one)");
+    println!("Enter: Toggle whether scrolling zooms toward the cursor or the center");
+    println!("]/[: Increase/decrease base iterations");
+    println!("I: Toggle fixed iteration count (no zoom-based detail multiplier)");
+    println!("6: Toggle the iteration histogram overlay");
+    println!("7: Toggle capping iterations while moving, for faster previews");
+    println!("Pause: Toggle skipping the interior confirmed by the previous frame while moving");
+    println!("8: Toggle f64 only, skipping high precision past its limit (faster, pixelated)");
+    println!("0: Toggle coloring points that escape just past the iteration cap as the set's edge");
+    println!("1-5: Base iteration presets ({:?})", ITERATION_PRESETS);
+    println!("Home/End: Rotate the view");
+    println!("Space: Toggle fine movement");
+    println!("Insert: Toggle blending of consecutive frames while panning");
+    println!("H: Fit the whole set in view");
+    println!("J: Show the Julia set of the view center");
+    println!("M: Return to the Mandelbrot set (Shift+M: cycle formula mandelbrot/burning ship/tricorn)");
+    println!("</>: Decrease/increase the power of the multibrot z^power + c");
+    println!("X: Cycle anti-aliasing (off/2x2/3x3/4x4)");
+    println!("Shift+X: Toggle anti-aliasing only near the set boundary");
+    println!("Z: Cycle anti-aliasing sample pattern (grid/rotated grid/halton)");
+    println!("`: Toggle f32 iteration on the standard path (faster, shallow zooms only)");
+    println!("R: Cycle custom recurrences ({})", CUSTOM_RECURRENCES.map(|(name, _)| name).join(", "));
+    println!(";/': Decrease/increase escape smoothing (hard bands to fully smooth)");
+    println!("B: Toggle smoothing of high precision frames");
+    println!("S: Set start node for animation");
+    println!("E: Set end node for animation");
+    println!("C: Clear animation nodes");
+    println!("A: Create animation (if start and end nodes are set)");
+    println!("F5: Cycle GIF encoding speed (1/10/30)");
+    println!("F6: Toggle indexed GIF encoding with the color scheme's palette");
+    println!("Tab: Jump to the next tour location ({})", TOUR.map(|(name, ..)| name).join(", "));
+    println!("K: Bookmark the current view");
+    println!("L: Clear bookmarks");
+    println!("V: Create fly-through animation visiting the bookmarks in order");
+    println!("PageUp/PageDown: Lengthen/shorten the time between bookmarks");
+    println!("O: Create Julia morph animation (between the nodes if set, else around a circle)");
+    println!("D: Cycle animation dithering (none/ordered/temporal)");
+    println!("T: Toggle animation auto-exposure");
+    println!("Backspace: Cycle color curves ({}, off)", CURVE_PRESETS.join(", "));
+    println!("Delete: Toggle drawing the orbit of the view center or Julia constant over animations, with a fading trail");
+    println!("G: Toggle complex plane grid and axes");
+    println!("Q: Toggle precision gauge (top bar: f64 used, bottom bar: BigFloat used)");
+    println!("F7: Toggle including the grid in exported stills");
+    println!("W: Export the current frame to render.png");
+    println!("U: Toggle sharpening of exported stills");
+    println!("/: Toggle exporting the set's interior as transparent");
+    println!("F11: Toggle 16-bit PNG export");
+    println!("F1: Print reference orbit info for the next frame");
+    println!("N: Save the scene to scene.mandel (Shift+N: load it)");
+    println!("F: Print the render parameters as command line options");
+    println!("F12: List the in-set components (cardioid and bulbs) of the next frame");
+    println!("F2: Toggle iteration cost heatmap");
+    println!("9: Cycle palette repeat length (max iterations/{:?} iterations)", CYCLE_LENGTHS);
+    println!("P: Cycle palette (rainbow, fire, ocean, grayscale, ultra)");
+    println!("Shift+P: Toggle histogram coloring, spreading the palette evenly over the frame's pixels");
+    println!("Shift+9: Reload the --palette file and recolor the frame without recalculating it");
+    println!("F3: Go to the location saved in location.kfr");
+    println!("F4: Toggle boundary line-art mode");
+    println!("Y: Copy the current location to the clipboard");
+    println!("F8: Cycle aspect policy (fit/fill/stretch)");
+    println!("F9: Cycle iteration smoothing strength (off/0.25/0.5/1)");
+    println!("\\: Cycle glow of the boundary into the set (off/4/8/16 px)");
+    println!("F10: Cycle FPS cap (30/60/120/uncapped)");
+    println!("Escape: Exit");
+}
+
+// Point of a period query and the attracting cycle it settles into
+type BulbCycle = ((f64, f64), Vec<(f64, f64)>);
+
+// The viewer's view and per-feature settings, changed by its keys. The
+// handlers hold the rest: colors, exports, animations and the window.
+pub struct InputHandler {
+    width: u32,
+    height: u32,
+    pub center_x: f64,
+    pub center_y: f64,
+    pub zoom: f64,
+    pub kind: FractalKind,
+    pub formula: Formula,
+    pub power: u32,
+    pub aspect_policy: AspectPolicy,
+    pub rotation: f64,
+    mandelbrot_view: Option<(f64, f64, f64)>,  // Mandelbrot view J left, for M to return to
+    pub exact_center: Option<(String, String)>,  // Decimal center of a loaded location, until the view moves
+    pub base_iterations: u32,
+    pub fixed_iterations: bool,
+    pub anti_aliasing: AntiAliasing,
+    pub escape_smoothing: f32,
+    pub standard_float: StandardFloat,
+    pub capped_lookahead: bool,
+    pub disable_high_precision: bool,
+    pub preview_iteration_cap: Option<u32>,
+    pub reuse_interior: bool,  // Moving frames skip the interior confirmed by the one before
+    recurrence_index: Option<usize>,
+    pub smooth_high_precision: bool,
+    pub print_reference_info: bool,
+    pub print_components: bool,
+    pub show_grid: bool,
+    pub show_precision_gauge: bool,
+    pub histogram: Option<Vec<u32>>,  // Iteration histogram of the frame on screen while shown
+    pub bulb_cycle: Option<BulbCycle>,  // Last period query
+    grid_in_exports: bool,
+    sixteen_bit_exports: bool,
+    bookmarks: Vec<Bookmark>,
+    hop_duration: f64,
+    curve_preset: Option<usize>,  // Index into CURVE_PRESETS of the viewer's curves
+    tour_index: Option<usize>,
+    pub fine_movement: bool,
+    scroll_toward_cursor: bool,  // Scroll zoom keeps the point under the cursor fixed, else the center
+    speed_scale: f64,
+    pub in_motion: bool,  // Movement or zoom keys are held
+    start_time: Instant,
+}
+
+impl InputHandler {
+    // Initial view state, framing the whole set for the other formulas
+    pub fn new(width: u32, height: u32, options: &Options) -> Self {
+        let aspect_policy = AspectPolicy::Fit;
+        let (center_x, center_y, zoom) = match options.formula {
+            Formula::Mandelbrot => (-0.5, 0.0, 1.0),
+            _ => fit_whole_set(options.kind, options.formula, width, height, aspect_policy),
+        };
+        InputHandler {
+            width,
+            height,
+            center_x,
+            center_y,
+            zoom,
+            kind: options.kind,
+            formula: options.formula,
+            power: options.power,
+            aspect_policy,
+            rotation: 0.0,
+            mandelbrot_view: None,
+            exact_center: None,
+            base_iterations: 100,
+            fixed_iterations: false,
+            anti_aliasing: AntiAliasing { factor: 1, pattern: SamplePattern::RotatedGrid, boundary_radius: None },
+            escape_smoothing: 1.0,
+            standard_float: options.standard_float,
+            capped_lookahead: options.capped_lookahead,
+            disable_high_precision: options.disable_high_precision.unwrap_or(false),
+            preview_iteration_cap: options.preview_iterations,
+            reuse_interior: false,
+            recurrence_index: None,
+            smooth_high_precision: false,
+            print_reference_info: false,
+            print_components: false,
+            show_grid: false,
+            show_precision_gauge: true,
+            histogram: None,
+            bulb_cycle: None,
+            grid_in_exports: false,
+            sixteen_bit_exports: false,
+            bookmarks: Vec::new(),
+            hop_duration: DEFAULT_HOP_DURATION,
+            curve_preset: None,
+            tour_index: None,
+            fine_movement: false,
+            scroll_toward_cursor: true,
+            speed_scale: 1.0,
+            in_motion: false,
+            start_time: Instant::now(),
+        }
+    }
+
+    // Iteration count of the view, scaled with its zoom unless fixed
+    pub fn iterations(&self) -> u32 {
+        view_iterations(self.base_iterations, self.zoom, self.fixed_iterations)
+    }
+
+    // Extra iterations needed to keep detail as the view zooms in
+    pub fn detail_multiplier(&self) -> u32 {
+        if self.fixed_iterations { 1 } else { zoom_detail_multiplier(self.zoom) }
+    }
+
+    // A frame of the current view with the viewer's settings, at width x height
+    pub fn frame(&self, width: u32, height: u32) -> MandelbrotFrame {
+        let mut frame = MandelbrotFrame::new(width, height);
+        frame.aspect_policy = self.aspect_policy;
+        frame.rotation_radians = self.rotation;
+        frame.escape_smoothing = self.escape_smoothing;
+        frame.standard_float = self.standard_float;
+        frame.capped_lookahead = self.capped_lookahead;
+        frame.disable_high_precision = self.disable_high_precision;
+        frame.recurrence = self.recurrence_index.map(|i| CUSTOM_RECURRENCES[i].1);
+        frame.kind = self.kind;
+        frame.formula = self.formula;
+        frame.power = self.power;
+        frame.set_view(self.center_x, self.center_y, self.zoom);
+        frame.max_iterations = self.iterations();
+        frame
+    }
+
+    fn fit_whole_set(&mut self) {
+        (self.center_x, self.center_y, self.zoom) = fit_whole_set(self.kind, self.formula, self.width, self.height, self.aspect_policy);
+    }
+
+    // Handles one frame's input, returning whether the frame on screen only
+    // needs recoloring
+    pub fn handle_input(&mut self, viewer: &mut ViewerHandler, frame_handler: &mut FrameHandler, animation_handler: &mut AnimationHandler,
+                        export_handler: &mut ExportHandler, options: &Options) -> Result<bool> {
+        self.handle_movement(viewer, frame_handler);
+        self.handle_view_keys(viewer);
+        self.handle_calculation_keys(viewer, options);
+        let recolor = self.handle_color_keys(viewer, frame_handler, options);
+        self.handle_export_keys(viewer, frame_handler, export_handler, options)?;
+        self.handle_animation_keys(viewer, frame_handler, animation_handler, options);
+        Ok(recolor)
+    }
+
+    // Panning, zooming and rotating
+    fn handle_movement(&mut self, viewer: &mut ViewerHandler, frame_handler: &mut FrameHandler) {
+        if viewer.is_key_pressed(Key::Space) {
+            self.fine_movement = !self.fine_movement;
+            println!("Fine movement {}", if self.fine_movement { "enabled" } else { "disabled" });
+        }
+
+        // Ease toward the target speed so toggling doesn't jerk a movement in progress
+        let target_scale = if self.fine_movement { FINE_MOVEMENT_SCALE } else { 1.0 };
+        self.speed_scale += (target_scale - self.speed_scale) * SPEED_EASING;
+        let movement_speed = BASE_SPEED * self.speed_scale / self.zoom;
+
+        // Track if any movement or zoom keys are pressed
+        let is_moving = viewer.is_key_down(Key::Left)
+            || viewer.is_key_down(Key::Right)
+            || viewer.is_key_down(Key::Up)
+            || viewer.is_key_down(Key::Down);
+        let scroll = viewer.scroll();
+        let is_zooming = viewer.is_key_down(Key::Equal) || viewer.is_key_down(Key::Minus) || scroll != 0.0;
+        self.in_motion = is_moving || is_zooming;
+
+        // Handle movement
+        let (mut dx, mut dy) = (0.0, 0.0);
+        if viewer.is_key_down(Key::Left) { dx -= movement_speed; }
+        if viewer.is_key_down(Key::Right) { dx += movement_speed; }
+        if viewer.is_key_down(Key::Up) { dy -= movement_speed; }
+        if viewer.is_key_down(Key::Down) { dy += movement_speed; }
+
+        // Arrow keys move along the screen axes, however the view is rotated
+        let (sin, cos) = self.rotation.sin_cos();
+        self.center_x += dx * cos - dy * sin;
+        self.center_y += dx * sin + dy * cos;
+        if viewer.is_key_down(Key::Home) { self.rotation -= ROTATION_SPEED; }
+        if viewer.is_key_down(Key::End) { self.rotation += ROTATION_SPEED; }
+        if viewer.is_key_down(Key::Equal) { self.zoom *= 1.1; }
+        if viewer.is_key_down(Key::Minus) { self.zoom /= 1.1; }
+        if viewer.is_key_pressed(Key::Enter) {
+            self.scroll_toward_cursor = !self.scroll_toward_cursor;
+            println!("Scroll zoom anchor: {}", if self.scroll_toward_cursor { "cursor" } else { "center" });
+        }
+        if viewer.is_key_pressed(Key::Insert) {
+            frame_handler.set_temporal_accumulation(!frame_handler.temporal_accumulation());
+            println!("Pan accumulation {}", if frame_handler.temporal_accumulation() { "enabled" } else { "disabled" });
+        }
+        if scroll != 0.0 {
+            let new_zoom = self.zoom * SCROLL_ZOOM_STEP.powf(scroll as f64);
+            // Ctrl swaps the anchor for this scroll
+            let ctrl = viewer.is_key_down(Key::LeftCtrl) || viewer.is_key_down(Key::RightCtrl);
+            if let (true, Some((mouse_x, mouse_y))) = (self.scroll_toward_cursor != ctrl, viewer.mouse_position()) {
+                let mut view = MandelbrotFrame::new(self.width, self.height);
+                view.aspect_policy = self.aspect_policy;
+                view.rotation_radians = self.rotation;
+                view.set_view(self.center_x, self.center_y, self.zoom);
+                // The view scales about the cursor's point, so it stays under the cursor
+                let (anchor_x, anchor_y) = view.pixel_to_complex(mouse_x as u32, mouse_y as u32);
+                self.center_x = anchor_x + (self.center_x - anchor_x) * self.zoom / new_zoom;
+                self.center_y = anchor_y + (self.center_y - anchor_y) * self.zoom / new_zoom;
+            }
+            self.zoom = new_zoom;
+        }
+    }
+
+    // Which set is shown, where, and how far it is iterated
+    fn handle_view_keys(&mut self, viewer: &mut ViewerHandler) {
+        let shift = viewer.is_key_down(Key::LeftShift) || viewer.is_key_down(Key::RightShift);
+        if viewer.is_key_down(Key::RightBracket) { self.base_iterations += 10; }
+        if viewer.is_key_down(Key::LeftBracket) && self.base_iterations > 10 { self.base_iterations -= 10; }
+        for (key, preset) in PRESET_KEYS.iter().zip(ITERATION_PRESETS) {
+            if viewer.is_key_pressed(*key) { self.base_iterations = preset; }
+        }
+        if viewer.is_key_pressed(Key::I) {
+            self.fixed_iterations = !self.fixed_iterations;
+            println!("Fixed iteration count {}", if self.fixed_iterations { "enabled" } else { "disabled" });
+        }
+        if viewer.is_key_pressed(Key::H) {
+            self.fit_whole_set();
+        }
+        if viewer.is_key_pressed(Key::J) {
+            if self.kind == FractalKind::Mandelbrot {
+                self.mandelbrot_view = Some((self.center_x, self.center_y, self.zoom));
+            }
+            println!("Julia set of c = {}, {}", self.center_x, self.center_y);
+            self.kind = FractalKind::Julia { cx: self.center_x, cy: self.center_y };
+            self.fit_whole_set();
+        }
+        if viewer.is_key_pressed(Key::Comma) && self.power > 2 {
+            self.power -= 1;
+            println!("Power: {}", self.power);
+        }
+        if viewer.is_key_pressed(Key::Period) {
+            self.power += 1;
+            println!("Power: {}", self.power);
+        }
+        if viewer.is_key_pressed(Key::M) {
+            if shift {
+                self.formula = match self.formula {
+                    Formula::Mandelbrot => Formula::BurningShip,
+                    Formula::BurningShip => Formula::Tricorn,
+                    Formula::Tricorn => Formula::Mandelbrot,
+                };
+                // Each formula's set lies elsewhere, so the view starts over
+                self.mandelbrot_view = None;
+                self.fit_whole_set();
+                println!("Formula: {:?}", self.formula);
+            } else if self.kind != FractalKind::Mandelbrot {
+                self.kind = FractalKind::Mandelbrot;
+                match self.mandelbrot_view.take() {
+                    Some(view) => (self.center_x, self.center_y, self.zoom) = view,
+                    None => self.fit_whole_set(),
+                }
+                println!("Mandelbrot set");
+            }
+        }
+        if viewer.is_key_pressed(Key::F8) {
+            self.aspect_policy = match self.aspect_policy {
+                AspectPolicy::Fit => AspectPolicy::Fill,
+                AspectPolicy::Fill => AspectPolicy::Stretch,
+                AspectPolicy::Stretch => AspectPolicy::Fit,
+            };
+            println!("Aspect policy: {:?}", self.aspect_policy);
+        }
+        if viewer.is_key_pressed(Key::F3) {
+            match Location::from_file("location.kfr") {
+                Ok(location) => {
+                    (self.center_x, self.center_y) = location.center();
+                    self.exact_center = Some((location.center_re.clone(), location.center_im.clone()));
+                    self.zoom = location.zoom;
+                    // Kalles Fraktaler locations are in the Mandelbrot set
+                    (self.kind, self.formula, self.power) = (FractalKind::Mandelbrot, Formula::Mandelbrot, 2);
+                    if let Some(iterations) = location.iterations {
+                        self.base_iterations = iterations;
+                        self.fixed_iterations = true;
+                    }
+                    println!("Moved to ({}, {}) with zoom {}", location.center_re, location.center_im, format_zoom(self.zoom));
+                }
+                Err(e) => println!("Could not load location.kfr: {}", e),
+            }
+        }
+        if viewer.is_key_pressed(Key::Tab) {
+            let index = self.tour_index.map_or(0, |i| (i + 1) % TOUR.len());
+            let (name, center, tour_zoom, iterations) = TOUR[index];
+            (self.center_x, self.center_y) = center;
+            self.zoom = tour_zoom;
+            self.base_iterations = iterations;
+            self.rotation = 0.0;
+            (self.kind, self.formula, self.power) = (FractalKind::Mandelbrot, Formula::Mandelbrot, 2);  // Tour locations are in the Mandelbrot set
+            self.tour_index = Some(index);
+            println!("Tour {}/{}: {}", index + 1, TOUR.len(), name);
+        }
+
+        if viewer.is_mouse_clicked() {
+            if let Some((mouse_x, mouse_y)) = viewer.mouse_position() {
+                let mut view = MandelbrotFrame::new(self.width, self.height);
+                view.aspect_policy = self.aspect_policy;
+                view.rotation_radians = self.rotation;
+                view.recurrence = self.recurrence_index.map(|i| CUSTOM_RECURRENCES[i].1);
+                view.max_iterations = self.iterations();
+                view.set_view(self.center_x, self.center_y, self.zoom);
+                let point = view.pixel_to_complex(mouse_x as u32, mouse_y as u32);
+
+                // Shift+click asks for the period of the bulb under the cursor
+                if shift {
+                    self.bulb_cycle = view.attracting_cycle(point).map(|(_, cycle)| (point, cycle));
+                    match &self.bulb_cycle {
+                        Some((_, cycle)) => println!("\n({:.6}, {:.6}) settles into a cycle of period {}", point.0, point.1, cycle.len()),
+                        None => println!("\n({:.6}, {:.6}) escapes or doesn't settle into a cycle", point.0, point.1),
+                    }
+                } else {
+                    (self.center_x, self.center_y) = point;
+                }
+            }
+        }
+    }
+
+    // How escape values are calculated, and what is reported about them
+    fn handle_calculation_keys(&mut self, viewer: &mut ViewerHandler, options: &Options) {
+        if viewer.is_key_pressed(Key::X) {
+            if viewer.is_key_down(Key::LeftShift) || viewer.is_key_down(Key::RightShift) {
+                self.anti_aliasing.boundary_radius = match self.anti_aliasing.boundary_radius {
+                    None => Some(DEFAULT_AA_BOUNDARY_RADIUS),
+                    Some(_) => None,
+                };
+                println!("Boundary-only anti-aliasing {}", if self.anti_aliasing.boundary_radius.is_some() { "enabled" } else { "disabled" });
+            } else {
+                self.anti_aliasing.factor = self.anti_aliasing.factor % 4 + 1;
+                println!("Anti-aliasing: {}x{}", self.anti_aliasing.factor, self.anti_aliasing.factor);
+            }
+        }
+        if viewer.is_key_pressed(Key::Z) {
+            self.anti_aliasing.pattern = match self.anti_aliasing.pattern {
+                SamplePattern::Grid => SamplePattern::RotatedGrid,
+                SamplePattern::RotatedGrid => SamplePattern::Halton,
+                SamplePattern::Halton => SamplePattern::Grid,
+            };
+            println!("Anti-aliasing pattern: {:?}", self.anti_aliasing.pattern);
+        }
+        if viewer.is_key_pressed(Key::Key7) {
+            self.preview_iteration_cap = match self.preview_iteration_cap {
+                Some(_) => None,
+                None => Some(options.preview_iterations.unwrap_or(DEFAULT_PREVIEW_ITERATION_CAP)),
+            };
+            match self.preview_iteration_cap {
+                Some(cap) => println!("Preview iteration cap: {}", cap),
+                None => println!("Preview iteration cap: off"),
+            }
+        }
+        if viewer.is_key_pressed(Key::Pause) {
+            self.reuse_interior = !self.reuse_interior;
+            println!("Interior reuse while moving {}", if self.reuse_interior { "enabled" } else { "disabled" });
+        }
+        if viewer.is_key_pressed(Key::Key8) {
+            self.disable_high_precision = !self.disable_high_precision;
+            println!("High precision {}", if self.disable_high_precision { "disabled" } else { "enabled" });
+        }
+        if viewer.is_key_pressed(Key::Key0) {
+            self.capped_lookahead = !self.capped_lookahead;
+            println!("Capped point lookahead {}", if self.capped_lookahead { "enabled" } else { "disabled" });
+        }
+        if viewer.is_key_pressed(Key::Backquote) {
+            self.standard_float = match self.standard_float {
+                StandardFloat::F64 => StandardFloat::F32,
+                StandardFloat::F32 => StandardFloat::F64,
+            };
+            println!("Standard path float: {:?}", self.standard_float);
+        }
+        if viewer.is_key_pressed(Key::R) {
+            self.recurrence_index = match self.recurrence_index {
+                None => Some(0),
+                Some(i) if i + 1 < CUSTOM_RECURRENCES.len() => Some(i + 1),
+                Some(_) => None,
+            };
+            match self.recurrence_index {
+                Some(i) => println!("Recurrence: z -> {}", CUSTOM_RECURRENCES[i].0),
+                None => println!("Recurrence: z -> z^2 + c"),
+            }
+        }
+        if viewer.is_key_pressed(Key::Semicolon) {
+            self.escape_smoothing = (self.escape_smoothing - ESCAPE_SMOOTHING_STEP).max(0.0);
+            println!("Escape smoothing: {:.2}", self.escape_smoothing);
+        }
+        if viewer.is_key_pressed(Key::Apostrophe) {
+            self.escape_smoothing = (self.escape_smoothing + ESCAPE_SMOOTHING_STEP).min(1.0);
+            println!("Escape smoothing: {:.2}", self.escape_smoothing);
+        }
+        if viewer.is_key_pressed(Key::B) {
+            self.smooth_high_precision = !self.smooth_high_precision;
+            println!("High precision smoothing {}", if self.smooth_high_precision { "enabled" } else { "disabled" });
+        }
+        if viewer.is_key_pressed(Key::F1) {
+            self.print_reference_info = true;
+        }
+        if viewer.is_key_pressed(Key::F12) {
+            self.print_components = true;
+        }
+    }
+
+    // Colors and overlays, returning whether the frame on screen needs recoloring
+    fn handle_color_keys(&mut self, viewer: &mut ViewerHandler, frame_handler: &mut FrameHandler, options: &Options) -> bool {
+        let shift = viewer.is_key_down(Key::LeftShift) || viewer.is_key_down(Key::RightShift);
+        let mut recolor = false;
+        if viewer.is_key_pressed(Key::G) {
+            self.show_grid = !self.show_grid;
+        }
+        if viewer.is_key_pressed(Key::Q) {
+            self.show_precision_gauge = !self.show_precision_gauge;
+            println!("Precision gauge {}", if self.show_precision_gauge { "enabled" } else { "disabled" });
+        }
+        if viewer.is_key_pressed(Key::Key6) {
+            // Filled in from the next finished frame
+            self.histogram = match self.histogram {
+                Some(_) => None,
+                None => Some(Vec::new()),
+            };
+            println!("Iteration histogram {}", if self.histogram.is_some() { "enabled" } else { "disabled" });
+        }
+        if viewer.is_key_pressed(Key::F2) {
+            let color_handler = frame_handler.color_handler_mut();
+            let scheme = match color_handler.scheme() {
+                ColorScheme::Hsv => ColorScheme::Heatmap,
+                ColorScheme::Heatmap => ColorScheme::Hsv,
+            };
+            color_handler.set_scheme(scheme);
+            println!("Color scheme: {:?}", scheme);
+            recolor = true;
+        }
+        if viewer.is_key_pressed(Key::Key9) {
+            let color_handler = frame_handler.color_handler_mut();
+            if shift {
+                match color_handler.reload_palette_file() {
+                    Ok(()) => println!("Palette reloaded from {}", color_handler.palette_file().unwrap_or_default()),
+                    Err(e) => println!("Could not reload the palette, keeping the current one: {}", e),
+                }
+            } else {
+                let next = match color_handler.cycle_length() {
+                    None => Some(0),
+                    Some(length) => CYCLE_LENGTHS.iter().position(|&preset| preset == length).map(|i| i + 1).filter(|&i| i < CYCLE_LENGTHS.len()),
+                };
+                color_handler.set_cycle_length(next.map(|i| CYCLE_LENGTHS[i]));
+                match color_handler.cycle_length() {
+                    Some(length) => println!("Palette cycle: every {} iterations", length),
+                    None => println!("Palette cycle: over max iterations"),
+                }
+            }
+            recolor = true;
+        }
+        if viewer.is_key_pressed(Key::P) {
+            if shift {
+                let coloring_mode = match frame_handler.coloring_mode() {
+                    ColoringMode::Linear => ColoringMode::Histogram,
+                    ColoringMode::Histogram | ColoringMode::DistanceEstimate => ColoringMode::Linear,
+                };
+                frame_handler.set_coloring_mode(coloring_mode);
+                println!("Histogram coloring {}", if coloring_mode == ColoringMode::Histogram { "enabled" } else { "disabled" });
+            } else {
+                let color_handler = frame_handler.color_handler_mut();
+                let palette = match color_handler.selected_palette() {
+                    Palette::Rainbow => Palette::Fire,
+                    Palette::Fire => Palette::Ocean,
+                    Palette::Ocean => Palette::Grayscale,
+                    Palette::Grayscale => Palette::Ultra,
+                    Palette::Ultra => Palette::Rainbow,
+                };
+                color_handler.set_palette(palette);
+                println!("Palette: {:?}", palette);
+            }
+            recolor = true;
+        }
+        if viewer.is_key_pressed(Key::Backspace) {
+            // Off goes back to the command line's curves, if any
+            self.curve_preset = match self.curve_preset {
+                Some(i) if i + 1 < CURVE_PRESETS.len() => Some(i + 1),
+                Some(_) => None,
+                None => Some(0),
+            };
+            match self.curve_preset {
+                Some(i) => {
+                    frame_handler.set_color_curves(ColorCurves::from_preset(CURVE_PRESETS[i]));
+                    println!("Color curves: {}", CURVE_PRESETS[i]);
+                }
+                None => {
+                    frame_handler.set_color_curves(options.curves.clone());
+                    println!("Color curves: off");
+                }
+            }
+            recolor = true;
+        }
+        if viewer.is_key_pressed(Key::F4) {
+            let edge_style = if frame_handler.edge_style().is_some() { None } else { Some(BOUNDARY_STYLE) };
+            frame_handler.set_edge_style(edge_style);
+            println!("Boundary line-art mode {}", if edge_style.is_some() { "enabled" } else { "disabled" });
+        }
+        if viewer.is_key_pressed(Key::F9) {
+            let strength = match frame_handler.iteration_smoothing() {
+                s if s < 0.25 => 0.25,
+                s if s < 0.5 => 0.5,
+                s if s < 1.0 => 1.0,
+                _ => 0.0,
+            };
+            frame_handler.set_iteration_smoothing(strength);
+            println!("Iteration smoothing: {}", strength);
+        }
+        if viewer.is_key_pressed(Key::Backslash) {
+            let radius = match frame_handler.boundary_glow() {
+                None => Some(4.0),
+                Some(r) if r < 8.0 => Some(8.0),
+                Some(r) if r < 16.0 => Some(16.0),
+                Some(_) => None,
+            };
+            frame_handler.set_boundary_glow(radius);
+            match radius {
+                Some(radius) => println!("Boundary glow: {} px", radius),
+                None => println!("Boundary glow: off"),
+            }
+        }
+        if viewer.is_key_pressed(Key::F10) {
+            let fps_cap = match viewer.fps_cap() {
+                Some(30) => Some(60),
+                Some(60) => Some(120),
+                Some(120) => None,
+                _ => Some(30),
+            };
+            viewer.set_fps_cap(fps_cap);
+            match fps_cap {
+                Some(fps) => println!("FPS cap: {}", fps),
+                None => println!("FPS cap: uncapped"),
+            }
+        }
+        recolor
+    }
+
+    // Stills, scenes and locations written out or read back
+    fn handle_export_keys(&mut self, viewer: &mut ViewerHandler, frame_handler: &mut FrameHandler, export_handler: &mut ExportHandler,
+                          options: &Options) -> Result<()> {
+        if viewer.is_key_pressed(Key::U) {
+            let sharpen = if export_handler.sharpen().is_some() { None } else { Some(EXPORT_SHARPEN) };
+            export_handler.set_sharpen(sharpen);
+            println!("Export sharpening {}", if sharpen.is_some() { "enabled" } else { "disabled" });
+        }
+        if viewer.is_key_pressed(Key::F7) {
+            self.grid_in_exports = !self.grid_in_exports;
+            println!("Grid in exports {}", if self.grid_in_exports { "enabled" } else { "disabled" });
+        }
+        if viewer.is_key_pressed(Key::Slash) {
+            let color_handler = frame_handler.color_handler_mut();
+            let transparent = !color_handler.transparent_interior();
+            color_handler.set_transparent_interior(transparent);
+            println!("Transparent interior in exports {}", if transparent { "enabled" } else { "disabled" });
+        }
+        if viewer.is_key_pressed(Key::F11) {
+            self.sixteen_bit_exports = !self.sixteen_bit_exports;
+            println!("16-bit PNG export {}", if self.sixteen_bit_exports { "enabled" } else { "disabled" });
+        }
+        if viewer.is_key_pressed(Key::W) {
+            if self.sixteen_bit_exports {
+                // Recalculated at full resolution, the grid overlay isn't drawn at this depth
+                let mut export_frame = self.frame(self.width, self.height);
+                let sample_iterations = if self.anti_aliasing.factor > 1 {
+                    export_frame.calculate_supersampled(&self.anti_aliasing)
+                } else {
+                    vec![export_frame.calculate()]
+                };
+                let colors = frame_handler.render_precise(&sample_iterations, export_frame.max_iterations, 1);
+                export_handler.export_png16(&colors, "render.png")?;
+            } else if self.show_grid && self.grid_in_exports {
+                let mut view = MandelbrotFrame::new(self.width, self.height);
+                view.aspect_policy = self.aspect_policy;
+                view.rotation_radians = self.rotation;
+                view.set_view(self.center_x, self.center_y, self.zoom);
+                export_handler.export_png(&frame_handler.draw_grid(&view), "render.png")?;
+            } else {
+                export_handler.export_png(frame_handler.get_draw_target(), "render.png")?;
+            }
+            println!("Frame exported to render.png");
+        }
+        if viewer.is_key_pressed(Key::N) {
+            if viewer.is_key_down(Key::LeftShift) || viewer.is_key_down(Key::RightShift) {
+                match load_project("scene.mandel") {
+                    Ok(project) => {
+                        self.load_project(project, frame_handler);
+                        println!("Scene loaded from scene.mandel");
+                    }
+                    Err(e) => println!("Could not load scene.mandel: {}", e),
+                }
+            } else {
+                match save_project(&self.project(frame_handler), "scene.mandel") {
+                    Ok(()) => println!("Scene saved to scene.mandel"),
+                    Err(e) => println!("Could not save scene.mandel: {}", e),
+                }
+            }
+        }
+        if viewer.is_key_pressed(Key::F) {
+            let view_options = Options {
+                center: (self.center_x, self.center_y),
+                zoom: self.zoom,
+                iterations: Some(self.iterations()),
+                anti_aliasing: Some(self.anti_aliasing),
+                aa_boundary: self.anti_aliasing.boundary_radius,
+                disable_high_precision: Some(self.disable_high_precision),
+                scheme: frame_handler.color_handler().scheme(),
+                aspect_policy: self.aspect_policy,
+                escape_smoothing: self.escape_smoothing,
+                smoothing: frame_handler.iteration_smoothing(),
+                rotation: self.rotation.to_degrees(),
+                standard_float: self.standard_float,
+                capped_lookahead: self.capped_lookahead,
+                transparent: frame_handler.color_handler().transparent_interior(),
+                cycle: frame_handler.color_handler().cycle_length(),
+                kind: self.kind,
+                formula: self.formula,
+                power: self.power,
+                reference_strategy: options.reference_strategy,
+                perturbation: options.perturbation,
+                glitch_passes: options.glitch_passes,
+                ..Options::default()
+            };
+            println!("\n{}", view_options.to_args());
+        }
+        if viewer.is_key_pressed(Key::Y) {
+            let mut location = Location::from_view((self.center_x, self.center_y), self.zoom, self.iterations());
+            if let Some((re, im)) = &self.exact_center {
+                (location.center_re, location.center_im) = (re.clone(), im.clone());
+            }
+            let location = location.to_kfr();
+            match clipboard::copy(&location) {
+                Ok(()) => println!("Location copied to the clipboard"),
+                Err(e) => println!("Could not copy to the clipboard ({}), location:\n{}", e, location),
+            }
+        }
+        Ok(())
+    }
+
+    // The scene saved by N, with the view's iterations
+    fn project(&self, frame_handler: &FrameHandler) -> Project {
+        let color_handler = frame_handler.color_handler();
+        Project {
+            view: ViewState {
+                center: (self.center_x, self.center_y),
+                zoom: self.zoom,
+                iterations: self.iterations(),
+                kind: self.kind,
+            },
+            scheme: color_handler.scheme(),
+            palette: color_handler.selected_palette(),
+            gradient: color_handler.gradient().map(|stops| stops.to_vec()),
+            cycle_length: color_handler.cycle_length(),
+            escape_smoothing: self.escape_smoothing,
+            iteration_smoothing: frame_handler.iteration_smoothing(),
+            aspect_policy: self.aspect_policy,
+            anti_aliasing: self.anti_aliasing,
+            formula: self.formula,
+            power: self.power,
+            disable_high_precision: self.disable_high_precision,
+        }
+    }
+
+    // Restores a scene loaded by Shift+N, whose iterations are kept fixed
+    fn load_project(&mut self, project: Project, frame_handler: &mut FrameHandler) {
+        (self.center_x, self.center_y) = project.view.center;
+        self.zoom = project.view.zoom;
+        self.base_iterations = project.view.iterations;
+        self.fixed_iterations = true;
+        frame_handler.set_iteration_smoothing(project.iteration_smoothing);
+        self.escape_smoothing = project.escape_smoothing;
+        self.aspect_policy = project.aspect_policy;
+        self.anti_aliasing = project.anti_aliasing;
+        self.kind = project.view.kind;
+        self.formula = project.formula;
+        self.power = project.power;
+        self.disable_high_precision = project.disable_high_precision;
+        let color_handler = frame_handler.color_handler_mut();
+        color_handler.set_scheme(project.scheme);
+        color_handler.set_palette(project.palette);
+        color_handler.set_gradient(project.gradient);
+        color_handler.set_cycle_length(project.cycle_length);
+    }
+
+    // Animation nodes, bookmarks and the animations made from them
+    fn handle_animation_keys(&mut self, viewer: &mut ViewerHandler, frame_handler: &mut FrameHandler, animation_handler: &mut AnimationHandler,
+                             options: &Options) {
+        if viewer.is_key_pressed(Key::S) {
+            let current_time = self.start_time.elapsed().as_secs_f64();
+            animation_handler.set_start_node(self.center_x, self.center_y, current_time, self.zoom);
+            println!("Start node set at ({:.3}, {:.3}) with zoom {}", self.center_x, self.center_y, format_zoom(self.zoom));
+        }
+        if viewer.is_key_pressed(Key::E) {
+            let current_time = self.start_time.elapsed().as_secs_f64();
+            animation_handler.set_end_node(self.center_x, self.center_y, current_time, self.zoom);
+            println!("End node set at ({:.3}, {:.3}) with zoom {}", self.center_x, self.center_y, format_zoom(self.zoom));
+        }
+        if viewer.is_key_pressed(Key::C) {
+            animation_handler.clear_nodes();
+            println!("Animation nodes cleared");
+        }
+        if viewer.is_key_pressed(Key::K) {
+            let iterations = self.iterations();
+            self.bookmarks.push(Bookmark { position: Position { x: self.center_x, y: self.center_y }, zoom: self.zoom, iterations });
+            println!("Bookmark {} saved at ({:.3}, {:.3}) with zoom {}", self.bookmarks.len(), self.center_x, self.center_y, format_zoom(self.zoom));
+        }
+        if viewer.is_key_pressed(Key::L) {
+            self.bookmarks.clear();
+            println!("Bookmarks cleared");
+        }
+        if viewer.is_key_pressed(Key::PageUp) {
+            self.hop_duration += HOP_DURATION_STEP;
+            println!("Time between bookmarks: {:.1}s", self.hop_duration);
+        }
+        if viewer.is_key_pressed(Key::PageDown) && self.hop_duration > HOP_DURATION_STEP {
+            self.hop_duration -= HOP_DURATION_STEP;
+            println!("Time between bookmarks: {:.1}s", self.hop_duration);
+        }
+        if viewer.is_key_pressed(Key::V) {
+            if self.bookmarks.len() >= 2 {
+                println!("Creating fly-through of {} bookmarks...", self.bookmarks.len());
+                let mut animation_frame = self.frame(self.width, self.height);
+                // Iterations follow each frame's zoom unless they are fixed
+                animation_handler.set_zoom_iterations((!self.fixed_iterations).then_some(self.base_iterations));
+                let nodes = nodes_from_bookmarks(&self.bookmarks, self.hop_duration);
+                match animation_handler.create_keyframe_animation(frame_handler, &mut animation_frame, &nodes) {
+                    Ok(()) => println!("Fly-through created!"),
+                    Err(e) => println!("Could not create fly-through: {}", e),
+                }
+            } else {
+                println!("Please save at least two bookmarks first");
+            }
+        }
+        if viewer.is_key_pressed(Key::O) {
+            println!("Creating Julia morph animation...");
+            let mut animation_frame = self.frame(self.width, self.height);
+            let path = animation_handler.node_path().unwrap_or(JULIA_MORPH_PATH);
+            match animation_handler.create_julia_morph(frame_handler, &mut animation_frame, path, JULIA_MORPH_DURATION) {
+                Ok(()) => println!("Julia morph animation created!"),
+                Err(e) => println!("Could not create Julia morph animation: {}", e),
+            }
+        }
+        if viewer.is_key_pressed(Key::D) {
+            let dither_mode = match animation_handler.dither_mode() {
+                DitherMode::None => DitherMode::Ordered,
+                DitherMode::Ordered => DitherMode::Temporal,
+                DitherMode::Temporal => DitherMode::None,
+            };
+            animation_handler.set_dither_mode(dither_mode);
+            println!("Animation dithering: {:?}", dither_mode);
+        }
+        if viewer.is_key_pressed(Key::T) {
+            let auto_exposure = if animation_handler.has_auto_exposure() { None } else { Some(ANIMATION_EXPOSURE) };
+            animation_handler.set_auto_exposure(auto_exposure);
+            println!("Animation auto-exposure {}", if auto_exposure.is_some() { "enabled" } else { "disabled" });
+        }
+        if viewer.is_key_pressed(Key::Delete) {
+            let decay = match frame_handler.orbit_trail() {
+                Some(_) => None,
+                None => Some(options.orbit_trail.unwrap_or(ORBIT_TRAIL_DECAY)),
+            };
+            frame_handler.set_orbit_trail(decay);
+            match decay {
+                Some(decay) => println!("Animation orbit trail: decay {}", decay),
+                None => println!("Animation orbit trail: off"),
+            }
+        }
+        if viewer.is_key_pressed(Key::F5) {
+            let speed = match animation_handler.encoding_speed() {
+                1 => 10,
+                10 => 30,
+                _ => 1,
+            };
+            animation_handler.set_encoding_speed(speed);
+            println!("GIF encoding speed: {}", speed);
+        }
+        if viewer.is_key_pressed(Key::F6) {
+            let fixed_palette = if animation_handler.has_fixed_palette() {
+                None
+            } else {
+                Some(FixedPalette::new(&frame_handler.color_handler().palette()))
+            };
+            println!("Indexed GIF encoding {}", if fixed_palette.is_some() { "enabled" } else { "disabled" });
+            animation_handler.set_fixed_palette(fixed_palette);
+        }
+        if viewer.is_key_pressed(Key::A) {
+            if animation_handler.has_start_node() && animation_handler.has_end_node() {
+                println!("Creating animation...");
+                // A fresh frame for the animation with the current settings
+                let mut animation_frame = self.frame(self.width, self.height);
+                // Iterations follow each frame's zoom unless they are fixed
+                animation_handler.set_zoom_iterations((!self.fixed_iterations).then_some(self.base_iterations));
+                match animation_handler.create_animation(frame_handler, &mut animation_frame) {
+                    Ok(()) => println!("Animation created!"),
+                    Err(e) => println!("Could not create animation: {}", e),
+                }
+            } else {
+                println!("Please set both start and end nodes first");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fitting_the_whole_set_keeps_iterating() {
+        for aspect_policy in [AspectPolicy::Stretch, AspectPolicy::Fit, AspectPolicy::Fill] {
+            let (_, _, zoom) = fit_whole_set(FractalKind::Mandelbrot, Formula::Mandelbrot, 800, 600, aspect_policy);
+            assert!(zoom < 1.0, "{:?} fits the whole set at zoom {}", aspect_policy, zoom);
+            assert!(view_iterations(ITERATION_PRESETS[0], zoom, false) > 0, "{:?} leaves no iterations", aspect_policy);
+        }
+    }
+}
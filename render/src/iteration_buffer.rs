@@ -0,0 +1,63 @@
+use std::fs;
+use crate::error::{Result, RustybrotError};
+
+// Start of every saved buffer, followed by the little-endian header fields
+const MAGIC: &[u8; 4] = b"MITR";
+const BUFFER_VERSION: u32 = 1;
+const HEADER_SIZE: usize = 4 + 4 * 4 + 8;
+
+// A calculated iteration buffer, saved so it can be colored again without
+// recalculating it
+pub struct SavedBuffer {
+    pub width: u32,
+    pub height: u32,
+    pub max_iterations: u32,
+    pub calculation_seconds: f64,  // How long the buffer took to calculate
+    pub iterations: Vec<f32>,
+}
+
+pub fn save_buffer(buffer: &SavedBuffer, path: &str) -> Result<()> {
+    let mut bytes = Vec::with_capacity(HEADER_SIZE + buffer.iterations.len() * 4);
+    bytes.extend_from_slice(MAGIC);
+    for field in [BUFFER_VERSION, buffer.width, buffer.height, buffer.max_iterations] {
+        bytes.extend_from_slice(&field.to_le_bytes());
+    }
+    bytes.extend_from_slice(&buffer.calculation_seconds.to_le_bytes());
+    for value in &buffer.iterations {
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    Ok(fs::write(path, bytes)?)
+}
+
+pub fn load_buffer(path: &str) -> Result<SavedBuffer> {
+    let invalid = |message: String| RustybrotError::Encoding(message.into());
+    let bytes = fs::read(path)?;
+    if bytes.len() < HEADER_SIZE || &bytes[..4] != MAGIC {
+        return Err(invalid(format!("{} is not a saved iteration buffer", path)));
+    }
+
+    let field = |index: usize| u32::from_le_bytes(le_bytes(&bytes, 4 + index * 4));
+    let version = field(0);
+    if version > BUFFER_VERSION {
+        return Err(invalid(format!("Buffer version {} is newer than this viewer supports ({})", version, BUFFER_VERSION)));
+    }
+    let (width, height, max_iterations) = (field(1), field(2), field(3));
+    let calculation_seconds = f64::from_le_bytes(le_bytes(&bytes, HEADER_SIZE - 8));
+
+    let values = &bytes[HEADER_SIZE..];
+    if values.len() != width as usize * height as usize * 4 {
+        return Err(invalid(format!("Buffer holds {} bytes of values but is {}x{}", values.len(), width, height)));
+    }
+    let iterations = values.chunks_exact(4)
+        .map(|value| f32::from_le_bytes(le_bytes(value, 0)))
+        .collect();
+
+    Ok(SavedBuffer { width, height, max_iterations, calculation_seconds, iterations })
+}
+
+// The N bytes at an offset the caller has checked is in range
+fn le_bytes<const N: usize>(bytes: &[u8], offset: usize) -> [u8; N] {
+    let mut array = [0; N];
+    array.copy_from_slice(&bytes[offset..offset + N]);
+    array
+}
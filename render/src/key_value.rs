@@ -0,0 +1,352 @@
+use std::fmt;
+use serde::de::{self, DeserializeSeed, IntoDeserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{self, Impossible, Serialize, SerializeSeq, SerializeStruct, SerializeTuple};
+use serde::{forward_to_deserialize_any, Deserialize};
+
+// Serde format of `key = value` lines, as .mandel project files are written.
+// It holds one flat struct: numbers, strings and unit enum variants are
+// written as is, tuples as space separated values on one line, None fields
+// are left out, and sequences repeat their key once per element. Reading
+// ignores blank lines, `#` comments and keys the struct doesn't have.
+
+#[derive(Debug)]
+pub struct Error(String);
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(message: T) -> Self {
+        Error(message.to_string())
+    }
+}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(message: T) -> Self {
+        Error(message.to_string())
+    }
+}
+
+pub fn to_string<T: Serialize>(value: &T) -> Result<String, Error> {
+    Ok(value.serialize(Serializer)?.concat())
+}
+
+// The file's fields as (key, value) pairs, keys lowercased, in file order
+pub fn parse_fields(text: &str) -> Vec<(String, String)> {
+    text.lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim().to_ascii_lowercase(), value.trim().to_string()))
+        .collect()
+}
+
+pub fn from_fields<'de, T: Deserialize<'de>>(fields: &'de [(String, String)]) -> Result<T, Error> {
+    T::deserialize(Document { fields })
+}
+
+// Serializes a struct into its lines and anything else into its values, one
+// per line it takes
+struct Serializer;
+
+impl Serializer {
+    fn value(value: impl fmt::Display) -> Result<Vec<String>, Error> {
+        Ok(vec![value.to_string()])
+    }
+
+    fn unsupported<T>(what: &str) -> Result<T, Error> {
+        Err(Error(format!("{} can't be written as a key = value field", what)))
+    }
+}
+
+impl ser::Serializer for Serializer {
+    type Ok = Vec<String>;
+    type Error = Error;
+    type SerializeSeq = Values;
+    type SerializeTuple = Values;
+    type SerializeTupleStruct = Impossible<Vec<String>, Error>;
+    type SerializeTupleVariant = Impossible<Vec<String>, Error>;
+    type SerializeMap = Impossible<Vec<String>, Error>;
+    type SerializeStruct = Lines;
+    type SerializeStructVariant = Impossible<Vec<String>, Error>;
+
+    fn serialize_bool(self, value: bool) -> Result<Vec<String>, Error> { Self::value(value) }
+    fn serialize_i8(self, value: i8) -> Result<Vec<String>, Error> { Self::value(value) }
+    fn serialize_i16(self, value: i16) -> Result<Vec<String>, Error> { Self::value(value) }
+    fn serialize_i32(self, value: i32) -> Result<Vec<String>, Error> { Self::value(value) }
+    fn serialize_i64(self, value: i64) -> Result<Vec<String>, Error> { Self::value(value) }
+    fn serialize_u8(self, value: u8) -> Result<Vec<String>, Error> { Self::value(value) }
+    fn serialize_u16(self, value: u16) -> Result<Vec<String>, Error> { Self::value(value) }
+    fn serialize_u32(self, value: u32) -> Result<Vec<String>, Error> { Self::value(value) }
+    fn serialize_u64(self, value: u64) -> Result<Vec<String>, Error> { Self::value(value) }
+    fn serialize_f32(self, value: f32) -> Result<Vec<String>, Error> { Self::value(value) }
+    fn serialize_f64(self, value: f64) -> Result<Vec<String>, Error> { Self::value(value) }
+    fn serialize_char(self, value: char) -> Result<Vec<String>, Error> { Self::value(value) }
+
+    // Values run to the end of their line
+    fn serialize_str(self, value: &str) -> Result<Vec<String>, Error> {
+        if value.contains('\n') {
+            return Self::unsupported("A string with a line break");
+        }
+        Self::value(value)
+    }
+
+    fn serialize_bytes(self, _value: &[u8]) -> Result<Vec<String>, Error> {
+        Self::unsupported("A byte string")
+    }
+
+    fn serialize_none(self) -> Result<Vec<String>, Error> {
+        Ok(Vec::new())
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Vec<String>, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Vec<String>, Error> {
+        Self::unsupported("A unit value")
+    }
+
+    fn serialize_unit_struct(self, name: &'static str) -> Result<Vec<String>, Error> {
+        Self::unsupported(name)
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _index: u32, variant: &'static str) -> Result<Vec<String>, Error> {
+        Self::value(variant)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(self, _name: &'static str, value: &T) -> Result<Vec<String>, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(self, name: &'static str, _index: u32, _variant: &'static str, _value: &T) -> Result<Vec<String>, Error> {
+        Self::unsupported(name)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Values, Error> {
+        Ok(Values { values: Vec::new(), joined: false })
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Values, Error> {
+        Ok(Values { values: Vec::new(), joined: true })
+    }
+
+    fn serialize_tuple_struct(self, name: &'static str, _len: usize) -> Result<Self::SerializeTupleStruct, Error> {
+        Self::unsupported(name)
+    }
+
+    fn serialize_tuple_variant(self, name: &'static str, _index: u32, _variant: &'static str, _len: usize) -> Result<Self::SerializeTupleVariant, Error> {
+        Self::unsupported(name)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        Self::unsupported("A map")
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Lines, Error> {
+        Ok(Lines(Vec::new()))
+    }
+
+    fn serialize_struct_variant(self, name: &'static str, _index: u32, _variant: &'static str, _len: usize) -> Result<Self::SerializeStructVariant, Error> {
+        Self::unsupported(name)
+    }
+}
+
+// Elements of a sequence, one line each, or of a tuple, joined into one line
+struct Values {
+    values: Vec<String>,
+    joined: bool,
+}
+
+impl Values {
+    fn push<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        match value.serialize(Serializer)?[..] {
+            [ref element] if !element.contains(' ') || !self.joined => self.values.push(element.clone()),
+            [_] => return Serializer::unsupported("A tuple element with a space"),
+            _ => return Serializer::unsupported("A nested sequence or optional element"),
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Vec<String> {
+        if self.joined { vec![self.values.join(" ")] } else { self.values }
+    }
+}
+
+impl SerializeSeq for Values {
+    type Ok = Vec<String>;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Vec<String>, Error> {
+        Ok(self.finish())
+    }
+}
+
+impl SerializeTuple for Values {
+    type Ok = Vec<String>;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Vec<String>, Error> {
+        Ok(self.finish())
+    }
+}
+
+// The `key = value` lines of a struct's fields
+struct Lines(Vec<String>);
+
+impl SerializeStruct for Lines {
+    type Ok = Vec<String>;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, key: &'static str, value: &T) -> Result<(), Error> {
+        for value in value.serialize(Serializer)? {
+            self.0.push(format!("{} = {}\n", key, value));
+        }
+        Ok(())
+    }
+
+    fn end(self) -> Result<Vec<String>, Error> {
+        Ok(self.0)
+    }
+}
+
+// A whole file, read as a struct whose fields are its keys
+struct Document<'de> {
+    fields: &'de [(String, String)],
+}
+
+impl<'de> de::Deserializer<'de> for Document<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        // Keys in order of first appearance, each with all its values
+        let mut keys: Vec<(&str, Vec<&str>)> = Vec::new();
+        for (key, value) in self.fields {
+            match keys.iter_mut().find(|(existing, _)| existing == key) {
+                Some((_, values)) => values.push(value),
+                None => keys.push((key, vec![value])),
+            }
+        }
+        visitor.visit_map(Fields { keys: keys.into_iter(), values: None })
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char str string bytes byte_buf option unit
+        unit_struct newtype_struct seq tuple tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+struct Fields<'de> {
+    keys: std::vec::IntoIter<(&'de str, Vec<&'de str>)>,
+    values: Option<Vec<&'de str>>,
+}
+
+impl<'de> MapAccess<'de> for Fields<'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, Error> {
+        let Some((key, values)) = self.keys.next() else {
+            return Ok(None);
+        };
+        self.values = Some(values);
+        seed.deserialize(key.into_deserializer()).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let values = self.values.take().ok_or_else(|| Error("Value read before its key".to_string()))?;
+        seed.deserialize(Field { values })
+    }
+}
+
+// Every value a key was given, in file order
+struct Field<'de> {
+    values: Vec<&'de str>,
+}
+
+impl<'de> Field<'de> {
+    fn single(&self) -> Result<&'de str, Error> {
+        match self.values[..] {
+            [value] => Ok(value),
+            _ => Err(Error(format!("Expected one value, got {:?}", self.values))),
+        }
+    }
+
+    fn parse<T: std::str::FromStr>(&self) -> Result<T, Error> {
+        let value = self.single()?;
+        value.parse().map_err(|_| Error(format!("Invalid {} {:?}", std::any::type_name::<T>(), value)))
+    }
+}
+
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident),*) => {
+        $(fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+            visitor.$visit(self.parse()?)
+        })*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for Field<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_borrowed_str(self.single()?)
+    }
+
+    deserialize_parsed! {
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8, deserialize_i16 => visit_i16, deserialize_i32 => visit_i32, deserialize_i64 => visit_i64,
+        deserialize_u8 => visit_u8, deserialize_u16 => visit_u16, deserialize_u32 => visit_u32, deserialize_u64 => visit_u64,
+        deserialize_f32 => visit_f32, deserialize_f64 => visit_f64
+    }
+
+    // A key that's present has a value
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_seq(Elements(self.values.into_iter()))
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_seq(Elements(self.single()?.split_whitespace().collect::<Vec<_>>().into_iter()))
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(self, _name: &'static str, _variants: &'static [&'static str], visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_enum(self.single()?.into_deserializer())
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        char str string bytes byte_buf unit unit_struct tuple_struct map struct identifier
+    }
+}
+
+struct Elements<'de>(std::vec::IntoIter<&'de str>);
+
+impl<'de> SeqAccess<'de> for Elements<'de> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, Error> {
+        self.0.next().map(|value| seed.deserialize(Field { values: vec![value] })).transpose()
+    }
+}
@@ -0,0 +1,94 @@
+use std::fs;
+use crate::error::{Result, RustybrotError};
+use crate::view_state::{clamp_iterations, clamp_zoom};
+
+// Kalles Fraktaler measures zoom against a view 4 units tall, ours is 2 units tall
+const KFR_ZOOM_SCALE: f64 = 2.0;
+
+// A view location as shared by Kalles Fraktaler (.kfr) and similar tools. The
+// center keeps its original decimal strings so no precision is lost on import.
+pub struct Location {
+    pub center_re: String,
+    pub center_im: String,
+    pub zoom: f64,
+    pub iterations: Option<u32>,
+}
+
+impl Location {
+    pub fn from_file(path: &str) -> Result<Location> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    // Reads `Key: value` lines, ignoring any fields that aren't understood
+    pub fn parse(text: &str) -> Result<Location> {
+        let mut center_re = None;
+        let mut center_im = None;
+        let mut zoom = None;
+        let mut iterations = None;
+
+        for line in text.lines() {
+            let Some((key, value)) = line.split_once([':', '=']) else {
+                continue;
+            };
+            let value = value.trim();
+
+            match key.trim().to_ascii_lowercase().as_str() {
+                "re" => center_re = Some(Self::parse_decimal(value, "Re")?),
+                "im" => center_im = Some(Self::parse_decimal(value, "Im")?),
+                "zoom" => zoom = Some(Self::parse_zoom(value)?),
+                "iterations" => iterations = Some(clamp_iterations(Self::parse_number::<u32>(value, "Iterations")?)),
+                _ => {}
+            }
+        }
+
+        let missing = |field: &str| RustybrotError::Config(format!("Location is missing the {} field", field));
+        Ok(Location {
+            center_re: center_re.ok_or_else(|| missing("Re"))?,
+            center_im: center_im.ok_or_else(|| missing("Im"))?,
+            zoom: zoom.ok_or_else(|| missing("Zoom"))?,
+            iterations,
+        })
+    }
+
+    pub fn from_view(center: (f64, f64), zoom: f64, iterations: u32) -> Location {
+        Location {
+            center_re: center.0.to_string(),
+            center_im: center.1.to_string(),
+            zoom,
+            iterations: Some(iterations),
+        }
+    }
+
+    // Inverse of parse, in the .kfr field layout
+    pub fn to_kfr(&self) -> String {
+        let mut text = format!("Re: {}\r\nIm: {}\r\nZoom: {}\r\n", self.center_re, self.center_im, self.zoom / KFR_ZOOM_SCALE);
+        if let Some(iterations) = self.iterations {
+            text.push_str(&format!("Iterations: {}\r\n", iterations));
+        }
+        text
+    }
+
+    // The center rounded to f64 for the viewer
+    pub fn center(&self) -> (f64, f64) {
+        // Both strings were validated as numbers while parsing
+        (self.center_re.parse().unwrap_or(0.0), self.center_im.parse().unwrap_or(0.0))
+    }
+
+    // Clamped into the range the viewer can render, like a loaded project's
+    fn parse_zoom(value: &str) -> Result<f64> {
+        clamp_zoom(Self::parse_number::<f64>(value, "Zoom")? * KFR_ZOOM_SCALE)
+    }
+
+    fn parse_decimal(value: &str, field: &str) -> Result<String> {
+        if !Self::parse_number::<f64>(value, field)?.is_finite() {
+            return Err(RustybrotError::Config(format!("{} value {:?} in location is not a finite number", field, value)));
+        }
+        Ok(value.to_string())
+    }
+
+    fn parse_number<T: std::str::FromStr>(value: &str, field: &str) -> Result<T> {
+        value.parse().map_err(|_| {
+            RustybrotError::Config(format!("Invalid {} value in location: {:?}", field, value))
+        })
+    }
+}
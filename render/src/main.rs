@@ -3,133 +3,568 @@ mod frame_handler;
 mod color_handler;
 mod viewer_handler;
 mod animation_handler;
+mod buddhabrot;
+mod clipboard;
+mod components;
+mod curves;
+mod error;
+mod export_handler;
+mod exr;
+mod iim;
+mod input_handler;
+mod iteration_buffer;
+mod key_value;
+mod location;
+mod options;
+mod pixel_format;
+mod project;
+mod quality;
+mod render_worker;
+mod stress;
+mod temporal;
+mod tile_handler;
+mod view_state;
 
-use mandelbrot::MandelbrotFrame;
-use frame_handler::FrameHandler;
+use mandelbrot::{diff_render, iterations_for_zoom, MandelbrotFrame};
+use mandelbrot::{DEFAULT_PERIODICITY, VIEW_HALF_WIDTH};
+use frame_handler::{ColoringMode, DensityScale, FrameHandler, HISTOGRAM_BINS, THUMBNAIL_SIZE};
+use color_handler::ColorHandler;
+use error::{Result, RustybrotError};
 use viewer_handler::ViewerHandler;
 use animation_handler::AnimationHandler;
-use minifb::Key;
+use export_handler::ExportHandler;
+use input_handler::{format_zoom, InputHandler, RECOLOR_KEYS};
+use iteration_buffer::{load_buffer, save_buffer, SavedBuffer};
+use options::{parse_options, Options};
+use quality::RenderSettings;
+use render_worker::{RenderJob, RenderOutput, RenderWorker};
+use tile_handler::{TileHandler, TILE_SIZE, VIEW_TILE_SIZE};
 use std::time::Instant;
 
-fn main() -> std::io::Result<()> {
+// Renders one still per factor of ten from the options' zoom toward end_zoom,
+// written as numbered PNGs next to the output. Iterations scale with each
+// still's depth, an --iterations count being taken as the base at zoom 1.
+fn render_zoom_sequence(width: u32, height: u32, options: &Options, end_zoom: f64) -> Result<()> {
+    let stem = options.output.strip_suffix(".png").unwrap_or(&options.output);
+    let decades = (end_zoom / options.zoom).log10();
+    let steps = (decades.abs() + 1e-9).floor() as i32;
+
+    for step in 0..=steps {
+        let zoom = options.zoom * 10f64.powi(step * decades.signum() as i32);
+        let still = Options {
+            zoom,
+            zoom_sequence: None,
+            iterations: options.iterations.map(|base| iterations_for_zoom(base, zoom)),
+            output: format!("{}_{:03}.png", stem, step),
+            ..options.clone()
+        };
+        render_headless(width, height, &still)?;
+    }
+    Ok(())
+}
+
+// The frame and render settings of a headless still, without calculating it
+fn headless_frame(width: u32, height: u32, options: &Options) -> (MandelbrotFrame, RenderSettings) {
+    let mut frame = MandelbrotFrame::new(width, height);
+    let mut settings = options.quality.apply(&mut frame, options.zoom);
+    if let Some(iterations) = options.iterations {
+        frame.max_iterations = iterations;
+    }
+    if let Some(anti_aliasing) = options.anti_aliasing {
+        settings.anti_aliasing = anti_aliasing;
+    }
+    if options.aa_boundary.is_some() {
+        settings.anti_aliasing.boundary_radius = options.aa_boundary;
+    }
+    if let Some(disable_high_precision) = options.disable_high_precision {
+        frame.disable_high_precision = disable_high_precision;
+    }
+    frame.kind = options.kind;
+    frame.formula = options.formula;
+    frame.power = options.power;
+    let density = options.buddhabrot.is_some() || options.iim.is_some();
+    if density {
+        // Density is accumulated at full resolution
+        settings.sample_step = 1;
+    }
+    frame.width = width / settings.sample_step;
+    frame.height = height / settings.sample_step;
+    frame.aspect_policy = options.aspect_policy;
+    frame.rotation_radians = options.rotation.to_radians();
+    frame.sequential = options.sequential;
+    frame.sample_offset = options.sample_origin.offset();
+    frame.standard_float = options.standard_float;
+    frame.reference_strategy = options.reference_strategy;
+    frame.perturbation = options.perturbation;
+    frame.max_glitch_passes = options.glitch_passes;
+    frame.capped_lookahead = options.capped_lookahead;
+    frame.initial_z = options.initial_z;
+    frame.periodicity = options.periodicity;
+    frame.log_polar_decades = options.log_polar;
+    frame.escape_smoothing = options.escape_smoothing.clamp(0.0, 1.0);
+    frame.set_view(options.center.0, options.center.1, options.zoom);
+    
+    if frame.precision_degraded() {
+        println!("Warning: the view is past f64 precision but high precision is disabled, pixels will merge into blocks");
+    }
+    // Density renders trace orbits in f64 whatever the zoom, so high precision's budget doesn't apply
+    let budget_step = frame.precision_budget_step();
+    if budget_step > 1 && !options.full_quality && !density {
+        println!("Warning: high precision frame over the time budget, rendering at 1/{} resolution (pass --full-quality to override)", budget_step);
+        settings.sample_step *= budget_step;
+        frame.width /= budget_step;
+        frame.height /= budget_step;
+        frame.set_view(options.center.0, options.center.1, options.zoom);
+    }
+    (frame, settings)
+}
+
+// Renders where two sets of options give different iteration values, colored
+// by render_difference, and prints how many pixels differ
+fn render_diff(width: u32, height: u32, options: &Options, variant: &Options) -> Result<()> {
+    let (mut frame, settings) = headless_frame(width, height, options);
+    let (mut variant_frame, _) = headless_frame(width, height, variant);
+    println!("Rendering the difference to {}...", options.output);
+    let difference = diff_render(&mut frame, &mut variant_frame)?;
+
+    let differing = difference.iter().filter(|&&delta| delta != 0.0).count();
+    let largest = difference.iter().copied().fold(0.0, f32::max);
+    println!("{} of {} pixels differ, by at most {} iterations", differing, difference.len(), largest);
+
+    let mut frame_handler = FrameHandler::new(width, height);
+    frame_handler.render_difference(&difference, settings.sample_step);
+    ExportHandler::new(width, height).export_png(frame_handler.get_draw_target(), &options.output)
+}
+
+// Color handler with the palette the options name, a project's gradient or
+// else a gradient file taking its place when given. An empty file leaves the
+// default palette.
+fn palette_color_handler(options: &Options) -> Result<ColorHandler> {
+    let Some(path) = &options.palette else {
+        let mut color_handler = ColorHandler::new();
+        color_handler.set_palette(options.color_palette);
+        color_handler.set_gradient(options.gradient.clone());
+        return Ok(color_handler);
+    };
+    Ok(ColorHandler::from_gradient_file(path)?)
+}
+
+// Colors a finished frame's iteration buffers into the frame handler
+fn color_output(frame_handler: &mut FrameHandler, output: &RenderOutput, smooth_high_precision: bool) {
+    let job = &output.job;
+    if job.anti_aliasing.is_some() {
+        frame_handler.render_supersampled(&output.sample_iterations, job.frame.max_iterations);
+    } else {
+        let iterations = &output.sample_iterations[0];
+        frame_handler.render_frame(iterations, job.frame.max_iterations, job.sample_step);
+        if smooth_high_precision && job.frame.uses_high_precision() {
+            frame_handler.smooth_frame(iterations, job.frame.max_iterations, job.sample_step);
+        }
+    }
+}
+
+// Colors a saved iteration buffer once per coloring without recalculating it,
+// writing each as a PNG named after the output and the coloring
+fn render_recolors(path: &str, options: &Options) -> Result<()> {
+    let buffer = load_buffer(path)?;
+    let stem = options.output.strip_suffix(".png").unwrap_or(&options.output);
+    println!("Recoloring {}x{} buffer {}, calculated in {:.2}s", buffer.width, buffer.height, path, buffer.calculation_seconds);
+
+    let mut frame_handler = FrameHandler::new(buffer.width, buffer.height);
+    frame_handler.set_color_handler(palette_color_handler(options)?);
+    frame_handler.set_iteration_smoothing(options.smoothing);
+    frame_handler.set_color_curves(options.curves.clone());
+    frame_handler.set_coloring_mode(options.coloring_mode);
+    frame_handler.color_handler_mut().set_transparent_interior(options.transparent);
+    let mut export_handler = ExportHandler::new(buffer.width, buffer.height);
+    export_handler.set_alpha_mode(options.alpha);
+
+    for &(scheme, cycle) in &options.colorings {
+        let start = Instant::now();
+        let color_handler = frame_handler.color_handler_mut();
+        color_handler.set_scheme(scheme);
+        color_handler.set_cycle_length(cycle);
+        frame_handler.render_frame(&buffer.iterations, buffer.max_iterations, 1);
+
+        let name = format!("{:?}", scheme).to_lowercase();
+        let output = match cycle {
+            Some(length) => format!("{}_{}_cycle{}.png", stem, name, length),
+            None => format!("{}_{}.png", stem, name),
+        };
+        export_handler.export_png(frame_handler.get_draw_target(), &output)?;
+        let seconds = start.elapsed().as_secs_f64();
+        println!("  {} in {:.1}ms, {:.0}x faster than calculating", output, seconds * 1000.0,
+                 buffer.calculation_seconds / seconds.max(f64::EPSILON));
+    }
+    Ok(())
+}
+
+fn render_headless(width: u32, height: u32, options: &Options) -> Result<()> {
+    if let Some(path) = &options.recolor {
+        return render_recolors(path, options);
+    }
+    if let Some(end_zoom) = options.zoom_sequence {
+        return render_zoom_sequence(width, height, options, end_zoom);
+    }
+    if let Some(out_dir) = &options.tiles {
+        println!("Rendering {} tile levels to {}...", options.levels, out_dir);
+        let mut tile_handler = TileHandler::new(options.quality, options.tile_cache);
+        tile_handler.set_tile_size(options.tile_size);
+        return tile_handler.write_pyramid(out_dir, options.center, VIEW_HALF_WIDTH / options.zoom, options.levels);
+    }
+    if let Some((image_width, image_height)) = options.image_size {
+        if !options.output.to_lowercase().ends_with(".pam") {
+            return Err(RustybrotError::Config(format!("Tiled images are written as PAM, name the output .pam rather than {}", options.output)));
+        }
+        println!("Rendering {}x{} image to {} in {} pixel tiles...", image_width, image_height, options.output, options.tile_size);
+        let mut tile_handler = TileHandler::new(options.quality, options.tile_cache);
+        tile_handler.set_tile_size(options.tile_size);
+        return tile_handler.write_image(&options.output, options.center, VIEW_HALF_WIDTH / options.zoom, image_width, image_height);
+    }
+    
+    let mut frame_handler = FrameHandler::new(width, height);
+    frame_handler.set_color_handler(palette_color_handler(options)?);
+    frame_handler.set_iteration_smoothing(options.smoothing);
+    frame_handler.set_color_curves(options.curves.clone());
+    frame_handler.set_coloring_mode(options.coloring_mode);
+    frame_handler.color_handler_mut().set_scheme(options.scheme);
+    frame_handler.color_handler_mut().set_cycle_length(options.cycle);
+    frame_handler.color_handler_mut().set_transparent_interior(options.transparent);
+    let mut export_handler = ExportHandler::new(width, height);
+    export_handler.set_alpha_mode(options.alpha);
+    
+    let (mut frame, settings) = headless_frame(width, height, options);
+    
+    if let Some(samples) = options.buddhabrot {
+        println!("Rendering {} sample Buddhabrot to {}...", samples, options.output);
+        let density = buddhabrot::accumulate(&frame, samples);
+        frame_handler.render_density(&density, DensityScale::Sqrt);
+        return export_handler.export_png(frame_handler.get_draw_target(), &options.output);
+    }
+    
+    if let Some(walks) = options.iim {
+        println!("Rendering {} walk inverse iteration Julia set to {}...", walks, options.output);
+        let density = iim::accumulate(&frame, walks)?;
+        frame_handler.render_density(&density, DensityScale::Log);
+        return export_handler.export_png(frame_handler.get_draw_target(), &options.output);
+    }
+    
+    if let Some(style) = options.escape_angle {
+        println!("Rendering external angles to {}...", options.output);
+        let iterations = frame.calculate();
+        let angles = frame.calculate_escape_angles();
+        frame_handler.render_escape_angles(&iterations, &angles, frame.max_iterations, settings.sample_step, style);
+        return export_handler.export_png(frame_handler.get_draw_target(), &options.output);
+    }
+    
+    if options.coloring_mode == ColoringMode::DistanceEstimate {
+        println!("Rendering distance estimates to {}...", options.output);
+        frame.distance_estimation = true;
+        let iterations = frame.calculate();
+        let distances = frame.take_distance_estimates().unwrap_or_else(|| vec![f32::INFINITY; iterations.len()]);
+        frame_handler.render_distance_estimates(&iterations, &distances, frame.max_iterations, settings.sample_step);
+        return export_handler.export_png(frame_handler.get_draw_target(), &options.output);
+    }
+    
+    if let Some(path) = &options.thumbnail {
+        let thumbnail = frame_handler.render_thumbnail(&frame, THUMBNAIL_SIZE)?;
+        let mut thumbnail_export = ExportHandler::new(thumbnail.width, thumbnail.height);
+        thumbnail_export.set_alpha_mode(options.alpha);
+        thumbnail_export.export_rgba8(&thumbnail.rgba, path)?;
+        println!("Thumbnail written to {}", path);
+    }
+    
+    println!("Rendering {:?} quality still to {}...", options.quality, options.output);
+    frame.distance_estimation = options.exr.is_some();
+    let supersample = settings.anti_aliasing.factor > 1 && settings.sample_step == 1;
+    let calculation_start = Instant::now();
+    let sample_iterations = if supersample {
+        frame.calculate_supersampled(&settings.anti_aliasing)
+    } else {
+        vec![frame.calculate()]
+    };
+    if let Some(path) = &options.save_buffer {
+        // Supersampled stills save their first sample, recolors aren't anti-aliased
+        let buffer = SavedBuffer {
+            width: frame.width,
+            height: frame.height,
+            max_iterations: frame.max_iterations,
+            calculation_seconds: calculation_start.elapsed().as_secs_f64(),
+            iterations: sample_iterations[0].clone(),
+        };
+        save_buffer(&buffer, path)?;
+        println!("Iteration buffer saved to {}", path);
+    }
+    if let Some(path) = &options.exr {
+        // Supersampled stills write their first sample, like saved buffers
+        let angles = frame.calculate_escape_angles();
+        let distances = frame.take_distance_estimates().unwrap_or_else(|| vec![f32::INFINITY; angles.len()]);
+        exr::write_exr(path, frame.width, frame.height, &[("iteration", &sample_iterations[0]), ("angle", &angles), ("distance", &distances)])?;
+        println!("Escape values written to {}", path);
+    }
+    
+    if options.sixteen_bit {
+        let colors = frame_handler.render_precise(&sample_iterations, frame.max_iterations, settings.sample_step);
+        return export_handler.export_png16(&colors, &options.output);
+    }
+    
+    if supersample {
+        frame_handler.render_supersampled(&sample_iterations, frame.max_iterations);
+    } else {
+        frame_handler.render_frame(&sample_iterations[0], frame.max_iterations, settings.sample_step);
+    }
+    export_handler.export_png(frame_handler.get_draw_target(), &options.output)
+}
+
+fn main() -> Result<()> {
     let width = 800;
     let height = 600;
     
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = parse_options(&args)?;
+    if options.stress {
+        return stress::run();
+    }
+    if let Some(diff) = &options.diff {
+        // Later options override earlier ones, so the variant is the command line with the diff's appended
+        let variant_args: Vec<String> = args.iter().cloned().chain(diff.split_whitespace().map(String::from)).collect();
+        return render_diff(width, height, &options, &parse_options(&variant_args)?);
+    }
+    if options.headless {
+        return render_headless(width, height, &options);
+    }
+    run_viewer(width, height, &options)
+}
+
+// Opens the viewer and runs it until closed. Keys change the view and settings
+// held by the input handler, and frames are calculated by the render worker.
+fn run_viewer(width: u32, height: u32, options: &Options) -> Result<()> {
     let mut frame_handler = FrameHandler::new(width, height);
-    let mut viewer = ViewerHandler::new(width as usize, height as usize, "Mandelbrot Viewer");
-    let mut animation_handler = AnimationHandler::new(width, height, "animation.gif", 30)?;
-    
-    // Initial view state
-    let mut center_x = -0.5;
-    let mut center_y = 0.0;
-    let mut zoom: f64 = 1.0;
-    let mut base_iterations = 100;
-    
-    // Movement speed control
-    let base_speed = 0.02;
-    
-    // Animation state
-    let start_time = Instant::now();
-    
-    println!("Controls:");
-    println!("Arrow keys: Move around");
-    println!("+/-: Zoom in/out");
-    println!("]/[: Increase/decrease base iterations");
-    println!("Space: Toggle fine movement");
-    println!("S: Set start node for animation");
-    println!("E: Set end node for animation");
-    println!("C: Clear animation nodes");
-    println!("A: Create animation (if start and end nodes are set)");
-    println!("Escape: Exit");
+    frame_handler.set_color_handler(palette_color_handler(options)?);
+    frame_handler.set_orbit_trail(options.orbit_trail);
+    frame_handler.set_color_curves(options.curves.clone());
+    frame_handler.set_coloring_mode(options.coloring_mode);
+    let mut viewer = ViewerHandler::new(width as usize, height as usize, "Mandelbrot Viewer", options.fps_cap)?;
+    let mut animation_handler = AnimationHandler::new(width, height, &options.animation_output, 30)?;
+    animation_handler.set_max_frames(options.max_frames);
+    if let Some(camera_path) = &options.camera_path {
+        animation_handler.set_camera_path(camera_path)?;
+    }
+    let mut export_handler = ExportHandler::new(width, height);
+    let mut input = InputHandler::new(width, height, options);
+    
+    // The viewer's tiles are smaller, as many more are kept in the same memory
+    let mut render_worker = RenderWorker::new(options.tile_cache * (TILE_SIZE / VIEW_TILE_SIZE).pow(2) as usize);
+    let mut displayed: Option<RenderOutput> = None;  // Frame on screen with its iteration buffers
+    let mut submitted_view = (input.center_x, input.center_y, input.zoom);
+    let mut settled = false;  // The idle view has been submitted, nothing to re-render
+    let mut precision_budget_step = 1;  // Last resolution reduction warned about
+    
+    // Observed loop rate, smoothed so the status line is readable
+    let mut last_update = Instant::now();
+    let mut observed_fps = 0.0;
+    
+    input_handler::print_controls();
     
     // Main loop
     while viewer.is_open() {
-        // Handle keyboard input
-        let movement_speed = base_speed / zoom;
-        
-        // Track if any movement or zoom keys are pressed
-        let is_moving = viewer.is_key_down(Key::Left) 
-            || viewer.is_key_down(Key::Right)
-            || viewer.is_key_down(Key::Up)
-            || viewer.is_key_down(Key::Down);
-        let is_zooming = viewer.is_key_down(Key::Equal) || viewer.is_key_down(Key::Minus);
-        let should_record = is_moving || is_zooming;
-        
-        // Handle movement
-        if viewer.is_key_down(Key::Left) { center_x -= movement_speed; }
-        if viewer.is_key_down(Key::Right) { center_x += movement_speed; }
-        if viewer.is_key_down(Key::Up) { center_y -= movement_speed; }
-        if viewer.is_key_down(Key::Down) { center_y += movement_speed; }
-        if viewer.is_key_down(Key::Equal) { zoom *= 1.1; }
-        if viewer.is_key_down(Key::Minus) { zoom /= 1.1; }
-        if viewer.is_key_down(Key::RightBracket) { base_iterations += 10; }
-        if viewer.is_key_down(Key::LeftBracket) && base_iterations > 10 { base_iterations -= 10; }
-
-        // Handle animation controls
-        if viewer.is_key_pressed(Key::S) {
-            let current_time = start_time.elapsed().as_secs_f64();
-            animation_handler.set_start_node(center_x, center_y, current_time, zoom);
-            println!("Start node set at ({:.3}, {:.3}) with zoom {:.1}x", center_x, center_y, zoom);
+        // Palette keys recolor the frame on screen rather than recalculating it
+        let recolor = input.handle_input(&mut viewer, &mut frame_handler, &mut animation_handler, &mut export_handler, options)?;
+        if let (true, Some(output)) = (recolor, &displayed) {
+            color_output(&mut frame_handler, output, input.smooth_high_precision);
         }
-        if viewer.is_key_pressed(Key::E) {
-            let current_time = start_time.elapsed().as_secs_f64();
-            animation_handler.set_end_node(center_x, center_y, current_time, zoom);
-            println!("End node set at ({:.3}, {:.3}) with zoom {:.1}x", center_x, center_y, zoom);
+        let in_motion = input.in_motion;
+        
+        // While idle, changes within the deadzone are floating point noise rather
+        // than movement, so the view snaps back and stops re-rendering once settled
+        let idle = !viewer.has_input_except(&RECOLOR_KEYS);
+        if !idle {
+            settled = false;
         }
-        if viewer.is_key_pressed(Key::C) {
-            animation_handler.clear_nodes();
-            println!("Animation nodes cleared");
-        }
-        if viewer.is_key_pressed(Key::A) {
-            if animation_handler.has_start_node() && animation_handler.has_end_node() {
-                println!("Creating animation...");
-                // Create a fresh MandelbrotFrame for the animation with current settings
-                let mut animation_frame = MandelbrotFrame::new(width, height);
-                animation_frame.max_iterations = base_iterations * (1.0 + zoom.log10() * 2.0) as u32;
-                animation_handler.create_animation(&mut frame_handler, &mut animation_frame)?;
-                println!("Animation created!");
-            } else {
-                println!("Please set both start and end nodes first");
-            }
+        let pixel_size = 2.0 * VIEW_HALF_WIDTH / input.zoom / width as f64;
+        let pan = (input.center_x - submitted_view.0).abs().max((input.center_y - submitted_view.1).abs()) / pixel_size;
+        let zoom_shift = (input.zoom / submitted_view.2 - 1.0).abs() * width as f64 / 2.0;
+        if idle && pan < options.deadzone && zoom_shift < options.deadzone {
+            (input.center_x, input.center_y, input.zoom) = submitted_view;
         }
         
         // Dynamic detail adjustment based on zoom and movement
-        let detail_multiplier = (1.0 + zoom.log10() * 2.0) as u32;
-        let max_iterations = base_iterations * detail_multiplier;
+        let detail_multiplier = input.detail_multiplier();
+        let mut max_iterations = input.iterations();
+        
+        // Moving frames are previews, optionally capped lower for responsiveness
+        let preview_cap = input.preview_iteration_cap.filter(|&cap| in_motion && cap < max_iterations);
+        if let Some(cap) = preview_cap {
+            max_iterations = cap;
+        }
         
         // Adjust sampling based on zoom level and movement/zooming
-        let sample_step = if should_record {
-            if zoom < 10.0 { 2 }
-            else if zoom < 100.0 { 2 }
-            else if zoom < 1000.0 { 3 }
+        let mut sample_step = if in_motion {
+            if input.zoom < 100.0 { 2 }
+            else if input.zoom < 1000.0 { 3 }
             else { 4 }
         } else {
             1
         };
         
-        // Create and update frame
-        let mut frame_calc = MandelbrotFrame::new(width/sample_step, height/sample_step);
-        frame_calc.x_min = center_x - (1.5 / zoom);
-        frame_calc.x_max = center_x + (1.5 / zoom);
-        frame_calc.y_min = center_y - (1.0 / zoom);
-        frame_calc.y_max = center_y + (1.0 / zoom);
+        // Queue the next frame for the worker, it is presented once calculated
+        // A loaded location's decimal center no longer applies once the view has moved off it
+        if input.exact_center.as_ref().is_some_and(|(re, im)| (re.parse(), im.parse()) != (Ok(input.center_x), Ok(input.center_y))) {
+            input.exact_center = None;
+        }
+        let mut frame_calc = input.frame(width/sample_step, height/sample_step);
+        frame_calc.exact_center = input.exact_center.clone();
+        frame_calc.reference_strategy = options.reference_strategy;
+        frame_calc.perturbation = options.perturbation;
+        frame_calc.max_glitch_passes = options.glitch_passes;
         frame_calc.max_iterations = max_iterations;
+        if input.reuse_interior && in_motion {
+            // Periodicity checking is what confirms interior points
+            frame_calc.periodicity = Some(options.periodicity.unwrap_or(DEFAULT_PERIODICITY));
+        }
         
-        // Calculate and render frame
-        let iterations = frame_calc.calculate();
-        frame_handler.render_frame(&iterations, frame_calc.max_iterations, sample_step);
+        // Deep high precision frames drop resolution rather than stall the viewer
+        let budget_step = if options.full_quality { 1 } else { frame_calc.precision_budget_step() };
+        if budget_step != precision_budget_step {
+            if budget_step > 1 {
+                println!("\nWarning: high precision frame over the time budget, rendering at 1/{} resolution (pass --full-quality to override)", budget_step);
+            }
+            precision_budget_step = budget_step;
+        }
+        if budget_step > 1 {
+            sample_step *= budget_step;
+            frame_calc.width /= budget_step;
+            frame_calc.height /= budget_step;
+            frame_calc.set_view(input.center_x, input.center_y, input.zoom);
+        }
+        let units_per_pixel = (frame_calc.x_max - frame_calc.x_min) / width as f64;
+        let precision_degraded = frame_calc.precision_degraded();
         
-        // Update viewer
-        viewer.update(frame_handler.get_draw_target());
+        if !render_worker.is_busy() && !settled {
+            // Supersample only once the view is still
+            let anti_aliasing = (input.anti_aliasing.factor > 1 && sample_step == 1).then_some(input.anti_aliasing);
+            if frame_calc.periodicity.is_some() {
+                frame_calc.known_interior = displayed.as_mut().and_then(|output| output.job.frame.take_interior_mask());
+            }
+            render_worker.submit(RenderJob { frame: frame_calc, sample_step, anti_aliasing, in_motion });
+            submitted_view = (input.center_x, input.center_y, input.zoom);
+            settled = idle;
+        }
+        
+        // Render the frame the worker finished, if any, while it starts on the next
+        if let Some(output) = render_worker.try_take() {
+            color_output(&mut frame_handler, &output, input.smooth_high_precision);
+            let job = &output.job;
+            frame_handler.accumulate_motion(&job.frame, job.in_motion);
+            
+            if input.print_reference_info {
+                print_reference_info(&job.frame);
+                input.print_reference_info = false;
+            }
+            if input.print_components {
+                print_components(&output);
+                input.print_components = false;
+            }
+            if input.histogram.is_some() {
+                input.histogram = Some(job.frame.iteration_histogram(&output.sample_iterations[0], HISTOGRAM_BINS));
+            }
+            // The buffers on screen are kept for recoloring, the ones they replace go back to the worker
+            if let Some(previous) = displayed.replace(output) {
+                render_worker.recycle(previous.sample_iterations);
+            }
+        }
+        
+        // Update viewer, overlays follow the frame on screen rather than the one in flight
+        match displayed.as_ref().map(|output| &output.job.frame) {
+            Some(frame) if input.show_grid || input.show_precision_gauge || input.bulb_cycle.is_some() || input.histogram.is_some() => {
+                let mut target = if input.show_grid { frame_handler.draw_grid(frame) } else { frame_handler.snapshot() };
+                if input.show_precision_gauge {
+                    FrameHandler::draw_precision_gauge(&mut target, frame.precision_headroom());
+                }
+                if let Some(histogram) = &input.histogram {
+                    frame_handler.draw_histogram(&mut target, histogram, frame.max_iterations);
+                }
+                if let Some((point, cycle)) = &input.bulb_cycle {
+                    frame_handler.draw_cycle(&mut target, frame, *point, cycle);
+                }
+                viewer.update(&target)
+            }
+            _ => viewer.update(frame_handler.get_draw_target()),
+        };
+        
+        let frame_time = last_update.elapsed().as_secs_f64();
+        last_update = Instant::now();
+        if frame_time > 0.0 {
+            observed_fps += (1.0 / frame_time - observed_fps) * 0.1;
+        }
         
         // Print current view state and animation status
-        print!("\rCenter: ({:.3}, {:.3}), Zoom: {:.1}x, Iterations: {}, Detail: {}x, Sample: {}px{} {} {}    ", 
-               center_x, center_y, zoom, base_iterations, detail_multiplier, sample_step,
-               if should_record { " (Recording)" } else { "" },
+        let detail = if input.fixed_iterations { "fixed".to_string() } else { format!("{}x", detail_multiplier) };
+        let preview = preview_cap.map_or(String::new(), |cap| format!(" (Preview, {} iterations)", cap));
+        print!("\rCenter: ({:.3}, {:.3}), Zoom: {} ({:.2e}/px), Iterations: {}, Detail: {}, Sample: {}px, FPS: {:.0}{}{} {} {} {}    ", 
+               input.center_x, input.center_y, format_zoom(input.zoom), units_per_pixel, input.base_iterations, detail, sample_step, observed_fps,
+               preview,
+               if precision_degraded { " (f64 only, past its precision)" } else { "" },
+               if input.fine_movement { "[Fine]" } else { "" },
                if animation_handler.has_start_node() { "[Start Set]" } else { "" },
                if animation_handler.has_end_node() { "[End Set]" } else { "" });
     }
     
     println!(); // Final newline
     Ok(())
-}
\ No newline at end of file
+}
+
+// Reference orbit details printed by F1
+fn print_reference_info(frame: &MandelbrotFrame) {
+    if let Some(info) = frame.reference_info() {
+        let escape = match info.escaped_at {
+            Some(n) => format!("escaped at iteration {}", n),
+            None => "did not escape".to_string(),
+        };
+        println!("\nReference orbit at {}, {}: {} points, {}{}", info.point.0, info.point.1, info.orbit_length, escape,
+                 if info.high_precision { " (high precision)" } else { "" });
+        if info.glitch_references > 0 {
+            println!("{} more references placed at glitched pixels", info.glitch_references);
+        }
+    }
+}
+
+// In-set components of a finished frame, listed by F12
+fn print_components(output: &RenderOutput) {
+    let frame = &output.job.frame;
+    let mask = frame.in_set_mask(&output.sample_iterations[0]);
+    let components = components::find_components(&mask, frame.width as usize, frame.height as usize);
+    println!("\n{} in-set components", components.len());
+    for (i, component) in components.iter().enumerate() {
+        let (re_min, im_min) = frame.pixel_to_complex(component.min_x as u32, component.min_y as u32);
+        let (re_max, im_max) = frame.pixel_to_complex(component.max_x as u32 + 1, component.max_y as u32 + 1);
+        println!("  {} {}: {} px, re {:.6}..{:.6}, im {:.6}..{:.6}",
+                 if i == 0 { "Main" } else { "Bulb" }, i + 1, component.area, re_min, re_max, im_min, im_max);
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exr_stills_carry_distance_estimates() {
+        let path = |extension: &str| std::env::temp_dir().join(format!("rustybrot-exr-still-{}.{}", std::process::id(), extension));
+        let (png, exr) = (path("png"), path("exr"));
+        let args = ["--quality", "draft", "--iterations", "100", "--exr", exr.to_str().unwrap(), "--output", png.to_str().unwrap()];
+        let options = parse_options(&args.map(str::to_string)).unwrap();
+        render_headless(80, 60, &options).unwrap();
+        let bytes = std::fs::read(&exr).unwrap();
+        let _ = (std::fs::remove_file(&png), std::fs::remove_file(&exr));
+
+        // In-set pixels and letterbox bars are at distance 0, escaping ones at
+        // some distance out
+        let (_, _, channels) = exr::tests::read_exr(&bytes);
+        for (&iteration, &distance) in channels["iteration"].iter().zip(&channels["distance"]) {
+            if iteration >= 100.0 {
+                assert_eq!(distance, 0.0);
+            } else {
+                assert!(distance > 0.0 && distance.is_finite(), "{} at escape value {}", distance, iteration);
+            }
+        }
+    }
+}
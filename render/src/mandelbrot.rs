@@ -1,6 +1,8 @@
+use std::fmt;
 use std::ops::{Add, Mul};
 use rayon::prelude::*;
 use num_bigfloat::BigFloat;
+use serde::{Deserialize, Serialize};
 
 #[derive(Clone)]
 pub struct HighPrecComplex {
@@ -17,27 +19,37 @@ impl HighPrecComplex {
     }
 
     fn magnitude_squared(&self) -> BigFloat {
-        let r = self.real.clone();
-        let i = self.imag.clone();
-        r * r + i * i
+        self.real * self.real + self.imag * self.imag
     }
 
     fn mul(&self, other: &HighPrecComplex) -> HighPrecComplex {
-        let r1 = self.real.clone();
-        let i1 = self.imag.clone();
-        let r2 = other.real.clone();
-        let i2 = other.imag.clone();
-
         HighPrecComplex {
-            real: r1.clone() * r2.clone() - i1.clone() * i2.clone(),
-            imag: r1 * i2 + i1 * r2,
+            real: self.real * other.real - self.imag * other.imag,
+            imag: self.real * other.imag + self.imag * other.real,
         }
     }
 
     fn add(&self, other: &HighPrecComplex) -> HighPrecComplex {
         HighPrecComplex {
-            real: self.real.clone() + other.real.clone(),
-            imag: self.imag.clone() + other.imag.clone(),
+            real: self.real + other.real,
+            imag: self.imag + other.imag,
+        }
+    }
+
+    fn powi(&self, power: u32) -> HighPrecComplex {
+        let mut result = self.clone();
+        for _ in 1..power {
+            result = result.mul(self);
+        }
+        result
+    }
+
+    // Formula::fold in high precision
+    fn fold(&self, formula: Formula) -> HighPrecComplex {
+        match formula {
+            Formula::Mandelbrot => self.clone(),
+            Formula::BurningShip => HighPrecComplex { real: self.real.abs(), imag: self.imag.abs() },
+            Formula::Tricorn => HighPrecComplex { real: self.real, imag: -self.imag },
         }
     }
 }
@@ -65,8 +77,33 @@ impl Complex {
     pub fn magnitude_squared(&self) -> f64 {
         self.real * self.real + self.imag * self.imag
     }
+
+    // z^power by repeated multiplication, which for power 2 is bit for bit
+    // the usual x^2 - y^2 + 2xyi
+    pub fn powi(self, power: u32) -> Complex {
+        // The common square is spelled out, the loop costs the hot paths
+        if power == 2 {
+            return Complex::with_error(self.real * self.real - self.imag * self.imag, 2.0 * self.real * self.imag, 2.0 * self.error);
+        }
+        let mut result = self;
+        for _ in 1..power {
+            result = result * self;
+        }
+        result
+    }
+
+    pub fn sin(self) -> Complex {
+        Complex {
+            real: self.real.sin() * self.imag.cosh(),
+            imag: self.real.cos() * self.imag.sinh(),
+            error: self.error,
+        }
+    }
 }
 
+// User-supplied recurrence z -> f(z, c) iterated in place of z^2 + c
+pub type Recurrence = fn(Complex, Complex) -> Complex;
+
 impl Add for Complex {
     type Output = Complex;
 
@@ -111,6 +148,460 @@ impl Matrix2x2 {
     }
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SamplePattern {
+    Grid,         // Regular factor x factor grid
+    RotatedGrid,  // Grid rotated by atan(1/factor) so no two samples share a row or column
+    Halton,       // Halton (2, 3) points paired with their reflections through the center
+}
+
+impl SamplePattern {
+    pub fn from_name(name: &str) -> Option<SamplePattern> {
+        match name.to_ascii_lowercase().as_str() {
+            "grid" => Some(SamplePattern::Grid),
+            "rotatedgrid" => Some(SamplePattern::RotatedGrid),
+            "halton" => Some(SamplePattern::Halton),
+            _ => None,
+        }
+    }
+}
+
+// Point of each pixel sampled without anti-aliasing. Centers keep tiles and
+// zoom levels aligned, corners reproduce renders from older versions.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SampleOrigin {
+    Center,
+    Corner,
+}
+
+impl SampleOrigin {
+    pub fn from_name(name: &str) -> Option<SampleOrigin> {
+        match name.to_ascii_lowercase().as_str() {
+            "center" => Some(SampleOrigin::Center),
+            "corner" => Some(SampleOrigin::Corner),
+            _ => None,
+        }
+    }
+
+    pub fn offset(&self) -> (f64, f64) {
+        match self {
+            SampleOrigin::Center => (0.5, 0.5),
+            SampleOrigin::Corner => (0.0, 0.0),
+        }
+    }
+}
+
+// Float type the standard path iterates in. f32 is faster and looks the same
+// at shallow zoom, but runs out of precision far sooner than f64.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum StandardFloat {
+    F32,
+    F64,
+}
+
+impl StandardFloat {
+    pub fn from_name(name: &str) -> Option<StandardFloat> {
+        match name.to_ascii_lowercase().as_str() {
+            "f32" => Some(StandardFloat::F32),
+            "f64" => Some(StandardFloat::F64),
+            _ => None,
+        }
+    }
+}
+
+// Samples per side of the grid the sampling reference strategies choose from
+const REFERENCE_SAMPLES: u32 = 16;
+
+// Where calculate places the reference orbit. Pixels are perturbed from it,
+// so the smaller their offsets from the reference and the longer its orbit
+// lasts, the fewer glitch.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ReferenceStrategy {
+    Center,     // The view's center, which has the smallest largest offset to any pixel
+    MaxDetail,  // The sample where iteration counts change fastest, keeping offsets small where the detail is
+    Auto,       // The center if its orbit lasts to max_iterations, else the sample closest to the center that does
+}
+
+impl ReferenceStrategy {
+    pub fn from_name(name: &str) -> Option<ReferenceStrategy> {
+        match name.to_ascii_lowercase().as_str() {
+            "center" => Some(ReferenceStrategy::Center),
+            "maxdetail" => Some(ReferenceStrategy::MaxDetail),
+            "auto" => Some(ReferenceStrategy::Auto),
+            _ => None,
+        }
+    }
+}
+
+// When f64 pixels of z^2 + c are perturbed from the reference orbit. Each
+// perturbed iteration costs more than a direct one, so perturbing only pays
+// off through the iterations the series approximation skips.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Perturbation {
+    Off,
+    Auto,  // Where the series skips at least half of the reference orbit, see series_pays_off
+    On,
+}
+
+impl Perturbation {
+    pub fn from_name(name: &str) -> Option<Perturbation> {
+        match name.to_ascii_lowercase().as_str() {
+            "off" => Some(Perturbation::Off),
+            "auto" => Some(Perturbation::Auto),
+            "on" => Some(Perturbation::On),
+            _ => None,
+        }
+    }
+}
+
+// Periodicity checking on the standard path: an orbit that returns to within
+// epsilon of a saved iterate has settled into a cycle, so the point is in the
+// set and stops iterating early. The saved iterate is replaced after
+// check_period iterations, then after twice as many each time, so cycles of
+// any length are eventually caught. Too loose an epsilon also catches orbits
+// that only crawl along slowly before escaping, turning escaping filaments
+// solid black; too tight and rounding keeps real cycles from matching.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Periodicity {
+    pub epsilon: f64,
+    pub check_period: u32,
+}
+
+pub const DEFAULT_PERIODICITY: Periodicity = Periodicity { epsilon: 1e-12, check_period: 16 };
+
+#[derive(Clone, Copy)]
+pub struct AntiAliasing {
+    pub factor: u32,  // factor * factor samples per pixel
+    pub pattern: SamplePattern,
+    pub boundary_radius: Option<u32>,  // Only supersample pixels this close to the set's edge, None for all
+}
+
+impl AntiAliasing {
+    // Sub-pixel offsets in 0..1 from the pixel's top-left corner, averaging to its center
+    pub fn sample_offsets(&self) -> Vec<(f64, f64)> {
+        let factor = self.factor.max(1);
+        let count = factor * factor;
+        let grid = (0..count).map(|i| (
+            ((i % factor) as f64 + 0.5) / factor as f64,
+            ((i / factor) as f64 + 0.5) / factor as f64,
+        ));
+
+        match self.pattern {
+            SamplePattern::Grid => grid.collect(),
+            SamplePattern::RotatedGrid => {
+                let k = factor as f64;
+                let scale = (k * k + 1.0).sqrt() / k;
+                let angle = (1.0 / k).atan();
+                let rotation = Matrix2x2::new(
+                    angle.cos() * scale, -angle.sin() * scale,
+                    angle.sin() * scale, angle.cos() * scale,
+                );
+                grid.map(|(x, y)| {
+                    let rotated = rotation.mul_complex(&Complex::new(x - 0.5, y - 0.5));
+                    (rotated.real + 0.5, rotated.imag + 0.5)
+                }).collect()
+            }
+            SamplePattern::Halton => {
+                let mut offsets = Vec::with_capacity(count as usize);
+                for i in 1..=count / 2 {
+                    let (x, y) = (halton(i, 2), halton(i, 3));
+                    offsets.push((x, y));
+                    offsets.push((1.0 - x, 1.0 - y));
+                }
+                if count % 2 == 1 {
+                    offsets.push((0.5, 0.5));
+                }
+                offsets
+            }
+        }
+    }
+}
+
+pub fn halton(mut index: u32, base: u32) -> f64 {
+    let mut fraction = 1.0;
+    let mut result = 0.0;
+    while index > 0 {
+        fraction /= base as f64;
+        result += fraction * (index % base) as f64;
+        index /= base;
+    }
+    result
+}
+
+// Viewport half-extents at zoom 1.0
+pub const VIEW_HALF_WIDTH: f64 = 1.5;
+pub const VIEW_HALF_HEIGHT: f64 = 1.0;
+
+// Zoom (1 / view width) past which f64 can't resolve pixels and high
+// precision takes over
+const HIGH_PRECISION_ZOOM: f64 = 1e14;
+
+// Iterations past the bailout before the smooth escape count is estimated
+const ESCAPE_EXTENSION: usize = 2;
+
+// |z|^2 an escaping orbit is iterated to before its distance estimate is
+// taken, which only holds once |z| is large
+const DISTANCE_BAILOUT: f64 = 1e20;
+
+// Decimal digits carried by BigFloat, which bound how deep high precision can go
+pub const BIGFLOAT_DIGITS: i32 = 40;
+
+// Estimated work, in pixels x iterations x BigFloat digits, a high precision
+// frame may take before it is rendered at reduced resolution. Around ten
+// seconds on a single core.
+const HIGH_PRECISION_BUDGET: f64 = 4e8;
+
+// Budget units one perturbed f64 pixel iteration costs, measured at about
+// 5 ns against the 25 ns of a BigFloat digit
+const PERTURBED_ITERATION_COST: f64 = 0.2;
+
+// A perturbed pixel has glitched once |z| falls below this fraction of the
+// reference's |Z|, squared (Pauldelbrot's criterion with a tolerance of 1e-3).
+// dz then carries z's own digits and their precision is lost.
+const GLITCH_TOLERANCE: f64 = 1e-6;
+
+// Re-referencing passes correcting glitched perturbed pixels by default
+pub const DEFAULT_GLITCH_PASSES: u32 = 4;
+
+// Largest error of the series approximation, relative to the offset it
+// approximates, at any of the probe pixels, see calculate_series. Pixels near
+// the boundary amplify any error in their offset, so it has to be this tight
+// for them to come out as they do perturbed without the series.
+const SERIES_TOLERANCE: f64 = 1e-12;
+
+// Period queries let the orbit settle for this many iterations before looking
+// for a cycle of at most MAX_PERIOD points, closed to within PERIOD_TOLERANCE
+const PERIOD_SETTLE_ITERATIONS: u32 = 100_000;
+const MAX_PERIOD: usize = 1024;
+const PERIOD_TOLERANCE: f64 = 1e-9;
+
+// How much of each precision mode's zoom range the view has used, on a log
+// scale from 0 to 1
+#[derive(Clone, Copy, Debug)]
+pub struct PrecisionHeadroom {
+    pub f64_used: f32,
+    pub high_precision_used: f32,
+}
+
+// Extra iterations needed to keep detail as the view zooms in, at least 1 so
+// views zoomed out past 1.0, like the whole set, still iterate
+pub fn zoom_detail_multiplier(zoom: f64) -> u32 {
+    ((1.0 + zoom.log10() * 2.0) as u32).max(1)
+}
+
+// Value letterbox bar samples get, just above max_iterations so they color
+// like the interior while edge detection, glow and boundary anti-aliasing can
+// tell them apart from it
+pub fn letterbox_bar_value(max_iterations: u32) -> f32 {
+    (max_iterations as f32).next_up()
+}
+
+pub fn is_letterbox_bar(value: f32, max_iterations: u32) -> bool {
+    value > max_iterations as f32
+}
+
+// Iteration count for a view at the given zoom, shared by the viewer,
+// quality profiles and animations so detail stays consistent between them.
+// Saturates, since base_iterations can come straight from --iterations
+pub fn iterations_for_zoom(base_iterations: u32, zoom: f64) -> u32 {
+    base_iterations.saturating_mul(zoom_detail_multiplier(zoom))
+}
+
+// iterations_for_zoom without its multiplier truncated, rising smoothly with
+// zoom where the other steps a whole base count at a time
+pub fn continuous_iterations_for_zoom(base_iterations: u32, zoom: f64) -> f64 {
+    base_iterations as f64 * (1.0 + zoom.log10() * 2.0).max(1.0)
+}
+
+// How the natural viewport maps into output dimensions of a different shape
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AspectPolicy {
+    Stretch,  // Viewport fills the output exactly, distorting the fractal
+    Fit,      // Whole viewport visible, black bars fill the remaining space
+    Fill,     // Viewport covers the output, cropping the longer dimension
+}
+
+impl AspectPolicy {
+    pub fn from_name(name: &str) -> Option<AspectPolicy> {
+        match name.to_ascii_lowercase().as_str() {
+            "stretch" => Some(AspectPolicy::Stretch),
+            "fit" => Some(AspectPolicy::Fit),
+            "fill" => Some(AspectPolicy::Fill),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FractalKind {
+    Mandelbrot,
+    Julia { cx: f64, cy: f64 },  // Fixed c, the pixel coordinate seeds z
+}
+
+// Variant of the escape-time iteration z -> fold(z)^2 + c, the fold
+// transforming z before it is squared each step
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Formula {
+    Mandelbrot,
+    BurningShip,  // Absolute values of both parts of z
+    Tricorn,      // Complex conjugate of z
+}
+
+impl Formula {
+    pub fn from_name(name: &str) -> Option<Formula> {
+        match name.to_ascii_lowercase().as_str() {
+            "mandelbrot" => Some(Formula::Mandelbrot),
+            "burningship" | "burning-ship" | "burning_ship" => Some(Formula::BurningShip),
+            "tricorn" => Some(Formula::Tricorn),
+            _ => None,
+        }
+    }
+
+    // Bounding box of the whole set with a margin, as (x_min, x_max, y_min,
+    // y_max). The Burning Ship sits mostly below the real axis, which is the
+    // top of the screen, so its box is shifted up to frame the ship upright.
+    pub fn bounds(self) -> (f64, f64, f64, f64) {
+        match self {
+            Formula::Mandelbrot => (-2.5, 1.0, -1.25, 1.25),
+            Formula::BurningShip => (-2.5, 1.5, -2.0, 1.0),
+            Formula::Tricorn => (-2.5, 1.0, -1.5, 1.5),
+        }
+    }
+
+    fn fold(self, z: Complex) -> Complex {
+        match self {
+            Formula::Mandelbrot => z,
+            Formula::BurningShip => Complex { real: z.real.abs(), imag: z.imag.abs(), ..z },
+            Formula::Tricorn => Complex { imag: -z.imag, ..z },
+        }
+    }
+}
+
+// Read-only summary of the reference orbit used by the last calculate call
+#[derive(Clone, Copy, Debug)]
+pub struct ReferenceInfo {
+    pub point: (f64, f64),  // Where the reference strategy placed the orbit
+    pub orbit_length: usize,  // Stored iterates, none of which have escaped
+    pub escaped_at: Option<u32>,  // Iteration at which the reference escaped, if it did
+    pub high_precision: bool,
+    pub glitch_references: u32,  // Further orbits placed at glitched pixels, see correct_glitches
+}
+
+// Where iterate_standard left an orbit: its continuous escape count, z and the
+// iteration count there, and for frames estimating distances its derivative
+// with respect to the point, dz/dc for the Mandelbrot set and dz/dz0 for Julia sets
+struct Escape {
+    value: f64,
+    z: Complex,
+    iterations: usize,
+    derivative: Option<Complex>,
+}
+
+// Where calculate_reference_orbit left off, so a higher max_iterations at the
+// same reference point extends the orbit instead of recomputing it
+#[derive(Clone, Copy)]
+struct OrbitProgress {
+    point: (f64, f64),
+    kind: FractalKind,
+    formula: Formula,
+    power: u32,
+    initial_z: (f64, f64),
+    iterations: u32,  // Limit the orbit was calculated up to
+    next: Complex,    // Next orbit value, not yet checked for escape
+    escaped_at: Option<u32>,
+    finished: bool,   // Escaped or lost precision, so the orbit can't grow further
+}
+
+// Truncated series dz_n = A d + B d^2 + C d^3 in a pixel's offset d from the
+// reference, dc for the Mandelbrot set and dz_0 for Julia sets, which stands
+// in for its first skip perturbed iterations
+#[derive(Clone, Copy)]
+struct Series {
+    skip: usize,
+    a: Complex,
+    b: Complex,
+    c: Complex,
+}
+
+impl Series {
+    fn offset(&self, d: Complex) -> Complex {
+        ((self.c * d + self.b) * d + self.a) * d
+    }
+}
+
+#[derive(Debug)]
+pub enum RenderError {
+    BufferSize { expected: usize, actual: usize },  // Output buffer doesn't match the frame
+    StressFailures { failed: usize, cases: usize },  // High precision stress cases that broke an invariant
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RenderError::BufferSize { expected, actual } => {
+                write!(f, "Output buffer holds {} values but the frame needs {}", actual, expected)
+            }
+            RenderError::StressFailures { failed, cases } => {
+                write!(f, "{} of {} high precision stress cases failed", failed, cases)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RenderError {}
+
+// Per-pixel absolute difference between the iteration values of two frames
+// of the same size, for checking that a setting or optimization leaves the
+// result unchanged. Identical configurations give all zeros.
+pub fn diff_render(a: &mut MandelbrotFrame, b: &mut MandelbrotFrame) -> Result<Vec<f32>, RenderError> {
+    let (mut first, mut second) = (vec![0.0; (a.width * a.height) as usize], vec![0.0; (a.width * a.height) as usize]);
+    a.calculate_into(&mut first)?;
+    b.calculate_into(&mut second)?;
+    Ok(first.iter().zip(&second).map(|(x, y)| (x - y).abs()).collect())
+}
+
+// Pixels of a calculated frame whose orbits periodicity checking caught in a
+// cycle, so they are interior for certain, kept to skip the interior of the
+// next frame while zooming
+#[derive(Clone)]
+pub struct InteriorMask {
+    view: Box<MandelbrotFrame>,  // Frame the mask was sampled on, only its geometry set
+    confirmed: Vec<bool>,
+}
+
+impl InteriorMask {
+    // Whether the 3x3 block of the mask's pixels around the point is all
+    // confirmed. Pixels between confirmed samples aren't certain to be in the
+    // set, so reusing the mask is an approximation for preview frames.
+    fn contains(&self, re: f64, im: f64) -> bool {
+        let (x, y) = self.view.complex_to_grid(re, im);
+        let (x, y) = (x.floor() as i64, y.floor() as i64);
+        let (width, height) = (self.view.width as i64, self.view.height as i64);
+        if x < 1 || y < 1 || x >= width - 1 || y >= height - 1 {
+            return false;
+        }
+        (y - 1..=y + 1).all(|y| (x - 1..=x + 1).all(|x| self.confirmed[(y * width + x) as usize]))
+    }
+
+    // Whether the mask was confirmed iterating the same fractal as the frame
+    fn same_fractal(&self, frame: &MandelbrotFrame) -> bool {
+        (self.view.kind, self.view.formula, self.view.power) == (frame.kind, frame.formula, frame.power)
+    }
+}
+
+// The view as set_view placed it. Past HIGH_PRECISION_ZOOM the f64 bounds
+// can't resolve pixels, but the center and extent themselves stay exact.
+#[derive(Clone, Copy)]
+struct ViewGeometry {
+    center: (f64, f64),
+    half_size: (f64, f64),  // Half the sampled extent along each axis
+    visible_half_size: (f64, f64),  // Half the letterboxed viewport, the sampled extent unless fitting
+}
+
+#[derive(Clone)]
 pub struct MandelbrotFrame {
     pub width: u32,
     pub height: u32,
@@ -119,10 +610,37 @@ pub struct MandelbrotFrame {
     pub y_min: f64,
     pub y_max: f64,
     pub max_iterations: u32,
+    pub kind: FractalKind,
+    pub formula: Formula,  // Applies to the built-in recurrence, a custom one replaces it
+    pub power: u32,  // Exponent of the built-in recurrence z^power + c, 2 unless rendering a multibrot
+    pub exact_center: Option<(String, String)>,  // Decimal center the view was set to, kept by high precision where f64 rounds it
+    pub perturbation: Perturbation,  // Iterate f64 pixels as offsets from the reference orbit, for z^2 + c without periodicity checking
+    pub max_glitch_passes: u32,  // New reference orbits tried for glitched perturbed pixels before iterating them directly
+    pub sample_offset: (f64, f64),  // Sub-pixel position sampled within each pixel, from its top-left corner
+    pub disable_high_precision: bool,  // Always use f64, even past its precision limit
+    pub standard_float: StandardFloat,  // Float type of the standard path's built-in recurrence
+    pub aspect_policy: AspectPolicy,
+    pub escape_smoothing: f32,  // Weight of the fractional escape count, 0 gives hard bands and 1 fully smooth
+    pub recurrence: Option<Recurrence>,  // Custom iteration, always rendered in f64 without symmetry
+    pub sequential: bool,  // Render pixels on one thread in a fixed order, for reproducible debugging
+    pub rotation_radians: f64,  // Rotation of the sampling grid about the view center
+    pub initial_z: (f64, f64),  // Critical point seeding z for the Mandelbrot set, zero for z^2 + c
+    pub periodicity: Option<Periodicity>,  // Stop iterating orbits caught in a cycle, f64 built-in recurrence only
+    pub capped_lookahead: bool,  // Keep iterating standard path points that reach max_iterations, see capped_value
+    pub log_polar_decades: Option<f64>,  // Zoom levels, in powers of ten, squeezed between the view's edge and center
+    pub known_interior: Option<InteriorMask>,  // Confirmed interior of an earlier frame, filled in without iterating
+    pub reference_strategy: ReferenceStrategy,
+    pub distance_estimation: bool,  // Carry each orbit's derivative and estimate distances to the set, see take_distance_estimates
+    interior_mask: Option<InteriorMask>,  // Confirmed interior of the last calculation, see take_interior_mask
+    distance_estimates: Option<Vec<f32>>,
+    letterbox: Option<(f64, f64, f64, f64)>,  // Visible region when fitting, pixels outside are bars
+    geometry: Option<ViewGeometry>,  // Exact view of the last set_view, see view_geometry
     reference_point: Complex,
     reference_orbit: Vec<Complex>,
-    high_prec_reference: Option<HighPrecComplex>,
-    high_prec_orbit: Vec<HighPrecComplex>,
+    orbit_progress: Option<OrbitProgress>,
+    series: Option<Series>,  // Skips the first perturbed iterations of every pixel, see calculate_series
+    high_prec_orbit: Vec<Complex>,  // BigFloat reference orbit rounded to f64, from reference_point
+    reference_info: Option<ReferenceInfo>,
 }
 
 impl MandelbrotFrame {
@@ -135,177 +653,1917 @@ impl MandelbrotFrame {
             y_min: -1.5,
             y_max: 1.5,
             max_iterations: 100,
+            kind: FractalKind::Mandelbrot,
+            formula: Formula::Mandelbrot,
+            power: 2,
+            exact_center: None,
+            perturbation: Perturbation::Auto,
+            max_glitch_passes: DEFAULT_GLITCH_PASSES,
+            sample_offset: SampleOrigin::Center.offset(),
+            disable_high_precision: false,
+            standard_float: StandardFloat::F64,
+            aspect_policy: AspectPolicy::Fit,
+            escape_smoothing: 1.0,
+            recurrence: None,
+            sequential: false,
+            rotation_radians: 0.0,
+            initial_z: (0.0, 0.0),
+            periodicity: None,
+            capped_lookahead: false,
+            log_polar_decades: None,
+            known_interior: None,
+            reference_strategy: ReferenceStrategy::Center,
+            distance_estimation: false,
+            interior_mask: None,
+            distance_estimates: None,
+            letterbox: None,
+            geometry: None,
             reference_point: Complex::new(0.0, 0.0),
             reference_orbit: Vec::new(),
-            high_prec_reference: None,
+            orbit_progress: None,
+            series: None,
             high_prec_orbit: Vec::new(),
+            reference_info: None,
         }
     }
 
-    pub fn calculate(&mut self) -> Vec<u32> {
-        let zoom_level = 1.0 / (self.x_max - self.x_min).abs();
-        
-        // Use high precision for deep zooms
-        let use_high_precision = zoom_level > 1e14;
-        
-        if use_high_precision {
-            self.calculate_high_precision()
-        } else {
-            self.calculate_standard()
-        }
+    // Copy of the frame shrunk to fit within max_size pixels on its longer
+    // side, for a quick preview. The bounds are kept rather than set again from
+    // the view, so it frames exactly what the full frame does, letterbox and
+    // rotation included.
+    pub fn thumbnail(&self, max_size: u32) -> MandelbrotFrame {
+        let scale = (max_size as f64 / self.width.max(self.height) as f64).min(1.0);
+        let mut thumbnail = self.clone();
+        thumbnail.width = ((self.width as f64 * scale).round() as u32).max(1);
+        thumbnail.height = ((self.height as f64 * scale).round() as u32).max(1);
+        thumbnail
     }
 
-    fn calculate_high_precision(&mut self) -> Vec<u32> {
-        let mut result = vec![0; (self.width * self.height) as usize];
-        
-        // Calculate center point
-        let center_x = (self.x_min + self.x_max) / 2.0;
-        let center_y = (self.y_min + self.y_max) / 2.0;
-        
-        self.high_prec_reference = Some(HighPrecComplex::new(center_x, center_y));
-        self.calculate_high_precision_orbit();
+    // Centers the viewport on a point at the given zoom level, shaped to the
+    // output according to the aspect policy
+    pub fn set_view(&mut self, center_x: f64, center_y: f64, zoom: f64) {
+        let view_half_width = VIEW_HALF_WIDTH / zoom;
+        let view_half_height = VIEW_HALF_HEIGHT / zoom;
+        let scale_x = 2.0 * view_half_width / self.width as f64;
+        let scale_y = 2.0 * view_half_height / self.height as f64;
 
-        result.chunks_mut(self.width as usize)
-            .enumerate()
-            .par_bridge()
-            .for_each(|(y, row)| {
-                for x in 0..self.width {
-                    let x_coord = self.x_min + (x as f64 / self.width as f64) * (self.x_max - self.x_min);
-                    let y_coord = self.y_min + (y as f64 / self.height as f64) * (self.y_max - self.y_min);
-                    
-                    let c = HighPrecComplex::new(x_coord, y_coord);
-                    row[x as usize] = self.iterate_high_precision(&c);
-                }
-            });
+        // Complex units per pixel along each axis
+        let (units_x, units_y) = match self.aspect_policy {
+            AspectPolicy::Stretch => (scale_x, scale_y),
+            AspectPolicy::Fit => (scale_x.max(scale_y), scale_x.max(scale_y)),
+            AspectPolicy::Fill => (scale_x.min(scale_y), scale_x.min(scale_y)),
+        };
+        let half_width = units_x * self.width as f64 / 2.0;
+        let half_height = units_y * self.height as f64 / 2.0;
 
-        result
+        self.x_min = center_x - half_width;
+        self.x_max = center_x + half_width;
+        self.y_min = center_y - half_height;
+        self.y_max = center_y + half_height;
+
+        self.letterbox = (self.aspect_policy == AspectPolicy::Fit).then_some((
+            center_x - view_half_width,
+            center_x + view_half_width,
+            center_y - view_half_height,
+            center_y + view_half_height,
+        ));
+        let visible_half_size = match self.letterbox {
+            Some(_) => (view_half_width, view_half_height),
+            None => (half_width, half_height),
+        };
+        self.geometry = Some(ViewGeometry { center: (center_x, center_y), half_size: (half_width, half_height), visible_half_size });
     }
 
-    fn calculate_high_precision_orbit(&mut self) {
-        self.high_prec_orbit.clear();
-        let mut z = HighPrecComplex::new(0.0, 0.0);
-        let c = self.high_prec_reference.as_ref().unwrap();
-        
-        self.high_prec_orbit.reserve(self.max_iterations as usize);
-        
-        for _ in 0..self.max_iterations {
-            if z.magnitude_squared() > BigFloat::from(4.0) {
-                break;
+    // The exact view set_view recorded, unless the bounds have been changed
+    // since, in which case the view is taken from them
+    fn view_geometry(&self) -> ViewGeometry {
+        match self.geometry {
+            Some(view) if view.center.0 - view.half_size.0 == self.x_min && view.center.0 + view.half_size.0 == self.x_max
+                && view.center.1 - view.half_size.1 == self.y_min && view.center.1 + view.half_size.1 == self.y_max => view,
+            _ => {
+                let half_size = ((self.x_max - self.x_min) / 2.0, (self.y_max - self.y_min) / 2.0);
+                ViewGeometry {
+                    center: ((self.x_min + self.x_max) / 2.0, (self.y_min + self.y_max) / 2.0),
+                    half_size,
+                    visible_half_size: self.letterbox.map_or(half_size, |(x_min, x_max, y_min, y_max)| ((x_max - x_min) / 2.0, (y_max - y_min) / 2.0)),
+                }
             }
-            self.high_prec_orbit.push(z.clone());
-            
-            // z = z^2 + c
-            z = z.mul(&z).add(c);
         }
     }
 
-    fn iterate_high_precision(&self, c: &HighPrecComplex) -> u32 {
-        let mut z = HighPrecComplex::new(0.0, 0.0);
-        let mut n = 0;
+    // Half the width and height of the region the view shows, the letterboxed
+    // viewport when fitting
+    pub fn visible_half_size(&self) -> (f64, f64) {
+        self.view_geometry().visible_half_size
+    }
 
-        while z.magnitude_squared() <= BigFloat::from(4.0) && n < self.max_iterations as usize {
-            z = z.mul(&z).add(c);
-            n += 1;
+    // Offset of a pixel's sample from the view center, None in a letterbox
+    // bar. Unlike pixel_to_complex it doesn't round through absolute f64
+    // coordinates, so it stays exact past HIGH_PRECISION_ZOOM.
+    fn pixel_offset(&self, px: u32, py: u32) -> Option<(f64, f64)> {
+        let view = self.view_geometry();
+        if self.log_polar_decades.is_some() {
+            let (re, im) = self.pixel_to_complex(px, py);
+            return Some((re - view.center.0, im - view.center.1));
         }
 
-        if n < self.max_iterations as usize {
-            let mag = z.magnitude_squared().to_f64();
-            n as u32 + 1 - (mag.ln().ln() / 2.0_f64.ln()).floor() as u32
-        } else {
-            self.max_iterations
+        let x = ((px as f64 + self.sample_offset.0) / self.width as f64 * 2.0 - 1.0) * view.half_size.0;
+        let y = ((py as f64 + self.sample_offset.1) / self.height as f64 * 2.0 - 1.0) * view.half_size.1;
+        if x.abs() > view.visible_half_size.0 || y.abs() > view.visible_half_size.1 {
+            return None;
         }
+        let (sin, cos) = self.rotation_radians.sin_cos();
+        Some((x * cos - y * sin, x * sin + y * cos))
     }
 
-    fn calculate_standard(&mut self) -> Vec<u32> {
-        // Calculate center point for reference orbit
-        let center_x = (self.x_min + self.x_max) / 2.0;
-        let center_y = (self.y_min + self.y_max) / 2.0;
-        self.reference_point = Complex::new(center_x, center_y);
-        
-        // Calculate reference orbit
-        self.calculate_reference_orbit();
-        
-        let mut result = vec![0; (self.width * self.height) as usize];
-        
-        result.par_chunks_mut(self.width as usize)
-            .enumerate()
-            .for_each(|(y, row)| {
-                for x in 0..self.width {
-                    let x_coord = self.x_min + (x as f64 / self.width as f64) * (self.x_max - self.x_min);
-                    let y_coord = self.y_min + (y as f64 / self.height as f64) * (self.y_max - self.y_min);
-                    
-                    let c = Complex::new(x_coord, y_coord);
-                    row[x as usize] = self.iterate_standard(c);
-                }
-            });
-        
-        result
-    }
+    fn rotate_about_center(&self, re: f64, im: f64, angle: f64) -> (f64, f64) {
+        if angle == 0.0 {
+            return (re, im);
+        }
 
-    fn calculate_reference_orbit(&mut self) {
-        self.reference_orbit.clear();
-        let mut z = Complex::new(0.0, 0.0);
-        let c = self.reference_point;
-        
-        // Pre-calculate transformation matrices for better numerical stability
-        let scale = 1.0 / (self.x_max - self.x_min).abs().max((self.y_max - self.y_min).abs());
-        let transform = Matrix2x2::new(
-            scale, 0.0,
-            0.0, scale
+        let center = ((self.x_min + self.x_max) / 2.0, (self.y_min + self.y_max) / 2.0);
+        let (sin, cos) = angle.sin_cos();
+        let rotation = Matrix2x2::new(
+            cos, -sin,
+            sin, cos
         );
-        
-        self.reference_orbit.reserve(self.max_iterations as usize);
-        
-        // Use scaled coordinates for better precision
-        let scaled_c = transform.mul_complex(&c);
-        
-        for _ in 0..self.max_iterations {
-            if z.magnitude_squared() > 4.0 {
-                break;
-            }
-            self.reference_orbit.push(z);
-            
-            // Calculate with error tracking
-            let r2 = z.real * z.real;
-            let i2 = z.imag * z.imag;
-            let ri = z.real * z.imag;
-            
-            // Track numerical errors
-            let error = (r2.abs() + i2.abs()) * f64::EPSILON;
-            
-            z = Complex::with_error(
-                r2 - i2 + scaled_c.real,
-                2.0 * ri + scaled_c.imag,
-                z.error + error
-            );
-            
-            // If error gets too large, break early
-            if z.error > 1e-6 {
-                break;
+        let offset = rotation.mul_complex(&Complex::new(re - center.0, im - center.1));
+        (center.0 + offset.real, center.1 + offset.imag)
+    }
+
+    // Whether a sample falls in a letterbox bar rather than the fitted viewport
+    fn in_letterbox_bar(&self, re: f64, im: f64) -> bool {
+        // Log-polar samples don't lie where their pixels are
+        if self.log_polar_decades.is_some() {
+            return false;
+        }
+
+        // Bars are fixed to the screen, so they are checked before rotation
+        let (re, im) = self.rotate_about_center(re, im, -self.rotation_radians);
+        match self.letterbox {
+            Some((x_min, x_max, y_min, y_max)) => re < x_min || re > x_max || im < y_min || im > y_max,
+            None => false,
+        }
+    }
+
+    // Maps a pixel to the complex coordinate sampled for it, offset from the
+    // pixel's top-left corner by the sample offset and rotated with the view
+    pub fn pixel_to_complex(&self, px: u32, py: u32) -> (f64, f64) {
+        let x = px as f64 + self.sample_offset.0;
+        let y = py as f64 + self.sample_offset.1;
+        let re = self.x_min + (x / self.width as f64) * (self.x_max - self.x_min);
+        let im = self.y_min + (y / self.height as f64) * (self.y_max - self.y_min);
+        let (re, im) = match self.log_polar_decades {
+            Some(decades) => self.log_polar(re, im, decades),
+            None => (re, im),
+        };
+        self.rotate_about_center(re, im, self.rotation_radians)
+    }
+
+    // Pulls a point of the view toward the center so that distance on screen
+    // becomes a logarithmic distance in the plane. A point at the view's left
+    // or right edge stays put, and moving in from there goes the given number
+    // of decades deeper by the center, which is itself left fixed. The extra
+    // factor of the screen distance keeps the center from smearing into a ring.
+    fn log_polar(&self, re: f64, im: f64, decades: f64) -> (f64, f64) {
+        let center = ((self.x_min + self.x_max) / 2.0, (self.y_min + self.y_max) / 2.0);
+        let half_width = (self.x_max - self.x_min) / 2.0;
+        let r = (re - center.0).hypot(im - center.1) / half_width;
+        let scale = 10f64.powf(-decades * (1.0 - r));
+        (center.0 + (re - center.0) * scale, center.1 + (im - center.1) * scale)
+    }
+
+    // Inverse of pixel_to_complex, the pixel whose sample falls on the point,
+    // fractional between pixels. Log-polar views aren't undone.
+    pub fn complex_to_pixel(&self, re: f64, im: f64) -> (f32, f32) {
+        let (x, y) = self.complex_to_grid(re, im);
+        ((x as f64 - self.sample_offset.0) as f32, (y as f64 - self.sample_offset.1) as f32)
+    }
+
+    // Position of a complex coordinate on the pixel grid, pixel (x, y) covering
+    // x..x + 1 and y..y + 1 whichever point of it is sampled
+    pub fn complex_to_grid(&self, re: f64, im: f64) -> (f32, f32) {
+        let (re, im) = self.rotate_about_center(re, im, -self.rotation_radians);
+        let x = (re - self.x_min) / (self.x_max - self.x_min) * self.width as f64;
+        let y = (im - self.y_min) / (self.y_max - self.y_min) * self.height as f64;
+        (x as f32, y as f32)
+    }
+
+    // Fills in the letterbox bars of an iteration buffer for this frame that
+    // was calculated without them, as one assembled from tiles is
+    pub fn mark_letterbox_bars(&self, iterations: &mut [f32]) {
+        for (i, value) in iterations.iter_mut().enumerate() {
+            let (re, im) = self.pixel_to_complex(i as u32 % self.width, i as u32 / self.width);
+            if self.in_letterbox_bar(re, im) {
+                *value = letterbox_bar_value(self.max_iterations);
             }
         }
     }
 
-    fn iterate_standard(&self, c: Complex) -> u32 {
-        let mut z = Complex::new(0.0, 0.0);
-        let mut n = 0;
+    // Which pixels of an iteration buffer from this frame are in the set,
+    // leaving out letterbox bars even though they're colored the same
+    pub fn in_set_mask(&self, iterations: &[f32]) -> Vec<bool> {
+        self.field(iterations)
+            .map(|(_, _, re, im, value)| value >= self.max_iterations as f32 && !self.in_letterbox_bar(re, im))
+            .collect()
+    }
 
-        while z.magnitude_squared() <= 4.0 && n < self.max_iterations as usize {
-            let r2 = z.real * z.real;
-            let i2 = z.imag * z.imag;
-            z.imag = 2.0 * z.real * z.imag + c.imag;
-            z.real = r2 - i2 + c.real;
-            n += 1;
+    // Counts of this frame's escape values in equal bins over 0..max_iterations,
+    // followed by one more bin counting the in-set points. Letterbox bars aren't counted.
+    pub fn iteration_histogram(&self, iterations: &[f32], bins: usize) -> Vec<u32> {
+        let mut histogram = vec![0; bins + 1];
+        let bin_width = self.max_iterations.max(1) as f32 / bins as f32;
+        for (_, _, re, im, value) in self.field(iterations) {
+            if value >= self.max_iterations as f32 {
+                if !self.in_letterbox_bar(re, im) {
+                    histogram[bins] += 1;
+                }
+            } else {
+                histogram[((value.max(0.0) / bin_width) as usize).min(bins - 1)] += 1;
+            }
         }
+        histogram
+    }
+
+    // Walks an iteration buffer from this frame in row order, yielding
+    // (px, py, re, im, escape value) for each pixel without copying the buffer
+    pub fn field<'a>(&'a self, iterations: &'a [f32]) -> impl Iterator<Item = (u32, u32, f64, f64, f32)> + 'a {
+        iterations.iter().enumerate().map(move |(i, &value)| {
+            let (px, py) = (i as u32 % self.width, i as u32 / self.width);
+            let (re, im) = self.pixel_to_complex(px, py);
+            (px, py, re, im, value)
+        })
+    }
+
+    pub fn reference_info(&self) -> Option<ReferenceInfo> {
+        self.reference_info
+    }
+
+    pub fn uses_high_precision(&self) -> bool {
+        // Use high precision for deep zooms
+        self.beyond_f64() && !self.disable_high_precision && self.recurrence.is_none()
+    }
 
-        self.smooth_color(z, n)
+    // Whether the view is deep enough to need high precision but renders in
+    // f64 because it was disabled, so pixels have merged into blocks
+    pub fn precision_degraded(&self) -> bool {
+        self.beyond_f64() && self.disable_high_precision
     }
 
-    fn smooth_color(&self, z: Complex, n: usize) -> u32 {
-        if n < self.max_iterations as usize {
-            n as u32 + 1 - (z.magnitude_squared().ln().ln() / 2.0_f64.ln()).floor() as u32
+    fn beyond_f64(&self) -> bool {
+        let zoom_level = 1.0 / (self.x_max - self.x_min).abs();
+        zoom_level > HIGH_PRECISION_ZOOM
+    }
+
+    // Factor to divide the resolution by to bring a high precision frame within
+    // the cost budget, 1 for frames within it or rendered in f64. BigFloat has
+    // a fixed number of digits, so resolution is what gives.
+    pub fn precision_budget_step(&self) -> u32 {
+        if !self.uses_high_precision() {
+            return 1;
+        }
+
+        let pixel_iterations = self.width as f64 * self.height as f64 * self.max_iterations as f64;
+        let cost = if self.perturbs_high_precision() {
+            // Perturbed pixels are f64, only the reference orbit takes BigFloat
+            self.max_iterations as f64 * BIGFLOAT_DIGITS as f64 + pixel_iterations * PERTURBED_ITERATION_COST
         } else {
-            self.max_iterations
+            pixel_iterations * BIGFLOAT_DIGITS as f64
+        };
+        ((cost / HIGH_PRECISION_BUDGET).sqrt().ceil().max(1.0) as u32).min(self.width.min(self.height).max(1))
+    }
+
+    // BigFloat is exhausted once the pixel size needs more digits than it
+    // carries, pixels being width times finer than the view
+    pub fn precision_headroom(&self) -> PrecisionHeadroom {
+        let zoom_digits = (1.0 / (self.x_max - self.x_min).abs()).log10();
+        let f64_digits = HIGH_PRECISION_ZOOM.log10();
+        let bigfloat_digits = BIGFLOAT_DIGITS as f64 - (self.width.max(1) as f64).log10();
+
+        PrecisionHeadroom {
+            f64_used: (zoom_digits / f64_digits).clamp(0.0, 1.0) as f32,
+            high_precision_used: ((zoom_digits - f64_digits) / (bigfloat_digits - f64_digits)).clamp(0.0, 1.0) as f32,
         }
     }
-} 
\ No newline at end of file
+
+    // Flat buffer of each pixel's continuous escape value, row by row, with
+    // glitched perturbed pixels already corrected. The whole iteration count
+    // is the value rounded down; in-set pixels are at max_iterations.
+    pub fn calculate(&mut self) -> Vec<f32> {
+        let mut result = vec![0.0; (self.width * self.height) as usize];
+        self.calculate_into(&mut result).expect("buffer sized to the frame");
+        result
+    }
+
+    // Calculates into a caller-owned buffer of width * height values, so
+    // repeated renders can reuse one allocation
+    pub fn calculate_into(&mut self, out: &mut [f32]) -> Result<(), RenderError> {
+        let expected = (self.width * self.height) as usize;
+        if out.len() != expected {
+            return Err(RenderError::BufferSize { expected, actual: out.len() });
+        }
+
+        // Estimates from an earlier view would no longer line up with this one
+        self.distance_estimates = None;
+        if self.uses_high_precision() {
+            self.calculate_high_precision(out);
+        } else {
+            self.calculate_standard(out);
+        }
+        Ok(())
+    }
+
+    // Calculates one iteration buffer per anti-aliasing sample offset
+    pub fn calculate_supersampled(&mut self, anti_aliasing: &AntiAliasing) -> Vec<Vec<f32>> {
+        if let Some(radius) = anti_aliasing.boundary_radius {
+            return self.calculate_boundary_supersampled(anti_aliasing, radius);
+        }
+
+        let original_offset = self.sample_offset;
+        let mut first_distances = None;
+        let buffers = anti_aliasing.sample_offsets()
+            .into_iter()
+            .enumerate()
+            .map(|(i, offset)| {
+                self.sample_offset = offset;
+                let buffer = self.calculate();
+                // Distances are kept for the first sample, like saved buffers
+                if i == 0 {
+                    first_distances = self.distance_estimates.take();
+                }
+                buffer
+            })
+            .collect();
+        self.sample_offset = original_offset;
+        self.distance_estimates = first_distances;
+        buffers
+    }
+
+    // Argument of z where each pixel's orbit escapes, in turns from 0 to 1, which
+    // traces out the external rays. In-set pixels and letterbox bars get 0.
+    // Always iterated in f64, so past the high precision zoom the rays break up.
+    pub fn calculate_escape_angles(&self) -> Vec<f32> {
+        let mut angles = vec![0.0; (self.width * self.height) as usize];
+        self.for_each_row(&mut angles, |y, row| {
+            for x in 0..self.width {
+                let (x_coord, y_coord) = self.pixel_to_complex(x, y as u32);
+                if !self.in_letterbox_bar(x_coord, y_coord) {
+                    row[x as usize] = self.escape_angle(Complex::new(x_coord, y_coord));
+                }
+            }
+        });
+        angles
+    }
+
+    fn escape_angle(&self, point: Complex) -> f32 {
+        let (mut z, c) = self.seed(point);
+
+        let mut n = 0;
+        while z.magnitude_squared() <= 4.0 && n < self.max_iterations {
+            z = self.step(z, c);
+            n += 1;
+        }
+
+        if z.magnitude_squared() > 4.0 {
+            (z.imag.atan2(z.real) / std::f64::consts::TAU).rem_euclid(1.0) as f32
+        } else {
+            0.0
+        }
+    }
+
+    // Samples every pixel once, then supersamples only those near the edge of
+    // the set. Flat interior and exterior pixels repeat their single sample in
+    // every buffer, so the buffers average like a full supersampled render.
+    fn calculate_boundary_supersampled(&mut self, anti_aliasing: &AntiAliasing, radius: u32) -> Vec<Vec<f32>> {
+        let base = self.calculate();
+        let boundary = self.boundary_pixels(&base, radius);
+        let width = self.width as usize;
+
+        let original_offset = self.sample_offset;
+        let buffers = anti_aliasing.sample_offsets()
+            .into_iter()
+            .map(|offset| {
+                self.sample_offset = offset;
+                let frame = &*self;
+                let samples: Vec<f32> = boundary.par_iter()
+                    .map(|&i| frame.iterate_pixel((i % width) as u32, (i / width) as u32))
+                    .collect();
+
+                let mut buffer = base.clone();
+                for (&i, sample) in boundary.iter().zip(samples) {
+                    buffer[i] = sample;
+                }
+                buffer
+            })
+            .collect();
+        self.sample_offset = original_offset;
+        buffers
+    }
+
+    // Indices of the pixels within radius pixels (along both axes) of a pixel
+    // whose 4-neighbour lies on the other side of the in-set / exterior edge.
+    // Letterbox bars are on neither side.
+    fn boundary_pixels(&self, iterations: &[f32], radius: u32) -> Vec<usize> {
+        let width = self.width as usize;
+        let height = self.height as usize;
+        let radius = radius as usize;
+        let in_set = |i: usize| iterations[i] >= self.max_iterations as f32;
+        let bar = |i: usize| is_letterbox_bar(iterations[i], self.max_iterations);
+
+        let edge: Vec<bool> = (0..width * height)
+            .map(|i| {
+                let (x, y) = (i % width, i / width);
+                let neighbors = [
+                    (x > 0).then(|| i - 1),
+                    (x + 1 < width).then(|| i + 1),
+                    (y > 0).then(|| i - width),
+                    (y + 1 < height).then(|| i + width),
+                ];
+                !bar(i) && neighbors.into_iter().flatten().any(|neighbor| !bar(neighbor) && in_set(neighbor) != in_set(i))
+            })
+            .collect();
+
+        // Dilated horizontally, then vertically
+        let near_edge: Vec<bool> = (0..width * height)
+            .map(|i| {
+                let (x, y) = (i % width, i / width);
+                (x.saturating_sub(radius)..=(x + radius).min(width - 1)).any(|sx| edge[y * width + sx])
+            })
+            .collect();
+        (0..width * height)
+            .filter(|&i| {
+                let (x, y) = (i % width, i / width);
+                (y.saturating_sub(radius)..=(y + radius).min(height - 1)).any(|sy| near_edge[sy * width + x])
+            })
+            .collect()
+    }
+
+    // Value of a single pixel at the current sample offset, as calculate gives it
+    fn iterate_pixel(&self, px: u32, py: u32) -> f32 {
+        let (x_coord, y_coord) = self.pixel_to_complex(px, py);
+        if self.in_letterbox_bar(x_coord, y_coord) {
+            letterbox_bar_value(self.max_iterations)
+        } else if self.uses_high_precision() {
+            self.high_precision_pixel(px, py)
+        } else {
+            self.iterate_standard(Complex::new(x_coord, y_coord)).value as f32
+        }
+    }
+
+    // When the view is symmetric about the real axis, the row whose samples are
+    // the complex conjugates of row y's, provided it comes before row y. The
+    // Mandelbrot and Tricorn iterations commute with conjugation, so that row's
+    // values can be copied instead of recomputed. The Burning Ship's absolute
+    // values don't.
+    fn mirror_row(&self, y: usize) -> Option<usize> {
+        if self.kind != FractalKind::Mandelbrot || self.formula == Formula::BurningShip
+            || self.recurrence.is_some() || self.initial_z.1 != 0.0
+            || self.y_min != -self.y_max || self.rotation_radians != 0.0 {
+            return None;
+        }
+        
+        // Sample rows are conjugate pairs when they sum to height - 2 * offset
+        let pair_sum = self.height as f64 - 2.0 * self.sample_offset.1;
+        if pair_sum.fract() != 0.0 {
+            return None;
+        }
+        
+        let mirror = pair_sum as i64 - y as i64;
+        (mirror >= 0 && (mirror as usize) < y).then_some(mirror as usize)
+    }
+
+    // Runs render_row over every row, in parallel unless the frame is set to
+    // render sequentially in top-to-bottom order
+    fn for_each_row<T, F>(&self, result: &mut [T], render_row: F)
+    where
+        T: Send,
+        F: Fn(usize, &mut [T]) + Sync,
+    {
+        let width = self.width as usize;
+        if self.sequential {
+            result.chunks_mut(width).enumerate().for_each(|(y, row)| render_row(y, row));
+        } else {
+            result.par_chunks_mut(width).enumerate().for_each(|(y, row)| render_row(y, row));
+        }
+    }
+
+    fn fill_mirrored_rows<T: Copy>(&self, result: &mut [T]) {
+        let width = self.width as usize;
+        for y in 0..self.height as usize {
+            if let Some(mirror) = self.mirror_row(y) {
+                result.copy_within(mirror * width..(mirror + 1) * width, y * width);
+            }
+        }
+    }
+
+    fn calculate_high_precision(&mut self, result: &mut [f32]) {
+        let (reference_x, reference_y) = self.choose_reference_point();
+        self.reference_point = Complex::new(reference_x, reference_y);
+        self.calculate_high_precision_orbit();
+
+        self.for_each_row(result, |y, row| {
+            // Mirrored rows are copied once the others are done
+            if self.mirror_row(y).is_some() {
+                return;
+            }
+            
+            for x in 0..self.width {
+                row[x as usize] = self.high_precision_pixel(x, y as u32);
+            }
+        });
+
+        self.fill_mirrored_rows(result);
+    }
+
+    // Whether high precision pixels are perturbed from the reference orbit in
+    // f64, which takes z^2 + c, rather than iterated in BigFloat
+    fn perturbs_high_precision(&self) -> bool {
+        self.formula == Formula::Mandelbrot && self.power == 2
+    }
+
+    // Value of a pixel in high precision. Its point is the exact view center
+    // plus its offset, bars rendering like the interior, black.
+    fn high_precision_pixel(&self, px: u32, py: u32) -> f32 {
+        let Some(offset) = self.pixel_offset(px, py) else {
+            return letterbox_bar_value(self.max_iterations);
+        };
+        let center = self.high_precision_center();
+        
+        // A reference that escapes at once leaves no step to perturb from
+        if self.perturbs_high_precision() && self.high_prec_orbit.len() >= 2 {
+            let reference = HighPrecComplex::new(self.reference_point.real, self.reference_point.imag);
+            let delta = Complex::new(
+                (center.real - reference.real).to_f64() + offset.0,
+                (center.imag - reference.imag).to_f64() + offset.1,
+            );
+            return self.iterate_high_precision_perturbed(delta);
+        }
+        let point = center.add(&HighPrecComplex::new(offset.0, offset.1));
+        self.iterate_high_precision(&point)
+    }
+
+    // The view center in high precision, exact_center while it still rounds
+    // to the view's f64 center, which moving the view undoes
+    fn high_precision_center(&self) -> HighPrecComplex {
+        let center = self.view_geometry().center;
+        let exact = self.exact_center.as_ref()
+            .filter(|(re, im)| (re.parse(), im.parse()) == (Ok(center.0), Ok(center.1)))
+            .and_then(|(re, im)| Some(HighPrecComplex { real: BigFloat::parse(re)?, imag: BigFloat::parse(im)? }));
+        exact.unwrap_or_else(|| HighPrecComplex::new(center.0, center.1))
+    }
+
+    // Iterates a pixel delta from the reference point in f64 against the
+    // high precision orbit, dz_{n+1} = 2 Z_m dz + dz^2 + dc. When z comes
+    // closer to zero than dz is large, or the orbit ends where the reference
+    // escaped, dz is rebased onto the start of the orbit, so z = Z_m + dz
+    // carries on exactly and dz stays small next to z without glitching.
+    fn iterate_high_precision_perturbed(&self, delta: Complex) -> f32 {
+        let orbit = &self.high_prec_orbit;
+        let (mut dz, dc) = match self.kind {
+            FractalKind::Mandelbrot => (Complex::new(0.0, 0.0), delta),
+            FractalKind::Julia { .. } => (delta, Complex::new(0.0, 0.0)),
+        };
+        let mut z = Complex::new(orbit[0].real + dz.real, orbit[0].imag + dz.imag);
+        let (mut m, mut n) = (0, 0);
+
+        while z.magnitude_squared() <= 4.0 && n < self.max_iterations as usize {
+            if m + 1 >= orbit.len() || z.magnitude_squared() < dz.magnitude_squared() {
+                dz = Complex::new(z.real - orbit[0].real, z.imag - orbit[0].imag);
+                m = 0;
+            }
+            let (twice_real, twice_imag) = (2.0 * orbit[m].real + dz.real, 2.0 * orbit[m].imag + dz.imag);
+            dz = Complex::new(
+                twice_real * dz.real - twice_imag * dz.imag + dc.real,
+                twice_real * dz.imag + twice_imag * dz.real + dc.imag,
+            );
+            m += 1;
+            n += 1;
+            z = Complex::new(orbit[m].real + dz.real, orbit[m].imag + dz.imag);
+        }
+
+        let (_, c) = self.seed(Complex::new(self.reference_point.real + delta.real, self.reference_point.imag + delta.imag));
+        self.smooth_color(z, c, n) as f32
+    }
+
+    // Each iterate is checked before it is stored, so the orbit holds only
+    // non-escaped values and the escaping one is recorded as escaped_at
+    fn calculate_high_precision_orbit(&mut self) {
+        self.high_prec_orbit.clear();
+        let reference = HighPrecComplex::new(self.reference_point.real, self.reference_point.imag);
+        let (mut z, c) = match self.kind {
+            FractalKind::Mandelbrot => (HighPrecComplex::new(self.initial_z.0, self.initial_z.1), reference.clone()),
+            FractalKind::Julia { cx, cy } => (reference.clone(), HighPrecComplex::new(cx, cy)),
+        };
+        
+        self.high_prec_orbit.reserve(self.max_iterations as usize);
+        
+        let mut escaped_at = None;
+        for n in 0..self.max_iterations {
+            if z.magnitude_squared() > BigFloat::from(4.0) {
+                escaped_at = Some(n);
+                break;
+            }
+            self.high_prec_orbit.push(Complex::new(z.real.to_f64(), z.imag.to_f64()));
+            
+            // z = fold(z)^power + c
+            z = z.fold(self.formula).powi(self.power).add(&c);
+        }
+        
+        self.reference_info = Some(ReferenceInfo {
+            point: (reference.real.to_f64(), reference.imag.to_f64()),
+            orbit_length: self.high_prec_orbit.len(),
+            escaped_at,
+            high_precision: true,
+            glitch_references: 0,
+        });
+    }
+
+    fn iterate_high_precision(&self, point: &HighPrecComplex) -> f32 {
+        let (mut z, c) = match self.kind {
+            FractalKind::Mandelbrot => (HighPrecComplex::new(self.initial_z.0, self.initial_z.1), point.clone()),
+            FractalKind::Julia { cx, cy } => (point.clone(), HighPrecComplex::new(cx, cy)),
+        };
+        let mut n = 0;
+
+        while z.magnitude_squared() <= BigFloat::from(4.0) && n < self.max_iterations as usize {
+            z = z.fold(self.formula).powi(self.power).add(&c);
+            n += 1;
+        }
+
+        // Past the bailout z fits in f64, which the smoothing iterations use
+        let to_f64 = |value: &HighPrecComplex| Complex::new(value.real.to_f64(), value.imag.to_f64());
+        self.smooth_color(to_f64(&z), to_f64(&c), n) as f32
+    }
+
+    fn calculate_standard(&mut self, result: &mut [f32]) {
+        let (reference_x, reference_y) = self.choose_reference_point();
+        self.reference_point = Complex::new(reference_x, reference_y);
+        self.calculate_reference_orbit();
+        
+        // A mask confirmed for another fractal says nothing about this one
+        let known_interior = self.known_interior.take().filter(|mask| self.confirms_interior() && mask.same_fractal(self));
+        if self.distance_estimation {
+            self.calculate_with_distances(result, known_interior.as_ref());
+            return;
+        }
+        if self.can_perturb() {
+            self.calculate_series();
+        }
+        if self.perturbs() {
+            self.calculate_perturbed(result);
+            return;
+        }
+        let sample = |x: u32, y: usize| {
+            let (x_coord, y_coord) = self.pixel_to_complex(x, y as u32);
+            
+            // Bars render like the interior, black
+            if self.in_letterbox_bar(x_coord, y_coord) {
+                return (letterbox_bar_value(self.max_iterations), false);
+            }
+            if known_interior.as_ref().is_some_and(|mask| mask.contains(x_coord, y_coord)) {
+                return (self.max_iterations as f32, true);
+            }
+            let (escape, confirmed) = self.iterate_standard_confirming(Complex::new(x_coord, y_coord));
+            (escape.value as f32, confirmed)
+        };
+        
+        if !self.confirms_interior() {
+            self.for_each_row(result, |y, row| {
+                // Mirrored rows are copied once the others are done
+                if self.mirror_row(y).is_some() {
+                    return;
+                }
+                for x in 0..self.width {
+                    row[x as usize] = sample(x, y).0;
+                }
+            });
+            self.fill_mirrored_rows(result);
+            return;
+        }
+        
+        // Also records which pixels are confirmed interior, for the next frame
+        let mut samples = vec![(0.0, false); result.len()];
+        self.for_each_row(&mut samples, |y, row| {
+            if self.mirror_row(y).is_some() {
+                return;
+            }
+            for x in 0..self.width {
+                row[x as usize] = sample(x, y);
+            }
+        });
+        self.fill_mirrored_rows(&mut samples);
+        for (value, (sample_value, _)) in result.iter_mut().zip(&samples) {
+            *value = *sample_value;
+        }
+        
+        let mut view = MandelbrotFrame::new(self.width, self.height);
+        (view.x_min, view.x_max, view.y_min, view.y_max) = (self.x_min, self.x_max, self.y_min, self.y_max);
+        view.rotation_radians = self.rotation_radians;
+        (view.kind, view.formula, view.power) = (self.kind, self.formula, self.power);
+        self.interior_mask = Some(InteriorMask { view: Box::new(view), confirmed: samples.into_iter().map(|(_, confirmed)| confirmed).collect() });
+    }
+
+    // Iterates every pixel carrying its derivative, and records its distance
+    // to the set in pixels for take_distance_estimates. In-set pixels and
+    // letterbox bars are at 0, as are pixels already confirmed interior by
+    // known_interior, and pixels of a formula without a complex derivative,
+    // the folded ones and custom recurrences, at infinity.
+    fn calculate_with_distances(&mut self, result: &mut [f32], known_interior: Option<&InteriorMask>) {
+        let pixel_size = self.view_geometry().half_size.0 * 2.0 / self.width as f64;
+        let mut samples = vec![(0.0, 0.0); result.len()];
+        self.for_each_row(&mut samples, |y, row| {
+            // Mirrored rows are copied once the others are done
+            if self.mirror_row(y).is_some() {
+                return;
+            }
+            for x in 0..self.width {
+                let (x_coord, y_coord) = self.pixel_to_complex(x, y as u32);
+                row[x as usize] = if self.in_letterbox_bar(x_coord, y_coord) {
+                    (letterbox_bar_value(self.max_iterations), 0.0)
+                } else if known_interior.is_some_and(|mask| mask.contains(x_coord, y_coord)) {
+                    (self.max_iterations as f32, 0.0)
+                } else {
+                    let point = Complex::new(x_coord, y_coord);
+                    let escape = self.iterate_standard(point);
+                    (escape.value as f32, (self.distance_estimate(point, &escape) / pixel_size) as f32)
+                };
+            }
+        });
+        self.fill_mirrored_rows(&mut samples);
+        for (value, (sample_value, _)) in result.iter_mut().zip(&samples) {
+            *value = *sample_value;
+        }
+        self.distance_estimates = Some(samples.into_iter().map(|(_, distance)| distance).collect());
+    }
+
+    // Perturbs every pixel from the reference orbit, then corrects the ones
+    // that glitched against new references, see correct_glitches
+    fn calculate_perturbed(&mut self, result: &mut [f32]) {
+        let mut samples = vec![None; result.len()];
+        self.for_each_row(&mut samples, |y, row| {
+            // Mirrored rows are copied once the others are done
+            if self.mirror_row(y).is_some() {
+                return;
+            }
+            for x in 0..self.width {
+                row[x as usize] = self.perturbed_pixel(x, y as u32);
+            }
+        });
+        self.fill_mirrored_rows(&mut samples);
+        self.correct_glitches(&mut samples);
+
+        // Pixels still glitched after the last pass are iterated directly
+        let width = self.width as usize;
+        for (index, (value, sample)) in result.iter_mut().zip(&samples).enumerate() {
+            *value = sample.unwrap_or_else(|| {
+                let (x_coord, y_coord) = self.pixel_to_complex((index % width) as u32, (index / width) as u32);
+                self.iterate_direct(Complex::new(x_coord, y_coord)) as f32
+            });
+        }
+    }
+
+    // Perturbed value of a pixel, None if it glitched. Bars render like the
+    // interior, black.
+    fn perturbed_pixel(&self, px: u32, py: u32) -> Option<f32> {
+        let (x_coord, y_coord) = self.pixel_to_complex(px, py);
+        if self.in_letterbox_bar(x_coord, y_coord) {
+            return Some(letterbox_bar_value(self.max_iterations));
+        }
+        let point = Complex::new(x_coord, y_coord);
+        let (z, c) = self.seed(point);
+        let (z, n) = self.iterate_perturbed(z, c)?;
+        Some(self.escape_value(point, z, c, n) as f32)
+    }
+
+    // Re-renders glitched pixels, the Nones, against a reference orbit placed
+    // at one of them, for up to max_glitch_passes passes or until none are
+    // left. A pixel never glitches against its own orbit, so each pass
+    // corrects at least the one it was placed at. The strategy's reference
+    // is restored afterwards, and reference_info counts the passes.
+    fn correct_glitches(&mut self, samples: &mut [Option<f32>]) {
+        let width = self.width as usize;
+        let primary_orbit = std::mem::take(&mut self.reference_orbit);
+        let (primary_point, primary_progress, primary_info) = (self.reference_point, self.orbit_progress, self.reference_info);
+        let primary_series = self.series;
+        let mut passes = 0;
+        for _ in 0..self.max_glitch_passes {
+            let Some(glitched) = samples.iter().position(Option::is_none) else { break };
+            let (x_coord, y_coord) = self.pixel_to_complex((glitched % width) as u32, (glitched / width) as u32);
+            self.reference_point = Complex::new(x_coord, y_coord);
+            self.calculate_reference_orbit();
+            self.calculate_series();
+
+            self.for_each_row(samples, |y, row| {
+                if self.mirror_row(y).is_some() {
+                    return;
+                }
+                for x in 0..self.width {
+                    if row[x as usize].is_none() {
+                        row[x as usize] = self.perturbed_pixel(x, y as u32);
+                    }
+                }
+            });
+            self.fill_mirrored_rows(samples);
+            passes += 1;
+        }
+        
+        (self.reference_point, self.orbit_progress, self.series) = (primary_point, primary_progress, primary_series);
+        self.reference_orbit = primary_orbit;
+        self.reference_info = primary_info.map(|info| ReferenceInfo { glitch_references: passes, ..info });
+    }
+
+    // Whether calculate finds confirmed interior points, which takes
+    // periodicity checking and so the f64 built-in recurrence
+    fn confirms_interior(&self) -> bool {
+        self.periodicity.is_some() && self.recurrence.is_none() && self.standard_float == StandardFloat::F64
+            && !self.uses_high_precision()
+    }
+
+    // Confirmed interior found by the last calculation, to pass on to the next
+    // frame's known_interior. None unless periodicity checking was on.
+    pub fn take_interior_mask(&mut self) -> Option<InteriorMask> {
+        self.interior_mask.take()
+    }
+
+    // Each pixel's distance to the set in pixels, from the last calculation if
+    // it had distance_estimation on. Supersampled calculations keep their first
+    // sample's, like saved buffers do. None for high precision frames, whose
+    // pixels don't carry the derivative.
+    pub fn take_distance_estimates(&mut self) -> Option<Vec<f32>> {
+        self.distance_estimates.take()
+    }
+
+    // The reference strategy's point. The sampling strategies iterate a
+    // REFERENCE_SAMPLES square grid of pixels directly, a small fraction of
+    // the frame's cost.
+    fn choose_reference_point(&self) -> (f64, f64) {
+        let center = self.view_geometry().center;
+        let lifetime = |(re, im): (f64, f64)| self.iterate_direct(Complex::new(re, im)) as f32;
+        if self.reference_strategy == ReferenceStrategy::Center
+            || (self.reference_strategy == ReferenceStrategy::Auto && lifetime(center) >= self.max_iterations as f32) {
+            return center;
+        }
+
+        let n = REFERENCE_SAMPLES;
+        let samples: Vec<((f64, f64), f32)> = (0..n * n)
+            .map(|i| {
+                let x = ((i % n) as f64 + 0.5) / n as f64 * self.width as f64;
+                let y = ((i / n) as f64 + 0.5) / n as f64 * self.height as f64;
+                let point = self.pixel_to_complex(x as u32, y as u32);
+                (point, lifetime(point))
+            })
+            .collect();
+        let distance = |(re, im): (f64, f64)| (re - center.0).hypot(im - center.1);
+        let closest = |a: &&((f64, f64), f32), b: &&((f64, f64), f32)| distance(b.0).total_cmp(&distance(a.0));
+
+        let chosen = match self.reference_strategy {
+            ReferenceStrategy::MaxDetail => {
+                // Detail is how far a sample's count is from its grid neighbors'
+                let detail = |i: u32| {
+                    let (x, y) = (i % n, i / n);
+                    [(x.wrapping_sub(1), y), (x + 1, y), (x, y.wrapping_sub(1)), (x, y + 1)].iter()
+                        .filter(|&&(nx, ny)| nx < n && ny < n)
+                        .map(|&(nx, ny)| (samples[(ny * n + nx) as usize].1 - samples[i as usize].1).abs())
+                        .sum::<f32>()
+                };
+                (0..n * n).max_by(|&a, &b| detail(a).total_cmp(&detail(b))).map(|i| &samples[i as usize])
+            }
+            _ => samples.iter()
+                .filter(|(_, lifetime)| *lifetime >= self.max_iterations as f32)
+                .max_by(closest),
+        };
+        chosen.map_or(center, |(point, _)| *point)
+    }
+
+    fn calculate_reference_orbit(&mut self) {
+        // A series fitted to the previous orbit doesn't apply to this one
+        self.series = None;
+        let point = self.reference_point;
+        let (initial_z, c) = self.seed(point);
+        
+        // The orbit only depends on the reference and the fractal, so with just more
+        // iterations the existing orbit is a prefix and is continued
+        let progress = self.orbit_progress.filter(|progress| {
+            progress.point == (point.real, point.imag) && self.orbit_fits(progress)
+                && progress.iterations <= self.max_iterations
+        });
+        let (start, mut z, mut escaped_at, mut finished) = match progress {
+            Some(progress) => (progress.iterations, progress.next, progress.escaped_at, progress.finished),
+            None => {
+                self.reference_orbit.clear();
+                (0, initial_z, None, false)
+            }
+        };
+        
+        self.reference_orbit.reserve((self.max_iterations as usize).saturating_sub(self.reference_orbit.len()));
+        
+        for n in start..self.max_iterations {
+            if finished {
+                break;
+            }
+            if z.magnitude_squared() > 4.0 {
+                escaped_at = Some(n);
+                finished = true;
+                break;
+            }
+            self.reference_orbit.push(z);
+            z = self.formula.fold(z);
+            
+            // Calculate with error tracking
+            let raised = z.powi(self.power);
+            
+            // Track numerical errors
+            let error = z.magnitude_squared().powf(self.power as f64 / 2.0) * f64::EPSILON;
+            
+            z = Complex::with_error(
+                raised.real + c.real,
+                raised.imag + c.imag,
+                z.error + error
+            );
+            
+            // If error gets too large, break early
+            if z.error > 1e-6 {
+                finished = true;
+                break;
+            }
+        }
+        
+        self.orbit_progress = Some(OrbitProgress {
+            point: (point.real, point.imag),
+            kind: self.kind,
+            formula: self.formula,
+            power: self.power,
+            initial_z: self.initial_z,
+            iterations: self.max_iterations,
+            next: z,
+            escaped_at,
+            finished,
+        });
+        self.reference_info = Some(ReferenceInfo {
+            point: (point.real, point.imag),
+            orbit_length: self.reference_orbit.len(),
+            escaped_at,
+            high_precision: false,
+            glitch_references: 0,
+        });
+    }
+
+    // Whether the orbit progress was calculated for the frame's fractal,
+    // whatever its reference point
+    fn orbit_fits(&self, progress: &OrbitProgress) -> bool {
+        progress.kind == self.kind && progress.formula == self.formula && progress.power == self.power
+            && progress.initial_z == self.initial_z
+    }
+
+    // Whether pixels are perturbed from the reference orbit: always when
+    // perturbation is on, and for Auto where the series pays off
+    fn perturbs(&self) -> bool {
+        self.can_perturb() && (self.perturbation == Perturbation::On || self.series_pays_off())
+    }
+
+    // Whether pixels could be perturbed, which takes the built-in z^2 + c in
+    // f64 without periodicity checking
+    fn can_perturb(&self) -> bool {
+        self.perturbation != Perturbation::Off && self.recurrence.is_none() && self.formula == Formula::Mandelbrot && self.power == 2
+            && self.standard_float == StandardFloat::F64 && self.periodicity.is_none()
+    }
+
+    // Fits the series that skips the first perturbed iterations of every
+    // pixel. Its coefficients follow from dz -> 2 Z dz + dz^2 + dc:
+    // A -> 2 Z A + 1 (2 Z A for Julia sets), B -> 2 Z B + A^2 and
+    // C -> 2 Z C + 2 A B. Probe pixels at the corners, the middle of each edge
+    // and the center of the view, the edges furthest from the reference, are
+    // perturbed alongside, and the series skips as far as it matches all of
+    // them within SERIES_TOLERANCE.
+    fn calculate_series(&mut self) {
+        self.series = None;
+        if self.orbit_progress.is_none_or(|progress| !self.orbit_fits(&progress)) {
+            return;
+        }
+        let (reference_z, reference_c) = self.seed(self.reference_point);
+        let corners = [0, self.width / 2, self.width.saturating_sub(1)].into_iter()
+            .flat_map(|x| [0, self.height / 2, self.height.saturating_sub(1)].map(|y| (x, y)));
+        let mut probes: Vec<(Complex, Complex, Complex)> = corners
+            .map(|(x, y)| {
+                let (x_coord, y_coord) = self.pixel_to_complex(x, y);
+                let (z, c) = self.seed(Complex::new(x_coord, y_coord));
+                let dz = Complex::new(z.real - reference_z.real, z.imag - reference_z.imag);
+                let dc = Complex::new(c.real - reference_c.real, c.imag - reference_c.imag);
+                let d = match self.kind {
+                    FractalKind::Mandelbrot => dc,
+                    FractalKind::Julia { .. } => dz,
+                };
+                (d, dz, dc)
+            })
+            .collect();
+
+        let (one, zero) = (Complex::new(1.0, 0.0), Complex::new(0.0, 0.0));
+        let (mut series, constant) = match self.kind {
+            FractalKind::Mandelbrot => (Series { skip: 0, a: zero, b: zero, c: zero }, one),
+            FractalKind::Julia { .. } => (Series { skip: 0, a: one, b: zero, c: zero }, zero),
+        };
+        for (n, pair) in self.reference_orbit.windows(2).enumerate() {
+            let twice = Complex::new(2.0 * pair[0].real, 2.0 * pair[0].imag);
+            let next = Series {
+                skip: n + 1,
+                a: twice * series.a + constant,
+                b: twice * series.b + series.a * series.a,
+                c: twice * series.c + Complex::new(2.0, 0.0) * series.a * series.b,
+            };
+            for (_, dz, dc) in probes.iter_mut() {
+                *dz = (twice + *dz) * *dz + *dc;
+            }
+            let matches = probes.iter().all(|(d, dz, _)| {
+                let approximation = next.offset(*d);
+                let error = Complex::new(approximation.real - dz.real, approximation.imag - dz.imag);
+                let z = Complex::new(pair[1].real + dz.real, pair[1].imag + dz.imag);
+                error.magnitude_squared() <= SERIES_TOLERANCE * SERIES_TOLERANCE * dz.magnitude_squared()
+                    && z.magnitude_squared() <= 4.0 && z.magnitude_squared() >= GLITCH_TOLERANCE * pair[1].magnitude_squared()
+            });
+            if !matches {
+                break;
+            }
+            series = next;
+        }
+        self.series = (series.skip > 0).then_some(series);
+    }
+
+    // Whether the series skips enough of the reference orbit for perturbing to
+    // beat direct iteration. Perturbed steps cost around one and a half
+    // direct ones, so views where it skips less than half the orbit, and most
+    // views short of a zoom of about 1e8, render faster directly.
+    fn series_pays_off(&self) -> bool {
+        self.series.is_some_and(|series| series.skip * 2 >= self.reference_orbit.len())
+    }
+
+    // Iterates the pixel's offset from the reference orbit Z_n rather than z
+    // itself, dz_{n+1} = 2 Z_n dz_n + dz_n^2 + dc, where z_n = Z_n + dz_n. Once
+    // the orbit runs out, because the reference escaped or lost precision, or
+    // without an orbit for this fractal, z is iterated directly. Returns the
+    // final z and iteration count like the loops of iterate_standard, or None
+    // if the pixel glitched, see GLITCH_TOLERANCE.
+    fn iterate_perturbed(&self, mut z: Complex, c: Complex) -> Option<(Complex, usize)> {
+        let max_iterations = self.max_iterations as usize;
+        let mut n = 0;
+
+        if let Some(progress) = self.orbit_progress.filter(|progress| self.orbit_fits(progress)) {
+            let (reference_z, reference_c) = self.seed(Complex::new(progress.point.0, progress.point.1));
+            let mut dz = Complex::new(z.real - reference_z.real, z.imag - reference_z.imag);
+            let dc = Complex::new(c.real - reference_c.real, c.imag - reference_c.imag);
+
+            // The series takes dz straight to its skip-th iteration
+            let mut start = 0;
+            if let Some(series) = self.series.filter(|series| series.skip < max_iterations) {
+                let d = match self.kind {
+                    FractalKind::Mandelbrot => dc,
+                    FractalKind::Julia { .. } => dz,
+                };
+                dz = series.offset(d);
+                let reference = self.reference_orbit[series.skip];
+                z = Complex::new(reference.real + dz.real, reference.imag + dz.imag);
+                (start, n) = (series.skip, series.skip);
+            }
+
+            // Each step takes Z_n to the stored Z_{n+1}
+            for pair in self.reference_orbit[start..].windows(2) {
+                if z.magnitude_squared() > 4.0 || n >= max_iterations {
+                    return Some((z, n));
+                }
+                let (reference, next) = (pair[0], pair[1]);
+                let (twice_real, twice_imag) = (2.0 * reference.real + dz.real, 2.0 * reference.imag + dz.imag);
+                dz = Complex::new(
+                    twice_real * dz.real - twice_imag * dz.imag + dc.real,
+                    twice_real * dz.imag + twice_imag * dz.real + dc.imag,
+                );
+                z = Complex::new(next.real + dz.real, next.imag + dz.imag);
+                n += 1;
+                if z.magnitude_squared() < GLITCH_TOLERANCE * next.magnitude_squared() {
+                    return None;
+                }
+            }
+        }
+
+        while z.magnitude_squared() <= 4.0 && n < max_iterations {
+            z = self.step(z, c);
+            n += 1;
+        }
+        Some((z, n))
+    }
+
+    // Starting z and the constant c at a point: the point is c for the
+    // Mandelbrot set, seeded with the initial z, and the starting z for Julia sets
+    fn seed(&self, point: Complex) -> (Complex, Complex) {
+        match self.kind {
+            FractalKind::Mandelbrot => (Complex::new(self.initial_z.0, self.initial_z.1), point),
+            FractalKind::Julia { cx, cy } => (point, Complex::new(cx, cy)),
+        }
+    }
+
+    // One step of the frame's recurrence, the custom one if set, else the
+    // formula's
+    fn step(&self, z: Complex, c: Complex) -> Complex {
+        match self.recurrence {
+            Some(recurrence) => recurrence(z, c),
+            None => {
+                let z = self.formula.fold(z).powi(self.power);
+                Complex::new(z.real + c.real, z.imag + c.imag)
+            }
+        }
+    }
+
+    // Continuous escape count of a point, in f64 until it's stored in an f32
+    // iteration buffer, which holds the fraction to well under 0.01 below a
+    // hundred thousand iterations
+    fn iterate_standard(&self, point: Complex) -> Escape {
+        self.iterate_standard_confirming(point).0
+    }
+
+    // iterate_standard, also returning whether periodicity checking caught the
+    // orbit in a cycle, confirming the point is in the set
+    fn iterate_standard_confirming(&self, point: Complex) -> (Escape, bool) {
+        let (mut z, c) = self.seed(point);
+        let mut n = 0;
+        let mut caught = false;
+        let mut derivative = None;
+
+        // Kept out of the loop below so the built-in recurrence stays inlined
+        if let Some(recurrence) = self.recurrence {
+            while z.magnitude_squared() <= 4.0 && n < self.max_iterations as usize {
+                z = recurrence(z, c);
+                n += 1;
+            }
+        } else if self.distance_estimation && self.formula == Formula::Mandelbrot {
+            let carried;
+            (z, n, carried) = self.iterate_with_derivative(z, c);
+            derivative = Some(carried);
+        } else if self.standard_float == StandardFloat::F32 {
+            (z, n) = Self::iterate_f32(z, c, self.max_iterations as usize, self.formula, self.power);
+        } else if let Some(periodicity) = self.periodicity {
+            (z, n, caught) = Self::iterate_periodic(z, c, self.max_iterations as usize, periodicity, self.formula, self.power);
+        } else if let Some(perturbed) = self.perturbs().then(|| self.iterate_perturbed(z, c)).flatten() {
+            (z, n) = perturbed;
+        } else {
+            // Glitched perturbed pixels start over here
+            (z, n) = self.iterate_built_in(point);
+        }
+
+        (Escape { value: self.escape_value(point, z, c, n), z, iterations: n, derivative }, caught)
+    }
+
+    // The built-in recurrence of iterate_standard carrying the derivative
+    // along, dz' -> power z^(power - 1) dz' + 1 for the Mandelbrot set and
+    // without the 1 for Julia sets. Returns the final z, iteration count and
+    // derivative.
+    fn iterate_with_derivative(&self, mut z: Complex, c: Complex) -> (Complex, usize, Complex) {
+        let (mut derivative, dc) = self.derivative_seed();
+        let power = Complex::new(self.power as f64, 0.0);
+        let mut n = 0;
+        while z.magnitude_squared() <= 4.0 && n < self.max_iterations as usize {
+            derivative = power * z.powi(self.power - 1) * derivative + dc;
+            let raised = z.powi(self.power);
+            z.real = raised.real + c.real;
+            z.imag = raised.imag + c.imag;
+            n += 1;
+        }
+        (z, n, derivative)
+    }
+
+    // Starting derivative and what each step adds to it: dz/dc starts at 0
+    // and gains 1 for the Mandelbrot set, dz/dz0 starts at 1 for Julia sets
+    fn derivative_seed(&self) -> (Complex, Complex) {
+        match self.kind {
+            FractalKind::Mandelbrot => (Complex::new(0.0, 0.0), Complex::new(1.0, 0.0)),
+            FractalKind::Julia { .. } => (Complex::new(1.0, 0.0), Complex::new(0.0, 0.0)),
+        }
+    }
+
+    // |z| ln|z| / |dz'| in the plane's units, once the escaped orbit is taken
+    // on to DISTANCE_BAILOUT short of overflowing, as the estimate stalls near
+    // the tips with only the usual bailout. 0 in the set, infinity without a
+    // derivative.
+    fn distance_estimate(&self, point: Complex, escape: &Escape) -> f64 {
+        let Some(mut derivative) = escape.derivative else {
+            return f64::INFINITY;
+        };
+        if escape.iterations >= self.max_iterations as usize {
+            return 0.0;
+        }
+        let (_, c) = self.seed(point);
+        let (_, dc) = self.derivative_seed();
+        let power = Complex::new(self.power as f64, 0.0);
+        let mut z = escape.z;
+        for _ in 0..self.max_iterations {
+            let next = z.powi(self.power) + c;
+            if z.magnitude_squared() > DISTANCE_BAILOUT || !next.magnitude_squared().is_finite() {
+                break;
+            }
+            derivative = power * z.powi(self.power - 1) * derivative + dc;
+            z = next;
+        }
+        let magnitude = z.magnitude_squared().sqrt();
+        magnitude * magnitude.ln() / derivative.magnitude_squared().sqrt()
+    }
+
+    // iterate_standard without perturbation
+    fn iterate_direct(&self, point: Complex) -> f64 {
+        let (_, c) = self.seed(point);
+        let (z, n) = self.iterate_built_in(point);
+        self.escape_value(point, z, c, n)
+    }
+
+    // Iterates the f64 built-in recurrence from the point's seed, returning
+    // the final z and iteration count
+    fn iterate_built_in(&self, point: Complex) -> (Complex, usize) {
+        let (mut z, c) = self.seed(point);
+        let mut n = 0;
+        while z.magnitude_squared() <= 4.0 && n < self.max_iterations as usize {
+            let raised = self.formula.fold(z).powi(self.power);
+            z.real = raised.real + c.real;
+            z.imag = raised.imag + c.imag;
+            n += 1;
+        }
+        (z, n)
+    }
+
+    // Value of a point whose orbit stopped at z after n iterations
+    fn escape_value(&self, point: Complex, z: Complex, c: Complex, n: usize) -> f64 {
+        let escape = if self.capped_lookahead && n >= self.max_iterations as usize {
+            self.capped_value(z, c)
+        } else {
+            self.smooth_color(z, c, n)
+        };
+        self.finite_escape(point, z, escape)
+    }
+
+    // Value of a point that reached max_iterations, given its z there. It is
+    // iterated for up to max_iterations more, and if it escapes it's just
+    // outside the set rather than in it, so it gets a value in
+    // max_iterations - 1..max_iterations, higher the later it escapes. Those
+    // points then take the color of the set's edge instead of the interior's.
+    fn capped_value(&self, mut z: Complex, c: Complex) -> f64 {
+        let max_iterations = self.max_iterations;
+        for n in 0..max_iterations {
+            if z.magnitude_squared() > 4.0 {
+                return max_iterations as f64 - 1.0 + n as f64 / (max_iterations + 1) as f64;
+            }
+            z = self.step(z, c);
+        }
+        max_iterations as f64
+    }
+
+    // The built-in recurrence of iterate_standard with periodicity checking,
+    // returning the final z, the iteration count, max_iterations once caught,
+    // and whether it was caught in a cycle
+    fn iterate_periodic(mut z: Complex, c: Complex, max_iterations: usize, periodicity: Periodicity, formula: Formula, power: u32) -> (Complex, usize, bool) {
+        let epsilon_squared = periodicity.epsilon * periodicity.epsilon;
+        let mut saved = z;
+        let mut check_period = periodicity.check_period.max(1) as usize;
+        let mut next_save = check_period;
+        let mut n = 0;
+
+        while z.magnitude_squared() <= 4.0 && n < max_iterations {
+            let raised = formula.fold(z).powi(power);
+            z.real = raised.real + c.real;
+            z.imag = raised.imag + c.imag;
+            n += 1;
+
+            let (dx, dy) = (z.real - saved.real, z.imag - saved.imag);
+            if dx * dx + dy * dy < epsilon_squared {
+                return (z, max_iterations, true);
+            }
+            if n == next_save {
+                saved = z;
+                check_period *= 2;
+                next_save += check_period;
+            }
+        }
+
+        (z, n, false)
+    }
+
+    // The built-in recurrence of iterate_standard in f32, returning the final z
+    // and iteration count
+    fn iterate_f32(z: Complex, c: Complex, max_iterations: usize, formula: Formula, power: u32) -> (Complex, usize) {
+        let (mut real, mut imag) = (z.real as f32, z.imag as f32);
+        let (c_real, c_imag) = (c.real as f32, c.imag as f32);
+        let mut n = 0;
+
+        while real * real + imag * imag <= 4.0 && n < max_iterations {
+            (real, imag) = match formula {
+                Formula::Mandelbrot => (real, imag),
+                Formula::BurningShip => (real.abs(), imag.abs()),
+                Formula::Tricorn => (real, -imag),
+            };
+            let (mut raised_real, mut raised_imag) = (real, imag);
+            for _ in 1..power {
+                (raised_real, raised_imag) = (raised_real * real - raised_imag * imag, raised_real * imag + raised_imag * real);
+            }
+            real = raised_real + c_real;
+            imag = raised_imag + c_imag;
+            n += 1;
+        }
+
+        (Complex::new(real as f64, imag as f64), n)
+    }
+
+    // A NaN or infinite z or escape value is a bug upstream. Debug builds panic
+    // naming the point, release builds clamp the value into 0..=max_iterations.
+    fn finite_escape(&self, point: Complex, z: Complex, escape: f64) -> f64 {
+        debug_assert!(
+            z.real.is_finite() && z.imag.is_finite() && escape.is_finite(),
+            "Non-finite iteration at {} + {}i: z = {} + {}i, escape value {}",
+            point.real, point.imag, z.real, z.imag, escape
+        );
+        if escape.is_nan() { 0.0 } else { escape.clamp(0.0, self.max_iterations as f64) }
+    }
+
+    // Iterates c from the initial z with the same recurrence as iterate_standard,
+    // recording each z in the orbit. Returns whether c escaped within max_iterations.
+    pub fn trace_orbit(&self, c: (f64, f64), orbit: &mut Vec<(f64, f64)>) -> bool {
+        let c = Complex::new(c.0, c.1);
+        let mut z = Complex::new(self.initial_z.0, self.initial_z.1);
+        orbit.clear();
+
+        for _ in 0..self.max_iterations {
+            if z.magnitude_squared() > 4.0 {
+                return true;
+            }
+            z = self.step(z, c);
+            orbit.push((z.real, z.imag));
+        }
+
+        z.magnitude_squared() > 4.0
+    }
+
+    // Period and points of the attracting cycle the orbit of a point in the set
+    // settles into, as found by iterating until the orbit returns to where it
+    // was after settling. None if the point escapes or hasn't settled onto a
+    // cycle of at most MAX_PERIOD points, as happens near bulb boundaries.
+    pub fn attracting_cycle(&self, point: (f64, f64)) -> Option<(usize, Vec<(f64, f64)>)> {
+        let point = Complex::new(point.0, point.1);
+        let (mut z, c) = self.seed(point);
+        let step = |z: Complex| self.step(z, c);
+
+        for _ in 0..self.max_iterations.max(PERIOD_SETTLE_ITERATIONS) {
+            if z.magnitude_squared() > 4.0 {
+                return None;
+            }
+            z = step(z);
+        }
+
+        let settled = z;
+        let mut cycle = Vec::new();
+        for period in 1..=MAX_PERIOD {
+            cycle.push((z.real, z.imag));
+            z = step(z);
+            let (dx, dy) = (z.real - settled.real, z.imag - settled.imag);
+            if dx * dx + dy * dy < PERIOD_TOLERANCE * PERIOD_TOLERANCE {
+                return Some((period, cycle));
+            }
+        }
+        None
+    }
+
+    // Continuous iteration count from the escape iteration and z at escape,
+    // with the fractional part weighted by the escape smoothing. In-set points
+    // get exactly max_iterations, and escaping ones stay below it, even once
+    // rounded to f32.
+    fn smooth_color(&self, z: Complex, c: Complex, n: usize) -> f64 {
+        if n >= self.max_iterations as usize {
+            return self.max_iterations as f64;
+        }
+
+        // The log-log estimate assumes |z| far past the bailout, so it steps
+        // where the escape count does unless z is iterated a little further.
+        // A recurrence that overflows doing so is estimated at escape.
+        let mut extended = z;
+        for _ in 0..ESCAPE_EXTENSION {
+            extended = self.step(extended, c);
+        }
+        let (magnitude_squared, extra) = match extended.magnitude_squared() {
+            magnitude_squared if magnitude_squared.is_finite() => (magnitude_squared, ESCAPE_EXTENSION),
+            _ => (z.magnitude_squared(), 0),
+        };
+
+        // |z| grows to the power each step, so its log-log grows by ln(power)
+        let fraction = (extra + 1) as f64 - magnitude_squared.ln().ln() / (self.power as f64).ln();
+        let value = (n as f64 + fraction * self.escape_smoothing as f64).max(0.0);
+        value.min((self.max_iterations as f32).next_down() as f64)
+    }
+} 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn complex_to_pixel_inverts_pixel_to_complex() {
+        for origin in [SampleOrigin::Center, SampleOrigin::Corner] {
+            let mut frame = MandelbrotFrame::new(64, 48);
+            frame.sample_offset = origin.offset();
+            frame.set_view(-0.75, 0.1, 3.0);
+            for (px, py) in [(0, 0), (63, 0), (0, 47), (63, 47), (32, 24)] {
+                let (re, im) = frame.pixel_to_complex(px, py);
+                let (x, y) = frame.complex_to_pixel(re, im);
+                assert!((x - px as f32).abs() < 1e-3 && (y - py as f32).abs() < 1e-3,
+                        "{:?} pixel ({}, {}) maps back to ({}, {})", origin, px, py, x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn sample_offsets_lie_within_the_pixel_and_average_to_its_center() {
+        for pattern in [SamplePattern::Grid, SamplePattern::RotatedGrid, SamplePattern::Halton] {
+            for factor in 1..=8 {
+                let offsets = AntiAliasing { factor, pattern, boundary_radius: None }.sample_offsets();
+                assert_eq!(offsets.len() as u32, factor * factor);
+                for &(x, y) in &offsets {
+                    assert!((0.0..=1.0).contains(&x) && (0.0..=1.0).contains(&y), "{:?} x{} offset ({}, {}) outside the pixel", pattern, factor, x, y);
+                }
+                let count = offsets.len() as f64;
+                let mean = offsets.iter().fold((0.0, 0.0), |sum, &(x, y)| (sum.0 + x / count, sum.1 + y / count));
+                assert!((mean.0 - 0.5).abs() < 1e-12 && (mean.1 - 0.5).abs() < 1e-12, "{:?} x{} offsets average to {:?}", pattern, factor, mean);
+            }
+        }
+    }
+
+    #[test]
+    fn mirrored_rows_match_a_full_render() {
+        let mut frame = MandelbrotFrame::new(64, 48);
+        frame.max_iterations = 200;
+        frame.set_view(-0.5, 0.0, 1.0);
+        assert!((0..48).any(|y| frame.mirror_row(y).is_some()), "view should be mirrored");
+        
+        let mirrored = frame.calculate();
+        for y in 0..48 {
+            for x in 0..64 {
+                let (value, direct) = (mirrored[y as usize * 64 + x as usize], frame.iterate_pixel(x, y));
+                assert!((value - direct).abs() < 1e-3, "pixel ({}, {}) is {} mirrored but {} rendered", x, y, value, direct);
+            }
+        }
+    }
+
+    #[test]
+    fn letterbox_bars_are_not_boundary_pixels() {
+        let mut frame = MandelbrotFrame::new(96, 32);
+        frame.max_iterations = 100;
+        frame.set_view(-0.5, 0.0, 1.0);
+        let iterations = frame.calculate();
+        let (width, max_iterations) = (96, frame.max_iterations);
+        let bar = |i: usize| is_letterbox_bar(iterations[i], max_iterations);
+        assert!((0..iterations.len()).any(bar), "fitting a wide frame should leave bars");
+
+        // Escaping samples whose only other-side neighbours are bars
+        let beside_bars: Vec<usize> = (0..iterations.len())
+            .filter(|&i| !bar(i) && iterations[i] < max_iterations as f32)
+            .filter(|&i| [i.wrapping_sub(1), i + 1].iter().any(|&n| n / width == i / width && bar(n)))
+            .filter(|&i| [i.wrapping_sub(1), i + 1, i.wrapping_sub(width), i + width].iter()
+                .all(|&n| n >= iterations.len() || bar(n) || iterations[n] < max_iterations as f32))
+            .collect();
+        assert!(!beside_bars.is_empty());
+
+        let boundary = frame.boundary_pixels(&iterations, 0);
+        assert!(boundary.iter().all(|&i| !bar(i)), "bar pixels marked as boundary");
+        assert!(beside_bars.iter().all(|i| !boundary.contains(i)), "bar edges marked as boundary");
+    }
+
+    #[test]
+    fn sequential_render_matches_parallel() {
+        let mut frame = MandelbrotFrame::new(64, 48);
+        frame.max_iterations = 300;
+        frame.set_view(-0.75, 0.1, 4.0);
+        let parallel = frame.calculate();
+        frame.sequential = true;
+        let sequential = frame.calculate();
+        assert_eq!(sequential, parallel);
+    }
+
+    #[test]
+    fn extending_the_reference_orbit_matches_a_full_recompute() {
+        let orbit = |frame: &MandelbrotFrame| -> Vec<(f64, f64, f64)> {
+            frame.reference_orbit.iter().map(|z| (z.real, z.imag, z.error)).collect()
+        };
+        let mut extended = MandelbrotFrame::new(8, 8);
+        extended.reference_point = Complex::new(-0.5, 0.1);
+        extended.max_iterations = 500;
+        extended.calculate_reference_orbit();
+        extended.max_iterations = 2000;
+        extended.calculate_reference_orbit();
+
+        let mut recomputed = MandelbrotFrame::new(8, 8);
+        recomputed.reference_point = Complex::new(-0.5, 0.1);
+        recomputed.max_iterations = 2000;
+        recomputed.calculate_reference_orbit();
+
+        assert_eq!(extended.reference_orbit.len(), 2000);
+        assert_eq!(orbit(&extended), orbit(&recomputed));
+    }
+
+    #[test]
+    fn center_sampling_puts_the_center_pixel_at_the_view_midpoint() {
+        let mut frame = MandelbrotFrame::new(65, 49);
+        frame.set_view(-0.75, 0.1, 3.0);
+        let (re, im) = frame.pixel_to_complex(32, 24);
+        let midpoint = ((frame.x_min + frame.x_max) / 2.0, (frame.y_min + frame.y_max) / 2.0);
+        assert!((re - midpoint.0).abs() <= 1e-15 && (im - midpoint.1).abs() <= 1e-15, "({}, {}) is off {:?}", re, im, midpoint);
+    }
+
+    #[test]
+    fn high_precision_orbit_stops_before_escaping() {
+        let mut frame = MandelbrotFrame::new(8, 8);
+        frame.max_iterations = 1000;
+        for reference in [(0.5, 0.5), (-0.75, 0.1), (0.26, 0.0), (-2.1, 0.0)] {
+            frame.reference_point = Complex::new(reference.0, reference.1);
+            frame.calculate_high_precision_orbit();
+            let info = frame.reference_info.unwrap();
+            assert_eq!(info.escaped_at, Some(frame.high_prec_orbit.len() as u32), "{:?} should escape", reference);
+            if let Some(last) = frame.high_prec_orbit.last() {
+                assert!(last.magnitude_squared() <= 4.0, "{:?} orbit ends at |z|^2 = {}", reference, last.magnitude_squared());
+            }
+        }
+    }
+
+    #[test]
+    fn f32_matches_f64_at_zoom_one_and_diverges_deep() {
+        let differing = |zoom: f64| {
+            let mut frame = MandelbrotFrame::new(80, 60);
+            frame.max_iterations = 200;
+            frame.set_view(-0.743643887, 0.131825904, zoom);
+            let f64_iterations = frame.calculate();
+            frame.standard_float = StandardFloat::F32;
+            let f32_iterations = frame.calculate();
+            f64_iterations.iter().zip(&f32_iterations).filter(|(a, b)| (*a - *b).abs() > 1.0).count() as f64 / f64_iterations.len() as f64
+        };
+        assert!(differing(1.0) < 0.01);
+        assert!(differing(1e5) > 0.1);
+    }
+
+    #[test]
+    fn capped_lookahead_separates_interior_from_capped_but_growing() {
+        let mut frame = MandelbrotFrame::new(1, 1);
+        frame.max_iterations = 200;
+        // 0 is interior, 0.2502 escapes at around 220 iterations
+        let (interior, growing) = (Complex::new(0.0, 0.0), Complex::new(0.2502, 0.0));
+        assert_eq!((frame.iterate_standard(interior).value, frame.iterate_standard(growing).value), (200.0, 200.0));
+
+        frame.capped_lookahead = true;
+        assert_eq!(frame.iterate_standard(interior).value, 200.0);
+        let value = frame.iterate_standard(growing).value;
+        assert!((199.0..200.0).contains(&value), "{}", value);
+        assert_eq!(frame.in_set_mask(&[value as f32]), [false]);
+        assert_eq!(frame.in_set_mask(&[200.0]), [true]);
+    }
+
+    #[test]
+    fn loose_periodicity_epsilon_catches_escaping_points() {
+        // 0.2502 crawls past the parabolic fixed point at 0.25 before escaping
+        let mut frame = MandelbrotFrame::new(1, 1);
+        frame.max_iterations = 1000;
+        let point = Complex::new(0.2502, 0.0);
+        let escaped = frame.iterate_standard(point).value;
+        assert!(escaped < 1000.0);
+
+        frame.periodicity = Some(Periodicity { epsilon: 1e-2, check_period: 16 });
+        assert_eq!(frame.iterate_standard(point).value, 1000.0);
+        frame.periodicity = Some(DEFAULT_PERIODICITY);
+        assert_eq!(frame.iterate_standard(point).value, escaped);
+    }
+
+    #[test]
+    fn reference_strategy_places_the_perturbed_orbit() {
+        let frame = |strategy: ReferenceStrategy, perturbation: Perturbation| {
+            let mut frame = MandelbrotFrame::new(80, 60);
+            frame.max_iterations = 500;
+            frame.perturbation = perturbation;
+            frame.reference_strategy = strategy;
+            frame.set_view(-0.75, 0.1, 50.0);
+            let iterations = frame.calculate();
+            (iterations, frame.reference_info().unwrap())
+        };
+        let (direct, _) = frame(ReferenceStrategy::Center, Perturbation::Off);
+
+        // The center escapes early, leaving most of each pixel's orbit to direct iteration
+        let (_, center) = frame(ReferenceStrategy::Center, Perturbation::On);
+        assert!(center.escaped_at.is_some_and(|n| n < 100));
+
+        // Auto moves the reference to a point whose orbit lasts, and perturbing
+        // from it still matches direct iteration
+        let (perturbed, auto) = frame(ReferenceStrategy::Auto, Perturbation::On);
+        assert_ne!(auto.point, center.point);
+        assert_eq!((auto.escaped_at, auto.orbit_length), (None, 500));
+        let differing = direct.iter().zip(&perturbed).filter(|(a, b)| (*a - *b).abs() > 0.01).count();
+        assert!(differing < direct.len() / 100, "{} of {} pixels differ", differing, direct.len());
+    }
+
+    #[test]
+    fn adjacent_pixels_across_an_escape_count_boundary_get_nearby_colors() {
+        // A 4000 pixel wide row along the real axis, escaping ever later
+        // toward the cusp at 0.25
+        let mut frame = MandelbrotFrame::new(4000, 1);
+        frame.max_iterations = 100;
+        frame.x_min = 0.26;
+        frame.x_max = 0.6;
+        frame.y_min = -1e-6;
+        frame.y_max = 1e-6;
+        let mut color_handler = crate::color_handler::ColorHandler::new();
+        color_handler.set_cycle_length(Some(8.0));
+
+        frame.escape_smoothing = 0.0;
+        let counts = frame.calculate();
+
+        // Largest color change between neighbors whose whole escape count
+        // differs, relative to the changes between the neighbors either side
+        let mut largest_jump = |escape_smoothing: f32| {
+            frame.escape_smoothing = escape_smoothing;
+            let row = frame.calculate();
+            let colors: Vec<[f32; 3]> = row.iter().map(|&value| color_handler.get_color_precise(value, 100)).collect();
+            let change = |i: usize| (0..3).map(|c| (colors[i][c] - colors[i + 1][c]).abs()).fold(0.0, f32::max);
+            let boundaries: Vec<usize> = (1..row.len() - 2).filter(|&i| counts[i] != counts[i + 1]).collect();
+            assert!(boundaries.len() > 10);
+            boundaries.iter().map(|&i| change(i) - change(i - 1).max(change(i + 1))).fold(f32::MIN, f32::max)
+        };
+        let (smooth, banded) = (largest_jump(1.0), largest_jump(0.0));
+        assert!(smooth < 0.01, "{}", smooth);
+        assert!(banded > 0.1, "{}", banded);
+    }
+
+    #[test]
+    fn each_formula_renders_a_different_set() {
+        let in_set_count = |formula: Formula| {
+            let mut frame = MandelbrotFrame::new(60, 60);
+            (frame.x_min, frame.x_max, frame.y_min, frame.y_max) = (-2.0, 1.0, -1.5, 1.5);
+            frame.max_iterations = 100;
+            frame.formula = formula;
+            let iterations = frame.calculate();
+            frame.in_set_mask(&iterations).iter().filter(|&&in_set| in_set).count()
+        };
+        let counts = [Formula::Mandelbrot, Formula::BurningShip, Formula::Tricorn].map(in_set_count);
+        assert!(counts.iter().all(|&count| count > 0), "{:?}", counts);
+        assert!(counts[0] != counts[1] && counts[1] != counts[2] && counts[0] != counts[2], "{:?}", counts);
+    }
+
+    #[test]
+    fn perturbation_matches_direct_iteration() {
+        let views = [
+            (FractalKind::Mandelbrot, (-0.743643, 0.131825), 1e4),
+            (FractalKind::Julia { cx: -1.0, cy: 0.1 }, (0.0, 0.0), 2.0),
+        ];
+        for (kind, (x, y), zoom) in views {
+            let frame = |perturbation: Perturbation| {
+                let mut frame = MandelbrotFrame::new(80, 60);
+                frame.max_iterations = 1000;
+                frame.kind = kind;
+                frame.perturbation = perturbation;
+                frame.set_view(x, y, zoom);
+                let iterations = frame.calculate();
+                (iterations, frame.reference_info().unwrap())
+            };
+            let ((direct, _), (perturbed, reference)) = (frame(Perturbation::Off), frame(Perturbation::On));
+
+            // The orbit lasts, so pixels are perturbed rather than iterated directly
+            assert!(reference.orbit_length > 500, "{:?}: orbit of {}", kind, reference.orbit_length);
+            let differing = direct.iter().zip(&perturbed).filter(|(a, b)| (*a - *b).abs() > 0.01).count();
+            assert!(differing < direct.len() / 100, "{:?}: {} of {} pixels differ", kind, differing, direct.len());
+        }
+    }
+
+    #[test]
+    fn auto_perturbation_perturbs_where_the_series_pays_off() {
+        let frame = |perturbation: Perturbation, (x, y): (f64, f64), zoom: f64| {
+            let mut frame = MandelbrotFrame::new(80, 60);
+            frame.max_iterations = 2000;
+            frame.perturbation = perturbation;
+            frame.set_view(x, y, zoom);
+            let iterations = frame.calculate();
+            (iterations, frame)
+        };
+
+        // The whole set is rendered directly, exactly as without perturbation
+        let (direct, _) = frame(Perturbation::Off, (-0.75, 0.0), 1.0);
+        let (auto, shallow) = frame(Perturbation::Auto, (-0.75, 0.0), 1.0);
+        assert!(!shallow.perturbs());
+        assert_eq!(auto, direct);
+
+        // Deep in a bulb's filaments the series skips most of the orbit, and
+        // the pixels still match direct iteration
+        let deep = (-1.25066, 0.02012);
+        let (direct, _) = frame(Perturbation::Off, deep, 1e10);
+        let (auto, perturbed) = frame(Perturbation::Auto, deep, 1e10);
+        assert!(perturbed.perturbs());
+        assert!(perturbed.series.is_some_and(|series| series.skip * 2 >= perturbed.reference_orbit.len()));
+        let differing = direct.iter().zip(&auto).filter(|(a, b)| (*a - *b).abs() > 0.01).count();
+        assert!(differing < direct.len() / 100, "{} of {} pixels differ", differing, direct.len());
+    }
+
+    #[test]
+    fn perturbed_high_precision_matches_bigfloat_iteration() {
+        // At the Misiurewicz point i, whose neighborhood escapes within a few
+        // dozen iterations at any depth, and where f64 has no digits left to
+        // tell the pixels apart
+        let mut frame = MandelbrotFrame::new(24, 18);
+        frame.max_iterations = 500;
+        frame.set_view(0.0, 1.0, 1e16);
+        assert!(frame.uses_high_precision() && frame.perturbs_high_precision());
+        let perturbed = frame.calculate();
+        assert!(frame.reference_info().unwrap().high_precision);
+
+        // Letterbox bars are left out
+        let center = frame.high_precision_center();
+        let pairs: Vec<(f32, f32)> = (0..frame.width * frame.height)
+            .filter_map(|i| {
+                let offset = frame.pixel_offset(i % frame.width, i / frame.width)?;
+                let direct = frame.iterate_high_precision(&center.add(&HighPrecComplex::new(offset.0, offset.1)));
+                Some((direct, perturbed[i as usize]))
+            })
+            .collect();
+        assert!(pairs.iter().any(|&(direct, _)| direct < frame.max_iterations as f32));
+        let differing = pairs.iter().filter(|(direct, perturbed)| (direct - perturbed).abs() > 0.01).count();
+        assert!(differing <= pairs.len() / 100, "{} of {} pixels differ", differing, pairs.len());
+    }
+
+    #[test]
+    fn perturbed_deep_frames_stay_within_the_precision_budget() {
+        // A viewer-sized frame at 1e16 only iterates its reference in BigFloat
+        let mut frame = MandelbrotFrame::new(800, 600);
+        frame.max_iterations = iterations_for_zoom(100, 1e16);
+        frame.set_view(0.0, 1.0, 1e16);
+        assert!(frame.uses_high_precision() && frame.perturbs_high_precision());
+        assert_eq!(frame.precision_budget_step(), 1);
+
+        // Every pixel of a burning ship frame is iterated in BigFloat
+        frame.formula = Formula::BurningShip;
+        assert!(frame.precision_budget_step() > 1);
+    }
+
+    #[test]
+    fn glitch_passes_rerender_glitched_pixels() {
+        let mut frame = MandelbrotFrame::new(48, 36);
+        frame.max_iterations = 3000;
+        frame.perturbation = Perturbation::On;
+        frame.set_view(-0.743643887037151, 0.131825904205330, 1e8);
+        let (x, y) = frame.choose_reference_point();
+        frame.reference_point = Complex::new(x, y);
+        frame.calculate_reference_orbit();
+
+        // Against the view's reference some pixels come within the glitch
+        // tolerance of zero
+        let width = frame.width;
+        let mut samples: Vec<Option<f32>> = (0..width * frame.height).map(|i| frame.perturbed_pixel(i % width, i / width)).collect();
+        let glitched: Vec<u32> = (0..width * frame.height).filter(|&i| samples[i as usize].is_none()).collect();
+        assert!(!glitched.is_empty());
+
+        // Each pass renders them again against a reference placed at one, until
+        // none are left for direct iteration
+        frame.max_glitch_passes = 0;
+        frame.clone().correct_glitches(&mut samples);
+        assert!(samples.iter().any(Option::is_none));
+        frame.max_glitch_passes = DEFAULT_GLITCH_PASSES;
+        frame.correct_glitches(&mut samples);
+        assert!(samples.iter().all(Option::is_some));
+
+        // Corrected pixels are as close to BigFloat iteration as direct f64
+        // iteration comes, which at this depth misses a few boundary pixels too
+        let (mut corrected, mut direct) = (0, 0);
+        for &i in &glitched {
+            let (re, im) = frame.pixel_to_complex(i % width, i / width);
+            let exact = frame.iterate_high_precision(&HighPrecComplex::new(re, im));
+            corrected += ((samples[i as usize].unwrap() - exact).abs() > 0.01) as usize;
+            direct += ((frame.iterate_direct(Complex::new(re, im)) as f32 - exact).abs() > 0.01) as usize;
+        }
+        assert!(corrected <= direct + 1, "{} corrected and {} directly iterated pixels of {} are wrong", corrected, direct, glitched.len());
+    }
+
+    #[test]
+    fn glitch_passes_keep_the_strategys_reference() {
+        let mut frame = MandelbrotFrame::new(48, 36);
+        frame.max_iterations = 3000;
+        frame.perturbation = Perturbation::On;
+        frame.reference_strategy = ReferenceStrategy::Auto;
+        frame.set_view(-0.743643887037151, 0.131825904205330, 1e8);
+        frame.calculate();
+
+        let info = frame.reference_info().unwrap();
+        assert_eq!(info.point, frame.choose_reference_point());
+        assert!(info.glitch_references > 0);
+        assert_eq!((frame.reference_point.real, frame.reference_point.imag), info.point);
+    }
+
+    #[test]
+    fn reference_choice_ignores_the_previous_views_orbit() {
+        let frame = |strategy: ReferenceStrategy, center: (f64, f64)| {
+            let mut frame = MandelbrotFrame::new(48, 36);
+            frame.max_iterations = 3000;
+            frame.perturbation = Perturbation::On;
+            frame.reference_strategy = strategy;
+            frame.set_view(center.0, center.1, 1e8);
+            frame
+        };
+        // About a view's width apart
+        let (from, to) = ((-0.743643887037151, 0.131825904205330), (-0.743643857037151, 0.131825889205330));
+        for strategy in [ReferenceStrategy::MaxDetail, ReferenceStrategy::Auto] {
+            // The first view's orbit and series are kept until the next view's are calculated
+            let mut panned = frame(strategy, from);
+            panned.calculate();
+            panned.set_view(to.0, to.1, 1e8);
+            assert_eq!(panned.choose_reference_point(), frame(strategy, to).choose_reference_point(), "{:?}", strategy);
+        }
+    }
+
+    #[test]
+    fn distance_estimate_shrinks_toward_the_boundary() {
+        let mut frame = MandelbrotFrame::new(1, 1);
+        frame.max_iterations = 10_000;
+        frame.distance_estimation = true;
+        let estimate = |point: Complex| frame.distance_estimate(point, &frame.iterate_standard(point));
+
+        // Out from the tip at -2 the set is a segment, so the true distance is the gap
+        let mut last = f64::INFINITY;
+        for gap in [0.5, 0.1, 0.01, 1e-3, 1e-4, 1e-5] {
+            let distance = estimate(Complex::new(-2.0 - gap, 0.0));
+            assert!(distance < last, "{} at {} after {}", distance, gap, last);
+            assert!((0.25..4.0).contains(&(distance / gap)), "{} at {}", distance, gap);
+            last = distance;
+        }
+
+        // Approaching the cusp at 0.25 it underestimates, but still shrinks
+        let mut last = f64::INFINITY;
+        for gap in [0.5, 0.1, 0.01, 1e-3] {
+            let distance = estimate(Complex::new(0.25 + gap, 0.0));
+            assert!(distance > 0.0 && distance < last, "{} at {} after {}", distance, gap, last);
+            last = distance;
+        }
+        assert_eq!(estimate(Complex::new(-0.5, 0.0)), 0.0);
+    }
+
+    #[test]
+    fn distance_estimation_keeps_the_escape_values() {
+        let mut frame = MandelbrotFrame::new(64, 48);
+        frame.max_iterations = 300;
+        frame.set_view(-0.75, 0.1, 4.0);
+        let plain = frame.calculate();
+        assert!(frame.take_distance_estimates().is_none());
+
+        frame.distance_estimation = true;
+        assert_eq!(frame.calculate(), plain);
+        let distances = frame.take_distance_estimates().unwrap();
+        assert!(frame.take_distance_estimates().is_none());
+        for (&value, &distance) in plain.iter().zip(&distances) {
+            assert_eq!(distance == 0.0, value >= frame.max_iterations as f32, "{} at {}", distance, value);
+        }
+
+        // The folded formulas have no complex derivative
+        frame.formula = Formula::BurningShip;
+        let folded = frame.calculate();
+        let distances = frame.take_distance_estimates().unwrap();
+        assert!(folded.iter().zip(&distances).all(|(&value, distance)| value >= frame.max_iterations as f32 || distance.is_infinite()));
+    }
+
+    #[test]
+    fn each_calculation_replaces_the_distance_estimates() {
+        let mut frame = MandelbrotFrame::new(64, 48);
+        frame.max_iterations = 300;
+        frame.distance_estimation = true;
+        frame.set_view(-0.75, 0.1, 4.0);
+        frame.calculate();
+        let wide = frame.take_distance_estimates().unwrap();
+        frame.calculate();
+        frame.set_view(-0.75, 0.1, 40.0);
+        frame.calculate();
+        let close = frame.take_distance_estimates().unwrap();
+        assert_ne!(wide, close);
+
+        // A high precision view has none, rather than the last view's
+        frame.calculate();
+        frame.set_view(-0.75, 0.1, 1e15);
+        frame.calculate();
+        assert!(frame.take_distance_estimates().is_none());
+    }
+
+    #[test]
+    fn distance_estimation_fills_in_the_known_interior() {
+        let mut frame = MandelbrotFrame::new(32, 24);
+        frame.max_iterations = 300;
+        frame.periodicity = Some(DEFAULT_PERIODICITY);
+        frame.distance_estimation = true;
+        frame.set_view(-0.75, 0.1, 4.0);
+
+        // A mask claiming the whole view is interior, so the mask alone fills it
+        let mut view = MandelbrotFrame::new(frame.width, frame.height);
+        (view.x_min, view.x_max, view.y_min, view.y_max) = (frame.x_min, frame.x_max, frame.y_min, frame.y_max);
+        frame.known_interior = Some(InteriorMask { view: Box::new(view), confirmed: vec![true; 32 * 24] });
+        let iterations = frame.calculate();
+        let distances = frame.take_distance_estimates().unwrap();
+        for y in 2..22 {
+            for x in 2..30 {
+                let i = y * 32 + x;
+                assert_eq!((iterations[i], distances[i]), (300.0, 0.0), "pixel {},{}", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn iterations_for_zoom_saturates() {
+        assert_eq!(iterations_for_zoom(100, 1e16), 3300);
+        assert_eq!(iterations_for_zoom(u32::MAX / 2, 1e16), u32::MAX);
+        assert_eq!(iterations_for_zoom(u32::MAX, 1e300), u32::MAX);
+    }
+}
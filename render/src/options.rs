@@ -0,0 +1,651 @@
+use crate::animation_handler::DEFAULT_MAX_FRAMES;
+use crate::color_handler::{AngleStyle, ColorScheme, GradientStop, Palette};
+use crate::curves::{ColorCurves, CURVE_PRESETS};
+use crate::error::{Result, RustybrotError};
+use crate::frame_handler::ColoringMode;
+use crate::mandelbrot::{AntiAliasing, AspectPolicy, Formula, FractalKind, Periodicity, Perturbation};
+use crate::mandelbrot::{ReferenceStrategy, SampleOrigin, SamplePattern, StandardFloat};
+use crate::mandelbrot::{DEFAULT_GLITCH_PASSES, DEFAULT_PERIODICITY};
+use crate::pixel_format::AlphaMode;
+use crate::project::{load_project, Project};
+use crate::quality::QualityProfile;
+use crate::tile_handler::{DEFAULT_TILE_CACHE, MAX_PYRAMID_LEVELS, TILE_SIZE};
+use crate::viewer_handler::DEFAULT_FPS_CAP;
+
+// Largest idle change of the view, in pixels, that is snapped back to the
+// view on screen rather than rendered
+const DEFAULT_VIEW_DEADZONE: f64 = 0.1;
+
+// Command line options. Most only apply when rendering without opening the viewer.
+#[derive(Clone)]
+pub struct Options {
+    pub headless: bool,
+    pub fps_cap: Option<u32>,  // Viewer update rate limit, None for uncapped
+    pub deadzone: f64,  // Idle view changes below this many pixels are snapped back
+    pub preview_iterations: Option<u32>,  // Iteration cap of frames rendered while the view moves
+    pub orbit_trail: Option<f32>,  // Per-frame decay of the orbit trail drawn over animations
+    pub max_frames: Option<u32>,  // Longest animation the viewer will create, None for no limit
+    pub quality: QualityProfile,
+    pub output: String,
+    pub center: (f64, f64),
+    pub zoom: f64,
+    pub zoom_sequence: Option<f64>,  // Final zoom of a series of stills a factor of ten apart
+    pub tiles: Option<String>,  // Output directory for a tile pyramid instead of a still
+    pub levels: u32,
+    pub tile_cache: usize,  // Tiles whose iterations are kept for re-rendering
+    pub tile_size: u32,  // Side of a pyramid or tiled image tile in pixels
+    pub image_size: Option<(u32, u32)>,  // Size of a still rendered tile by tile straight into the output file
+    pub camera_path: Option<String>,  // CSV the viewer's animations write their per-frame camera to
+    pub animation_output: String,  // GIF, or APNG if it ends in .png or .apng, the viewer's animations are written to
+    pub aspect_policy: AspectPolicy,
+    pub smoothing: f32,
+    pub sequential: bool,
+    pub full_quality: bool,  // Render high precision frames at full resolution however long they take
+    pub sixteen_bit: bool,  // Export with 16 bits per channel
+    pub escape_smoothing: f32,
+    pub iterations: Option<u32>,  // Overrides the quality profile's iteration count
+    pub anti_aliasing: Option<AntiAliasing>,  // Overrides the quality profile's anti-aliasing
+    pub disable_high_precision: Option<bool>,  // Overrides whether the quality profile allows high precision
+    pub kind: FractalKind,
+    pub formula: Formula,
+    pub power: u32,  // Exponent of the multibrot z^power + c
+    pub scheme: ColorScheme,
+    pub buddhabrot: Option<u32>,  // Sample count of a Buddhabrot density render instead of escape time
+    pub iim: Option<u32>,  // Walk count of an inverse iteration Julia set density render instead of escape time
+    pub sample_origin: SampleOrigin,
+    pub standard_float: StandardFloat,
+    pub reference_strategy: ReferenceStrategy,  // Where the reference orbit is placed
+    pub perturbation: Perturbation,  // When to iterate pixels as offsets from the reference orbit
+    pub glitch_passes: u32,  // New reference orbits tried for pixels whose perturbation glitched
+    pub aa_boundary: Option<u32>,  // Supersample only pixels this close to the set's edge
+    pub escape_angle: Option<AngleStyle>,  // Color by the external angle instead of the scheme
+    pub rotation: f64,  // Degrees
+    pub transparent: bool,  // Export in-set points with alpha 0
+    pub alpha: AlphaMode,  // Alpha convention of exported translucent pixels
+    pub capped_lookahead: bool,  // Color points escaping just past the iteration cap as the set's edge
+    pub diff: Option<String>,  // Options rendered against these ones, the difference being written instead
+    pub stress: bool,  // Run the high precision stress cases instead of rendering
+    pub cycle: Option<f32>,  // Iterations per palette repeat
+    pub color_palette: Palette,
+    pub palette: Option<String>,  // Gradient file of `position r g b` stops replacing the palette
+    pub gradient: Option<Vec<GradientStop>>,  // Stops a project file carries, replacing the palette like a gradient file
+    pub curves: Option<ColorCurves>,  // Tone curves grading the finished colors
+    pub coloring_mode: ColoringMode,
+    pub initial_z: (f64, f64),  // Critical point the Mandelbrot iteration starts from
+    pub periodicity: Option<Periodicity>,
+    pub log_polar: Option<f64>,  // Decades of zoom squeezed into the still, see MandelbrotFrame::log_polar
+    pub thumbnail: Option<String>,  // Write a quick low resolution preview here before the still
+    pub save_buffer: Option<String>,  // Also save the still's iteration buffer here, for recoloring
+    pub exr: Option<String>,  // Also write the still's raw escape values, angles and distance estimates here as OpenEXR
+    pub recolor: Option<String>,  // Saved iteration buffer to color with each of the colorings
+    pub colorings: Vec<(ColorScheme, Option<f32>)>,  // Scheme and cycle length of each recolor
+}
+
+impl Options {
+    // Command line reproducing a still of the options' view, readable by parse_options
+    pub fn to_args(&self) -> String {
+        let mut args = format!("--center {},{} --zoom {}", self.center.0, self.center.1, self.zoom);
+        if let FractalKind::Julia { cx, cy } = self.kind {
+            args.push_str(&format!(" --julia {},{}", cx, cy));
+        }
+        if self.formula != Formula::Mandelbrot {
+            args.push_str(&format!(" --formula {}", format!("{:?}", self.formula).to_lowercase()));
+        }
+        if self.power != 2 {
+            args.push_str(&format!(" --power {}", self.power));
+        }
+        if let Some(periodicity) = self.periodicity {
+            args.push_str(&format!(" --periodicity {},{}", periodicity.epsilon, periodicity.check_period));
+        }
+        if let Some(decades) = self.log_polar {
+            args.push_str(&format!(" --log-polar {}", decades));
+        }
+        if self.initial_z != (0.0, 0.0) {
+            args.push_str(&format!(" --initial-z {},{}", self.initial_z.0, self.initial_z.1));
+        }
+        if let Some(iterations) = self.iterations {
+            args.push_str(&format!(" --iterations {}", iterations));
+        }
+        if let Some(anti_aliasing) = self.anti_aliasing {
+            args.push_str(&format!(" --aa {},{}", anti_aliasing.factor, format!("{:?}", anti_aliasing.pattern).to_lowercase()));
+        }
+        if let Some(radius) = self.aa_boundary {
+            args.push_str(&format!(" --aa-boundary {}", radius));
+        }
+        if let Some(disable_high_precision) = self.disable_high_precision {
+            args.push_str(&format!(" --precision {}", if disable_high_precision { "f64" } else { "auto" }));
+        }
+        if self.transparent {
+            args.push_str(" --transparent");
+        }
+        if self.alpha != AlphaMode::Straight {
+            args.push_str(&format!(" --alpha {}", format!("{:?}", self.alpha).to_lowercase()));
+        }
+        if self.rotation != 0.0 {
+            args.push_str(&format!(" --rotation {}", self.rotation));
+        }
+        if self.sample_origin != SampleOrigin::Center {
+            args.push_str(&format!(" --sample-origin {}", format!("{:?}", self.sample_origin).to_lowercase()));
+        }
+        if self.capped_lookahead {
+            args.push_str(" --capped-lookahead");
+        }
+        if let Some(length) = self.cycle {
+            args.push_str(&format!(" --cycle {}", length));
+        }
+        if self.color_palette != Palette::Rainbow {
+            args.push_str(&format!(" --palette {}", format!("{:?}", self.color_palette).to_lowercase()));
+        }
+        if let Some(path) = &self.palette {
+            args.push_str(&format!(" --palette {}", path));
+        }
+        if self.standard_float != StandardFloat::F64 {
+            args.push_str(&format!(" --float {}", format!("{:?}", self.standard_float).to_lowercase()));
+        }
+        if self.reference_strategy != ReferenceStrategy::Center {
+            args.push_str(&format!(" --reference {}", format!("{:?}", self.reference_strategy).to_lowercase()));
+        }
+        if self.perturbation != Perturbation::Auto {
+            args.push_str(&format!(" --perturbation {}", format!("{:?}", self.perturbation).to_lowercase()));
+        }
+        if self.glitch_passes != DEFAULT_GLITCH_PASSES {
+            args.push_str(&format!(" --glitch-passes {}", self.glitch_passes));
+        }
+        if self.coloring_mode != ColoringMode::Linear {
+            args.push_str(&format!(" --coloring {}", format!("{:?}", self.coloring_mode).to_lowercase()));
+        }
+        if let Some(curves) = &self.curves {
+            args.push_str(&format!(" --curves {}", curves.to_spec()));
+        }
+        if let Some(style) = self.escape_angle {
+            args.push_str(&format!(" --escape-angle {}", format!("{:?}", style).to_lowercase()));
+        }
+        if let Some(samples) = self.buddhabrot {
+            args.push_str(&format!(" --buddhabrot {}", samples));
+        }
+        if let Some(walks) = self.iim {
+            args.push_str(&format!(" --iim {}", walks));
+        }
+        args.push_str(&format!(" --scheme {} --aspect {} --escape-smoothing {} --smoothing {} --output {}",
+                               format!("{:?}", self.scheme).to_lowercase(), format!("{:?}", self.aspect_policy).to_lowercase(),
+                               self.escape_smoothing, self.smoothing, self.output));
+        args
+    }
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            headless: false,
+            fps_cap: DEFAULT_FPS_CAP,
+            deadzone: DEFAULT_VIEW_DEADZONE,
+            preview_iterations: None,
+            orbit_trail: None,
+            max_frames: Some(DEFAULT_MAX_FRAMES),
+            quality: QualityProfile::Interactive,
+            output: "render.png".to_string(),
+            center: (-0.5, 0.0),
+            zoom: 1.0,
+            zoom_sequence: None,
+            tiles: None,
+            levels: 4,
+            tile_cache: DEFAULT_TILE_CACHE,
+            tile_size: TILE_SIZE,
+            image_size: None,
+            camera_path: None,
+            animation_output: "animation.gif".to_string(),
+            aspect_policy: AspectPolicy::Fit,
+            smoothing: 0.0,
+            sequential: false,
+            full_quality: false,
+            sixteen_bit: false,
+            escape_smoothing: 1.0,
+            iterations: None,
+            anti_aliasing: None,
+            disable_high_precision: None,
+            kind: FractalKind::Mandelbrot,
+            formula: Formula::Mandelbrot,
+            power: 2,
+            scheme: ColorScheme::Hsv,
+            buddhabrot: None,
+            iim: None,
+            sample_origin: SampleOrigin::Center,
+            standard_float: StandardFloat::F64,
+            reference_strategy: ReferenceStrategy::Center,
+            perturbation: Perturbation::Auto,
+            glitch_passes: DEFAULT_GLITCH_PASSES,
+            aa_boundary: None,
+            escape_angle: None,
+            rotation: 0.0,
+            transparent: false,
+            alpha: AlphaMode::Straight,
+            capped_lookahead: false,
+            diff: None,
+            stress: false,
+            cycle: None,
+            color_palette: Palette::Rainbow,
+            palette: None,
+            gradient: None,
+            curves: None,
+            coloring_mode: ColoringMode::Linear,
+            initial_z: (0.0, 0.0),
+            periodicity: None,
+            log_polar: None,
+            thumbnail: None,
+            save_buffer: None,
+            exr: None,
+            recolor: None,
+            colorings: vec![(ColorScheme::Hsv, None), (ColorScheme::Heatmap, None)],
+        }
+    }
+}
+
+// Headless rendering is selected by passing --quality, --tiles, --image-size, --buddhabrot, --iim, --escape-angle,
+// --zoom-sequence, --diff, --save-buffer, --recolor or --output
+pub fn parse_options(args: &[String]) -> Result<Options> {
+    let invalid = RustybrotError::Config;
+    let mut options = Options::default();
+
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        if options.set_switch(flag) {
+            continue;
+        }
+        let value = args.next().ok_or_else(|| invalid(format!("Missing value for {}", flag)))?;
+        let known = options.set_viewer_option(flag, value)?
+            || options.set_output_option(flag, value)?
+            || options.set_view_option(flag, value)?
+            || options.set_color_option(flag, value)?;
+        if !known {
+            return Err(invalid(format!("Unknown option {}", flag)));
+        }
+    }
+
+    // The Buddhabrot traces the orbits of the Mandelbrot set's parameters
+    if options.buddhabrot.is_some() && matches!(options.kind, FractalKind::Julia { .. }) {
+        return Err(invalid("--buddhabrot can't be combined with --julia".to_string()));
+    }
+
+    Ok(options)
+}
+
+// Each setter below handles one group of options, returning false for flags
+// outside its group
+impl Options {
+    // Switches take no value
+    fn set_switch(&mut self, flag: &str) -> bool {
+        match flag {
+            "--sequential" => self.sequential = true,
+            "--full-quality" => self.full_quality = true,
+            "--transparent" => self.transparent = true,
+            "--stress-high-precision" => self.stress = true,
+            "--capped-lookahead" => self.capped_lookahead = true,
+            _ => return false,
+        }
+        true
+    }
+
+    // How the viewer runs and records animations
+    fn set_viewer_option(&mut self, flag: &str, value: &str) -> Result<bool> {
+        let invalid = RustybrotError::Config;
+        match flag {
+            "--fps" => {
+                self.fps_cap = match value {
+                    "uncapped" => None,
+                    _ => Some(value.parse().ok().filter(|&fps| fps > 0).ok_or_else(|| {
+                        invalid(format!("Invalid FPS cap {:?}, expected a positive number or uncapped", value))
+                    })?),
+                };
+            }
+            "--deadzone" => {
+                self.deadzone = value.parse().ok().filter(|&pixels: &f64| pixels >= 0.0).ok_or_else(|| {
+                    invalid(format!("Invalid deadzone {:?}, expected a distance in pixels", value))
+                })?;
+            }
+            "--max-frames" => {
+                self.max_frames = match value {
+                    "off" => None,
+                    _ => Some(value.parse().ok().filter(|&frames| frames > 0).ok_or_else(|| {
+                        invalid(format!("Invalid frame cap {:?}, expected a positive number or off", value))
+                    })?),
+                };
+            }
+            "--orbit-trail" => {
+                self.orbit_trail = Some(value.parse().ok().filter(|decay: &f32| (0.0..=1.0).contains(decay)).ok_or_else(|| {
+                    invalid(format!("Invalid orbit trail decay {:?}, expected a fraction from 0 to 1", value))
+                })?);
+            }
+            "--preview-iterations" => {
+                self.preview_iterations = match value {
+                    "off" => None,
+                    _ => Some(value.parse().ok().filter(|&cap| cap > 0).ok_or_else(|| {
+                        invalid(format!("Invalid preview iteration cap {:?}, expected a positive number or off", value))
+                    })?),
+                };
+            }
+            "--camera-path" => {
+                self.camera_path = Some(value.to_string());
+            }
+            "--animation-output" => {
+                self.animation_output = value.to_string();
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    // What a headless render produces and where it is written
+    fn set_output_option(&mut self, flag: &str, value: &str) -> Result<bool> {
+        let invalid = RustybrotError::Config;
+        match flag {
+            "--quality" => {
+                self.quality = QualityProfile::from_name(value).ok_or_else(|| {
+                    invalid(format!("Unknown quality profile {:?}, expected draft, interactive, high or print", value))
+                })?;
+                self.headless = true;
+            }
+            "--tiles" => {
+                self.tiles = Some(value.to_string());
+                self.headless = true;
+            }
+            "--levels" => {
+                self.levels = value.parse().ok().filter(|&levels| levels <= MAX_PYRAMID_LEVELS).ok_or_else(|| {
+                    invalid(format!("Invalid level count {:?}, expected at most {}", value, MAX_PYRAMID_LEVELS))
+                })?;
+            }
+            "--tile-size" => {
+                self.tile_size = value.parse().ok().filter(|&size| size > 0).ok_or_else(|| {
+                    invalid(format!("Invalid tile size {:?}, expected a positive number of pixels", value))
+                })?;
+            }
+            "--image-size" => {
+                self.image_size = Some(value.split_once('x')
+                    .and_then(|(width, height)| width.parse().ok().zip(height.parse().ok()))
+                    .filter(|&(width, height)| width > 0 && height > 0)
+                    .ok_or_else(|| invalid(format!("Invalid image size {:?}, expected WIDTHxHEIGHT", value)))?);
+                self.headless = true;
+            }
+            "--tile-cache" => {
+                self.tile_cache = value.parse().map_err(|_| invalid(format!("Invalid tile cache size {:?}", value)))?;
+            }
+            "--depth" => {
+                self.sixteen_bit = match value {
+                    "8" => false,
+                    "16" => true,
+                    _ => return Err(invalid(format!("Invalid bit depth {:?}, expected 8 or 16", value))),
+                };
+            }
+            "--alpha" => {
+                self.alpha = AlphaMode::from_name(value)
+                    .ok_or_else(|| invalid(format!("Unknown alpha mode {:?}, expected straight or premultiplied", value)))?;
+            }
+            "--thumbnail" => {
+                self.thumbnail = Some(value.to_string());
+                self.headless = true;
+            }
+            "--save-buffer" => {
+                self.save_buffer = Some(value.to_string());
+                self.headless = true;
+            }
+            "--exr" => {
+                self.exr = Some(value.to_string());
+                self.headless = true;
+            }
+            "--recolor" => {
+                self.recolor = Some(value.to_string());
+                self.headless = true;
+            }
+            "--colorings" => {
+                self.colorings = parse_colorings(value)
+                    .ok_or_else(|| invalid(format!("Invalid colorings {:?}, expected a list such as hsv,heatmap,hsv/32", value)))?;
+            }
+            "--diff" => {
+                self.diff = Some(value.to_string());
+                self.headless = true;
+            }
+            "--buddhabrot" => {
+                self.buddhabrot = Some(value.parse().map_err(|_| invalid(format!("Invalid Buddhabrot sample count {:?}", value)))?);
+                self.headless = true;
+            }
+            "--iim" => {
+                self.iim = Some(value.parse().map_err(|_| invalid(format!("Invalid inverse iteration walk count {:?}", value)))?);
+                self.headless = true;
+            }
+            "--output" => {
+                self.output = value.to_string();
+                self.headless = true;
+            }
+            "--zoom-sequence" => {
+                self.zoom_sequence = Some(value.parse().ok().filter(|&zoom: &f64| zoom.is_finite() && zoom > 0.0).ok_or_else(|| {
+                    invalid(format!("Invalid zoom sequence end {:?}, expected a positive zoom", value))
+                })?);
+                self.headless = true;
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    // Where the view looks and how its escape values are calculated
+    fn set_view_option(&mut self, flag: &str, value: &str) -> Result<bool> {
+        let invalid = RustybrotError::Config;
+        match flag {
+            "--project" => {
+                self.set_project(load_project(value)?);
+            }
+            "--center" => {
+                self.center = parse_pair(value).ok_or_else(|| invalid(format!("Invalid center {:?}, expected re,im", value)))?;
+            }
+            "--zoom" => {
+                self.zoom = value.parse().map_err(|_| invalid(format!("Invalid zoom {:?}", value)))?;
+            }
+            "--aspect" => {
+                self.aspect_policy = AspectPolicy::from_name(value).ok_or_else(|| {
+                    invalid(format!("Unknown aspect policy {:?}, expected stretch, fit or fill", value))
+                })?;
+            }
+            "--iterations" => {
+                self.iterations = Some(value.parse().map_err(|_| invalid(format!("Invalid iteration count {:?}", value)))?);
+            }
+            "--aa" => {
+                let (factor, pattern) = value.split_once(',').unwrap_or((value, "rotatedgrid"));
+                let anti_aliasing = factor.parse().ok()
+                    .zip(SamplePattern::from_name(pattern))
+                    .map(|(factor, pattern)| AntiAliasing { factor, pattern, boundary_radius: None })
+                    .ok_or_else(|| invalid(format!("Invalid anti-aliasing {:?}, expected factor[,grid|rotatedgrid|halton]", value)))?;
+                self.anti_aliasing = Some(anti_aliasing);
+            }
+            "--aa-boundary" => {
+                self.aa_boundary = Some(value.parse().map_err(|_| invalid(format!("Invalid boundary radius {:?}, expected pixels", value)))?);
+            }
+            "--precision" => {
+                self.disable_high_precision = match value {
+                    "auto" => Some(false),
+                    "f64" => Some(true),
+                    _ => return Err(invalid(format!("Invalid precision {:?}, expected auto or f64", value))),
+                };
+            }
+            "--julia" => {
+                let (cx, cy) = parse_pair(value).ok_or_else(|| invalid(format!("Invalid Julia constant {:?}, expected re,im", value)))?;
+                self.kind = FractalKind::Julia { cx, cy };
+            }
+            "--formula" => {
+                self.formula = Formula::from_name(value)
+                    .ok_or_else(|| invalid(format!("Unknown formula {:?}, expected mandelbrot, burningship or tricorn", value)))?;
+            }
+            "--power" => {
+                self.power = value.parse().ok().filter(|&power| power >= 2)
+                    .ok_or_else(|| invalid(format!("Invalid power {:?}, expected a whole number of at least 2", value)))?;
+            }
+            "--initial-z" => {
+                self.initial_z = parse_pair(value).ok_or_else(|| invalid(format!("Invalid initial z {:?}, expected re,im", value)))?;
+            }
+            "--log-polar" => {
+                self.log_polar = Some(value.parse().ok().filter(|&decades: &f64| decades > 0.0 && decades.is_finite())
+                    .ok_or_else(|| invalid(format!("Invalid log-polar depth {:?}, expected decades above 0", value)))?);
+            }
+            "--periodicity" => {
+                self.periodicity = match value {
+                    "off" => None,
+                    "on" => Some(DEFAULT_PERIODICITY),
+                    _ => Some(parse_periodicity(value).ok_or_else(|| {
+                        invalid(format!("Invalid periodicity {:?}, expected on, off or epsilon[,check period]", value))
+                    })?),
+                };
+            }
+            "--rotation" => {
+                self.rotation = value.parse().map_err(|_| invalid(format!("Invalid rotation {:?}, expected degrees", value)))?;
+            }
+            "--sample-origin" => {
+                self.sample_origin = SampleOrigin::from_name(value)
+                    .ok_or_else(|| invalid(format!("Unknown sample origin {:?}, expected center or corner", value)))?;
+            }
+            "--float" => {
+                self.standard_float = StandardFloat::from_name(value)
+                    .ok_or_else(|| invalid(format!("Unknown float type {:?}, expected f32 or f64", value)))?;
+            }
+            "--reference" => {
+                self.reference_strategy = ReferenceStrategy::from_name(value)
+                    .ok_or_else(|| invalid(format!("Unknown reference strategy {:?}, expected center, maxdetail or auto", value)))?;
+            }
+            "--perturbation" => {
+                self.perturbation = Perturbation::from_name(value)
+                    .ok_or_else(|| invalid(format!("Unknown perturbation {:?}, expected auto, on or off", value)))?;
+            }
+            "--glitch-passes" => {
+                self.glitch_passes = value.parse().map_err(|_| invalid(format!("Invalid glitch pass count {:?}", value)))?;
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    // How escape values are turned into colors
+    fn set_color_option(&mut self, flag: &str, value: &str) -> Result<bool> {
+        let invalid = RustybrotError::Config;
+        match flag {
+            "--scheme" => {
+                self.scheme = ColorScheme::from_name(value)
+                    .ok_or_else(|| invalid(format!("Unknown color scheme {:?}, expected hsv or heatmap", value)))?;
+            }
+            "--palette" => {
+                // A palette's name, or else a gradient file
+                match Palette::from_name(value) {
+                    Some(palette) => self.color_palette = palette,
+                    None => self.palette = Some(value.to_string()),
+                }
+            }
+            "--cycle" => {
+                self.cycle = Some(value.parse().ok().filter(|&length: &f32| length.is_finite() && length > 0.0).ok_or_else(|| {
+                    invalid(format!("Invalid cycle length {:?}, expected a positive number of iterations", value))
+                })?);
+            }
+            "--coloring" => {
+                self.coloring_mode = ColoringMode::from_name(value)
+                    .ok_or_else(|| invalid(format!("Unknown coloring mode {:?}, expected linear, histogram or distanceestimate", value)))?;
+            }
+            "--curves" => {
+                self.curves = Some(ColorCurves::parse(value).ok_or_else(|| {
+                    invalid(format!("Invalid curves {:?}, expected {} or channel=x:y,x:y,... for r, g, b or rgb, separated by ;", value, CURVE_PRESETS.join(", ")))
+                })?);
+            }
+            "--smoothing" => {
+                self.smoothing = value.parse().map_err(|_| invalid(format!("Invalid smoothing strength {:?}", value)))?;
+            }
+            "--escape-smoothing" => {
+                self.escape_smoothing = value.parse().map_err(|_| invalid(format!("Invalid escape smoothing {:?}", value)))?;
+            }
+            "--escape-angle" => {
+                self.escape_angle = Some(AngleStyle::from_name(value)
+                    .ok_or_else(|| invalid(format!("Unknown escape angle style {:?}, expected hue or shaded", value)))?);
+                self.headless = true;
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    // A project file's view and coloring, which later options can override
+    fn set_project(&mut self, project: Project) {
+        self.center = project.view.center;
+        self.zoom = project.view.zoom;
+        self.iterations = Some(project.view.iterations);
+        self.kind = project.view.kind;
+        self.formula = project.formula;
+        self.power = project.power;
+        self.scheme = project.scheme;
+        self.escape_smoothing = project.escape_smoothing;
+        self.smoothing = project.iteration_smoothing;
+        self.aspect_policy = project.aspect_policy;
+        self.anti_aliasing = Some(project.anti_aliasing);
+        self.aa_boundary = project.anti_aliasing.boundary_radius;
+        self.disable_high_precision = Some(project.disable_high_precision);
+        self.color_palette = project.palette;
+        self.cycle = project.cycle_length;
+        self.gradient = project.gradient;
+    }
+}
+
+fn parse_pair(value: &str) -> Option<(f64, f64)> {
+    let (re, im) = value.split_once(',')?;
+    Some((re.trim().parse().ok()?, im.trim().parse().ok()?))
+}
+
+// epsilon[,check period], the check period defaulting to DEFAULT_PERIODICITY's
+fn parse_periodicity(value: &str) -> Option<Periodicity> {
+    let (epsilon, check_period) = value.split_once(',').unwrap_or((value, ""));
+    let epsilon = epsilon.trim().parse().ok().filter(|&epsilon: &f64| epsilon > 0.0)?;
+    let check_period = match check_period.trim() {
+        "" => DEFAULT_PERIODICITY.check_period,
+        period => period.parse().ok().filter(|&period| period > 0)?,
+    };
+    Some(Periodicity { epsilon, check_period })
+}
+
+// Comma separated schemes, each optionally followed by /cycle length
+fn parse_colorings(value: &str) -> Option<Vec<(ColorScheme, Option<f32>)>> {
+    value.split(',')
+        .map(|coloring| {
+            let (scheme, cycle) = match coloring.split_once('/') {
+                Some((scheme, cycle)) => (scheme, Some(cycle.trim().parse::<f32>().ok().filter(|length| *length > 0.0)?)),
+                None => (coloring, None),
+            };
+            Some((ColorScheme::from_name(scheme.trim())?, cycle))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buddhabrot_rejects_julia_sets() {
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<String>>();
+        assert!(parse_options(&args(&["--buddhabrot", "100", "--julia", "-0.8,0.156"])).is_err());
+        assert!(parse_options(&args(&["--julia", "-0.8,0.156", "--buddhabrot", "100"])).is_err());
+        assert!(parse_options(&args(&["--buddhabrot", "100"])).is_ok());
+    }
+
+    #[test]
+    fn level_counts_past_the_pyramid_limit_are_rejected() {
+        let levels = |count: &str| parse_options(&["--levels".to_string(), count.to_string()]).map(|options| options.levels);
+        assert_eq!(levels(&MAX_PYRAMID_LEVELS.to_string()).ok(), Some(MAX_PYRAMID_LEVELS));
+        for count in [MAX_PYRAMID_LEVELS + 1, 32, 40] {
+            assert!(levels(&count.to_string()).is_err(), "{} levels accepted", count);
+        }
+    }
+
+    #[test]
+    fn formulas_are_named_in_any_case() {
+        let formula = |name: &str| {
+            let args = ["--formula".to_string(), name.to_string()];
+            parse_options(&args).ok().map(|options| options.formula)
+        };
+        for name in ["burningship", "BurningShip", "burning-ship", "Burning_Ship"] {
+            assert_eq!(formula(name), Some(Formula::BurningShip), "{}", name);
+        }
+        assert_eq!(formula("Tricorn"), Some(Formula::Tricorn));
+        assert_eq!(formula("ship"), None);
+    }
+}
@@ -0,0 +1,67 @@
+// Channel order of the pixel buffers passed between modules. raqote draw
+// targets and minifb windows both hold one 0xAARRGGBB u32 per pixel, so the
+// viewer presents them as is, while the gif and png encoders and the pixel
+// filters work on RGBA bytes.
+//
+// Color channels in draw targets are premultiplied by alpha, which is what
+// raqote blends. ColorHandler builds its straight colors into premultiplied
+// ones, blending passes work on the premultiplied values, and exports convert
+// to the file's convention as the last step, see AlphaMode.
+
+// Alpha convention of exported RGBA pixels. PNG defines straight alpha, so
+// that's the default; premultiplied suits compositors that take it as is.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AlphaMode {
+    Straight,
+    Premultiplied,
+}
+
+impl AlphaMode {
+    pub fn from_name(name: &str) -> Option<AlphaMode> {
+        match name.to_lowercase().as_str() {
+            "straight" => Some(AlphaMode::Straight),
+            "premultiplied" => Some(AlphaMode::Premultiplied),
+            _ => None,
+        }
+    }
+}
+
+// Divides the color channels of a premultiplied RGBA pixel by its alpha,
+// rounding to the nearest straight value. Fully transparent pixels have no
+// color and come out black.
+pub fn unpremultiply(rgba: &[u8]) -> [u8; 4] {
+    let alpha = rgba[3] as u32;
+    let channel = |value: u8| (value as u32 * 255 + alpha / 2).checked_div(alpha).map_or(0, |straight| straight.min(255) as u8);
+    [channel(rgba[0]), channel(rgba[1]), channel(rgba[2]), rgba[3]]
+}
+
+// Unpacks 0xAARRGGBB pixels into R, G, B, A bytes
+pub fn argb_u32_to_rgba8(pixels: &[u32]) -> Vec<u8> {
+    pixels.iter()
+        .flat_map(|&pixel| {
+            let [a, r, g, b] = pixel.to_be_bytes();
+            [r, g, b, a]
+        })
+        .collect()
+}
+
+// Packs R, G, B, A bytes into 0xAARRGGBB pixels, the inverse of argb_u32_to_rgba8
+pub fn rgba8_to_argb_u32(bytes: &[u8]) -> Vec<u32> {
+    bytes.chunks_exact(4)
+        .map(|rgba| u32::from_be_bytes([rgba[3], rgba[0], rgba[1], rgba[2]]))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn argb_and_rgba_conversions_round_trip() {
+        let pixels = [0xff102030, 0x80ff0000, 0x00000000, 0x7f00ff7f, 0xffffffff];
+        let bytes = argb_u32_to_rgba8(&pixels);
+        assert_eq!(bytes[..8], [0x10, 0x20, 0x30, 0xff, 0xff, 0x00, 0x00, 0x80]);
+        assert_eq!(rgba8_to_argb_u32(&bytes), pixels);
+        assert_eq!(argb_u32_to_rgba8(&rgba8_to_argb_u32(&bytes)), bytes);
+    }
+}
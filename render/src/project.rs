@@ -0,0 +1,333 @@
+use std::fs;
+use serde::{Deserialize, Serialize};
+use crate::color_handler::{Blend, ColorScheme, GradientStop, Palette};
+use crate::error::{Result, RustybrotError};
+use crate::key_value;
+use crate::mandelbrot::{AntiAliasing, AspectPolicy, Formula, FractalKind, SamplePattern};
+use crate::view_state::ViewState;
+
+// Bumped whenever a field changes meaning, older files are migrated on load
+pub const PROJECT_VERSION: u32 = 2;
+
+const MAX_AA_FACTOR: u32 = 16;
+
+// Each step multiplies z by itself power - 1 times, so huge powers only stall
+const MAX_POWER: u32 = 64;
+
+// Everything needed to reproduce a render, saved as a .mandel file of
+// `key = value` lines
+#[derive(Clone)]
+pub struct Project {
+    pub view: ViewState,
+    pub formula: Formula,
+    pub power: u32,
+    pub scheme: ColorScheme,
+    pub palette: Palette,
+    pub gradient: Option<Vec<GradientStop>>,  // Stops replacing the palette
+    pub cycle_length: Option<f32>,  // Iterations per palette repeat
+    pub escape_smoothing: f32,
+    pub iteration_smoothing: f32,
+    pub aspect_policy: AspectPolicy,
+    pub anti_aliasing: AntiAliasing,
+    pub disable_high_precision: bool,
+}
+
+impl Default for Project {
+    fn default() -> Self {
+        Project {
+            view: ViewState::default(),
+            formula: Formula::Mandelbrot,
+            power: 2,
+            scheme: ColorScheme::Hsv,
+            palette: Palette::Rainbow,
+            gradient: None,
+            cycle_length: None,
+            escape_smoothing: 1.0,
+            iteration_smoothing: 0.0,
+            aspect_policy: AspectPolicy::Fit,
+            anti_aliasing: AntiAliasing { factor: 1, pattern: SamplePattern::RotatedGrid, boundary_radius: None },
+            disable_high_precision: false,
+        }
+    }
+}
+
+impl Project {
+    // Normalizes a project loaded from outside into one that can be rendered,
+    // the view as ViewState::validate_and_clamp does and the render settings
+    // clamped into range
+    pub fn validate_and_clamp(self) -> Result<Project> {
+        let defaults = Project::default();
+        let unit = |value: f32, default: f32| if value.is_nan() { default } else { value.clamp(0.0, 1.0) };
+
+        Ok(Project {
+            view: self.view.validate_and_clamp()?,
+            power: self.power.clamp(2, MAX_POWER),
+            escape_smoothing: unit(self.escape_smoothing, defaults.escape_smoothing),
+            iteration_smoothing: unit(self.iteration_smoothing, defaults.iteration_smoothing),
+            anti_aliasing: AntiAliasing {
+                factor: self.anti_aliasing.factor.clamp(1, MAX_AA_FACTOR),
+                ..self.anti_aliasing
+            },
+            ..self
+        })
+    }
+}
+
+// The file's layout, one field per key. Gradient stops are written like a
+// gradient file's lines, one `gradient = position r g b blend` line per stop.
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+struct ProjectFile {
+    version: u32,
+    center_re: f64,
+    center_im: f64,
+    zoom: f64,
+    iterations: u32,
+    kind: Kind,
+    julia_re: Option<f64>,
+    julia_im: Option<f64>,
+    formula: Formula,
+    power: u32,
+    scheme: ColorScheme,
+    palette: Palette,
+    gradient: Vec<(f32, f32, f32, f32, Blend)>,
+    cycle: Option<f32>,
+    escape_smoothing: f32,
+    iteration_smoothing: f32,
+    aspect: AspectPolicy,
+    aa_factor: u32,
+    aa_pattern: SamplePattern,
+    aa_boundary: Option<u32>,
+    precision: Precision,
+}
+
+#[derive(Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Kind {
+    Mandelbrot,
+    Julia,
+}
+
+#[derive(Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Precision {
+    Auto,
+    F64,
+}
+
+impl Default for ProjectFile {
+    fn default() -> Self {
+        ProjectFile::from(&Project::default())
+    }
+}
+
+impl From<&Project> for ProjectFile {
+    fn from(project: &Project) -> Self {
+        let view = &project.view;
+        let julia = match view.kind {
+            FractalKind::Mandelbrot => None,
+            FractalKind::Julia { cx, cy } => Some((cx, cy)),
+        };
+        ProjectFile {
+            version: PROJECT_VERSION,
+            center_re: view.center.0,
+            center_im: view.center.1,
+            zoom: view.zoom,
+            iterations: view.iterations,
+            kind: if julia.is_some() { Kind::Julia } else { Kind::Mandelbrot },
+            julia_re: julia.map(|(cx, _)| cx),
+            julia_im: julia.map(|(_, cy)| cy),
+            formula: project.formula,
+            power: project.power,
+            scheme: project.scheme,
+            palette: project.palette,
+            gradient: project.gradient.iter().flatten()
+                .map(|&GradientStop { position, color: [r, g, b], blend }| (position, r * 255.0, g * 255.0, b * 255.0, blend))
+                .collect(),
+            cycle: project.cycle_length,
+            escape_smoothing: project.escape_smoothing,
+            iteration_smoothing: project.iteration_smoothing,
+            aspect: project.aspect_policy,
+            aa_factor: project.anti_aliasing.factor,
+            aa_pattern: project.anti_aliasing.pattern,
+            aa_boundary: project.anti_aliasing.boundary_radius,
+            precision: if project.disable_high_precision { Precision::F64 } else { Precision::Auto },
+        }
+    }
+}
+
+impl TryFrom<ProjectFile> for Project {
+    type Error = RustybrotError;
+
+    fn try_from(file: ProjectFile) -> Result<Project> {
+        let mut gradient = Vec::with_capacity(file.gradient.len());
+        for (position, r, g, b, blend) in file.gradient {
+            if !(0.0..=1.0).contains(&position) || ![r, g, b].iter().all(|channel| (0.0..=255.0).contains(channel)) {
+                return Err(RustybrotError::Config(format!(
+                    "Invalid gradient stop {} {} {} {}, expected position 0..1 and channels 0..255", position, r, g, b)));
+            }
+            gradient.push(GradientStop { position, color: [r / 255.0, g / 255.0, b / 255.0], blend });
+        }
+        gradient.sort_by(|a, b| a.position.total_cmp(&b.position));
+
+        Ok(Project {
+            view: ViewState {
+                center: (file.center_re, file.center_im),
+                zoom: file.zoom,
+                iterations: file.iterations,
+                kind: match file.kind {
+                    Kind::Mandelbrot => FractalKind::Mandelbrot,
+                    Kind::Julia => FractalKind::Julia { cx: file.julia_re.unwrap_or(0.0), cy: file.julia_im.unwrap_or(0.0) },
+                },
+            },
+            formula: file.formula,
+            power: file.power,
+            scheme: file.scheme,
+            palette: file.palette,
+            gradient: (!gradient.is_empty()).then_some(gradient),
+            cycle_length: file.cycle.filter(|length| *length > 0.0),
+            escape_smoothing: file.escape_smoothing,
+            iteration_smoothing: file.iteration_smoothing,
+            aspect_policy: file.aspect,
+            anti_aliasing: AntiAliasing { factor: file.aa_factor, pattern: file.aa_pattern, boundary_radius: file.aa_boundary },
+            disable_high_precision: file.precision == Precision::F64,
+        })
+    }
+}
+
+pub fn save_project(project: &Project, path: &str) -> Result<()> {
+    Ok(fs::write(path, key_value::to_string(&ProjectFile::from(project))?)?)
+}
+
+// Fields missing from the file keep their defaults, unknown keys are ignored
+pub fn load_project(path: &str) -> Result<Project> {
+    let invalid = RustybrotError::Config;
+    let mut fields = key_value::parse_fields(&fs::read_to_string(path)?);
+
+    let version = match fields.iter().find(|(key, _)| key == "version") {
+        Some((_, value)) => value.parse().map_err(|_| invalid(format!("Invalid project version {:?}", value)))?,
+        None => return Err(invalid("Project file has no version".to_string())),
+    };
+    if version > PROJECT_VERSION {
+        return Err(invalid(format!("Project version {} is newer than this viewer supports ({})", version, PROJECT_VERSION)));
+    }
+    migrate(version, &mut fields);
+
+    let file: ProjectFile = key_value::from_fields(&fields)?;
+    Project::try_from(file)?.validate_and_clamp()
+}
+
+// Rewrites fields saved by older versions into the current layout, one step
+// per version bump
+fn migrate(version: u32, fields: &mut [(String, String)]) {
+    // Version 2 gave gradient stops a blend, version 1 stops blended in RGB
+    if version < 2 {
+        for (_, value) in fields.iter_mut().filter(|(key, _)| key == "gradient") {
+            value.push_str(" rgb");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn projects_round_trip_through_their_file() {
+        let project = Project {
+            view: ViewState {
+                center: (-0.743643887037151, 0.13182590420533),
+                zoom: 2.5e7,
+                iterations: 5000,
+                kind: FractalKind::Julia { cx: -0.8, cy: 0.156 },
+            },
+            formula: Formula::Tricorn,
+            power: 3,
+            scheme: ColorScheme::Heatmap,
+            palette: Palette::Ocean,
+            gradient: Some(vec![
+                GradientStop { position: 0.0, color: [0.0, 0.0, 0.0], blend: Blend::Rgb },
+                GradientStop { position: 0.5, color: [1.0, 0.5, 0.0], blend: Blend::Hsv },
+                GradientStop { position: 1.0, color: [1.0, 1.0, 1.0], blend: Blend::Cubic },
+            ]),
+            cycle_length: Some(64.0),
+            escape_smoothing: 0.5,
+            iteration_smoothing: 0.25,
+            aspect_policy: AspectPolicy::Fill,
+            anti_aliasing: AntiAliasing { factor: 3, pattern: SamplePattern::Halton, boundary_radius: Some(2) },
+            disable_high_precision: true,
+        };
+        let path = std::env::temp_dir().join(format!("rustybrot-project-{}.mandel", std::process::id()));
+        let path = path.to_str().unwrap();
+        save_project(&project, path).unwrap();
+        let text = fs::read_to_string(path).unwrap();
+        let loaded = load_project(path).unwrap();
+        fs::remove_file(path).unwrap();
+
+        assert_eq!(loaded.view, project.view);
+        assert_eq!((loaded.formula, loaded.power), (project.formula, project.power));
+        assert_eq!((loaded.scheme, loaded.palette, loaded.cycle_length), (project.scheme, project.palette, project.cycle_length));
+        assert_eq!((loaded.escape_smoothing, loaded.iteration_smoothing, loaded.aspect_policy), (0.5, 0.25, AspectPolicy::Fill));
+        assert_eq!((loaded.anti_aliasing.factor, loaded.anti_aliasing.pattern, loaded.anti_aliasing.boundary_radius), (3, SamplePattern::Halton, Some(2)));
+        assert!(loaded.disable_high_precision);
+        assert_eq!(loaded.gradient, project.gradient);
+        assert!(text.contains("gradient = 0.5 255 127.5 0 hsv\n"), "{}", text);
+    }
+
+    #[test]
+    fn project_files_skip_comments_and_unknown_keys() {
+        let fields = key_value::parse_fields("version = 1\n# saved by hand\nzoom = 40\nlighting = on\nkind = julia\njulia_re = 0.25\n");
+        let project = Project::try_from(key_value::from_fields::<ProjectFile>(&fields).unwrap()).unwrap();
+        assert_eq!(project.view.zoom, 40.0);
+        assert_eq!(project.view.kind, FractalKind::Julia { cx: 0.25, cy: 0.0 });
+        assert_eq!(project.view.iterations, Project::default().view.iterations);
+
+        let fields = key_value::parse_fields("version = 1\nformula = spiral\n");
+        assert!(key_value::from_fields::<ProjectFile>(&fields).is_err());
+    }
+
+    #[test]
+    fn version_one_gradients_blend_in_rgb() {
+        let path = std::env::temp_dir().join(format!("rustybrot-project-v1-{}.mandel", std::process::id()));
+        let path = path.to_str().unwrap();
+        fs::write(path, "version = 1\ngradient = 0 0 0 255\ngradient = 1 255 255 0\n").unwrap();
+        let project = load_project(path);
+        fs::remove_file(path).unwrap();
+
+        let gradient = project.unwrap().gradient.unwrap();
+        assert_eq!(gradient.iter().map(|stop| stop.blend).collect::<Vec<_>>(), [Blend::Rgb, Blend::Rgb]);
+        assert_eq!(gradient[1].color, [1.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn project_views_are_validated() {
+        let project = Project { view: ViewState { center: (f64::NAN, 0.0), ..ViewState::default() }, ..Project::default() };
+        assert!(project.validate_and_clamp().is_err());
+        let project = Project { view: ViewState { zoom: -1.0, iterations: 0, ..ViewState::default() }, ..Project::default() };
+        let view = project.validate_and_clamp().unwrap().view;
+        assert_eq!((view.zoom, view.iterations), (1e-3, 1));
+    }
+
+    #[test]
+    fn powers_are_clamped_into_range() {
+        assert_eq!(Project { power: 0, ..Project::default() }.validate_and_clamp().unwrap().power, 2);
+        assert_eq!(Project { power: 1000, ..Project::default() }.validate_and_clamp().unwrap().power, MAX_POWER);
+    }
+
+    #[test]
+    fn smoothing_is_clamped_into_range() {
+        let project = Project { escape_smoothing: 2.5, iteration_smoothing: -1.0, ..Project::default() }.validate_and_clamp().unwrap();
+        assert_eq!((project.escape_smoothing, project.iteration_smoothing), (1.0, 0.0));
+        let project = Project { escape_smoothing: f32::NAN, iteration_smoothing: f32::NAN, ..Project::default() }.validate_and_clamp().unwrap();
+        assert_eq!((project.escape_smoothing, project.iteration_smoothing), (1.0, 0.0));
+    }
+
+    #[test]
+    fn anti_aliasing_factors_are_clamped_into_range() {
+        let factor = |factor| {
+            let anti_aliasing = AntiAliasing { factor, ..Project::default().anti_aliasing };
+            Project { anti_aliasing, ..Project::default() }.validate_and_clamp().unwrap().anti_aliasing.factor
+        };
+        assert_eq!((factor(0), factor(100)), (1, MAX_AA_FACTOR));
+    }
+}
@@ -0,0 +1,51 @@
+use crate::mandelbrot::{iterations_for_zoom, AntiAliasing, MandelbrotFrame, SamplePattern};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum QualityProfile {
+    Draft,
+    Interactive,
+    High,
+    Print,
+}
+
+// Render settings that live outside MandelbrotFrame
+#[derive(Clone, Copy)]
+pub struct RenderSettings {
+    pub sample_step: u32,
+    pub anti_aliasing: AntiAliasing,
+}
+
+impl QualityProfile {
+    pub fn from_name(name: &str) -> Option<QualityProfile> {
+        match name.to_ascii_lowercase().as_str() {
+            "draft" => Some(QualityProfile::Draft),
+            "interactive" => Some(QualityProfile::Interactive),
+            "high" => Some(QualityProfile::High),
+            "print" => Some(QualityProfile::Print),
+            _ => None,
+        }
+    }
+
+    // (sample step, anti-aliasing factor, base iterations, allow high precision)
+    fn parameters(self) -> (u32, u32, u32, bool) {
+        match self {
+            QualityProfile::Draft => (4, 1, 100, false),
+            QualityProfile::Interactive => (1, 1, 250, true),
+            QualityProfile::High => (1, 2, 1000, true),
+            QualityProfile::Print => (1, 4, 4000, true),
+        }
+    }
+
+    // Configures the frame for this profile at the given zoom and returns the
+    // settings the caller needs for calculating and rendering it
+    pub fn apply(self, frame: &mut MandelbrotFrame, zoom: f64) -> RenderSettings {
+        let (sample_step, factor, base_iterations, allow_high_precision) = self.parameters();
+        frame.max_iterations = iterations_for_zoom(base_iterations, zoom);
+        frame.disable_high_precision = !allow_high_precision;
+
+        RenderSettings {
+            sample_step,
+            anti_aliasing: AntiAliasing { factor, pattern: SamplePattern::RotatedGrid, boundary_radius: None },
+        }
+    }
+}
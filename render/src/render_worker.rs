@@ -0,0 +1,96 @@
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::thread;
+use crate::mandelbrot::{AntiAliasing, MandelbrotFrame};
+use crate::tile_handler::TileCache;
+
+pub struct RenderJob {
+    pub frame: MandelbrotFrame,
+    pub sample_step: u32,
+    pub anti_aliasing: Option<AntiAliasing>,  // Supersample with these settings instead of one pass
+    pub in_motion: bool,  // Queued while the view was panning or zooming
+}
+
+// A finished job, with the frame as it was left by the calculation
+pub struct RenderOutput {
+    pub job: RenderJob,
+    pub sample_iterations: Vec<Vec<f32>>,  // One buffer per sample offset, a single one without AA
+}
+
+// Calculates frames on a background thread so the next frame is computed
+// while the current one is presented. At most one job is in flight. Frames
+// queued while the view moves are assembled from cached tiles, so panning
+// back over an area only calculates what wasn't seen before.
+pub struct RenderWorker {
+    jobs: Sender<(RenderJob, Vec<f32>)>,
+    outputs: Receiver<RenderOutput>,
+    busy: bool,
+    spare_buffer: Vec<f32>,  // Iteration buffer handed back by the viewer for the next job
+}
+
+impl RenderWorker {
+    pub fn new(tile_cache: usize) -> Self {
+        let (jobs, job_receiver) = mpsc::channel::<(RenderJob, Vec<f32>)>();
+        let (output_sender, outputs) = mpsc::channel();
+
+        // Exits once the worker is dropped and the job channel closes
+        thread::spawn(move || {
+            let mut tiles = TileCache::new(tile_cache);
+            for (mut job, mut buffer) in job_receiver {
+                let sample_iterations = match job.anti_aliasing {
+                    Some(anti_aliasing) => job.frame.calculate_supersampled(&anti_aliasing),
+                    None => {
+                        // The size changes with the sample step
+                        buffer.resize((job.frame.width * job.frame.height) as usize, 0.0);
+                        // Settled frames are calculated directly, at their exact position
+                        let tiled = job.in_motion && tiles.calculate_view(&job.frame, &mut buffer);
+                        if !tiled && job.frame.calculate_into(&mut buffer).is_err() {
+                            break;
+                        }
+                        vec![buffer]
+                    }
+                };
+                if output_sender.send(RenderOutput { job, sample_iterations }).is_err() {
+                    break;
+                }
+            }
+        });
+
+        RenderWorker {
+            jobs,
+            outputs,
+            busy: false,
+            spare_buffer: Vec::new(),
+        }
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.busy
+    }
+
+    pub fn submit(&mut self, job: RenderJob) {
+        let buffer = std::mem::take(&mut self.spare_buffer);
+        self.busy = self.jobs.send((job, buffer)).is_ok();
+    }
+
+    // Returns a finished output's buffers so the next job can reuse one
+    pub fn recycle(&mut self, sample_iterations: Vec<Vec<f32>>) {
+        if let Some(buffer) = sample_iterations.into_iter().next() {
+            self.spare_buffer = buffer;
+        }
+    }
+
+    // Takes the finished frame if the job in flight is done
+    pub fn try_take(&mut self) -> Option<RenderOutput> {
+        match self.outputs.try_recv() {
+            Ok(output) => {
+                self.busy = false;
+                Some(output)
+            }
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => {
+                self.busy = false;
+                None
+            }
+        }
+    }
+}
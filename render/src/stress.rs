@@ -0,0 +1,137 @@
+use std::ops::RangeInclusive;
+use crate::error::Result;
+use crate::mandelbrot::{MandelbrotFrame, RenderError};
+
+// Small enough that the whole battery renders in seconds in high precision
+const STRESS_WIDTH: u32 = 48;
+const STRESS_HEIGHT: u32 = 32;
+
+// Largest roughness a frame may have, healthy deep views measure a few
+// hundredths and noise about a third
+const MAX_ROUGHNESS: f32 = 0.15;
+
+// A deep view with a known feature, and the fraction of its pixels that
+// should come out in the set
+struct StressCase {
+    name: &'static str,
+    center: (f64, f64),
+    zoom: f64,
+    iterations: u32,
+    in_set: RangeInclusive<f32>,
+}
+
+// Zooms sit just past the switch to high precision, where pixel coordinates
+// are still distinct in f64
+const STRESS_CASES: [StressCase; 5] = [
+    StressCase { name: "cardioid interior", center: (-0.1, 0.1), zoom: 4e14, iterations: 200, in_set: 1.0..=1.0 },
+    StressCase { name: "exterior", center: (0.3, 0.6), zoom: 4e14, iterations: 200, in_set: 0.0..=0.0 },
+    StressCase { name: "misiurewicz spiral", center: (-0.77568377, 0.13646737), zoom: 4e14, iterations: 500, in_set: 0.0..=0.5 },
+    StressCase { name: "antenna tip", center: (-2.0, 0.0), zoom: 4e14, iterations: 500, in_set: 0.0..=0.5 },
+    StressCase { name: "dendrite at i", center: (0.0, 1.0), zoom: 4e14, iterations: 500, in_set: 0.0..=0.5 },
+];
+
+// Renders each case twice in high precision and checks invariants no
+// healthy frame breaks, see check. The tests run the same battery.
+pub fn run() -> Result<()> {
+    let mut failed = 0;
+    for case in &STRESS_CASES {
+        let problems = check(case);
+        if problems.is_empty() {
+            println!("{}: ok", case.name);
+        } else {
+            println!("{}: FAILED, {}", case.name, problems.join("; "));
+            failed += 1;
+        }
+    }
+
+    if failed > 0 {
+        return Err(RenderError::StressFailures { failed, cases: STRESS_CASES.len() }.into());
+    }
+    println!("All {} high precision stress cases passed", STRESS_CASES.len());
+    Ok(())
+}
+
+// Problems with a case's render, none for a healthy frame: the frame really
+// took the high precision path, every value is finite and within
+// 0..=max_iterations, the in-set fraction is in the case's range, boundary
+// views are neither one flat value nor noise, and two renders match bit for
+// bit. No golden images are kept, so it catches all-black, all-noise and
+// nondeterministic frames rather than small shifts.
+fn check(case: &StressCase) -> Vec<String> {
+    let mut frame = MandelbrotFrame::new(STRESS_WIDTH, STRESS_HEIGHT);
+    frame.max_iterations = case.iterations;
+    frame.set_view(case.center.0, case.center.1, case.zoom);
+
+    let first = frame.calculate();
+    let second = frame.calculate();
+    let in_set = frame.in_set_mask(&first).iter().filter(|&&inside| inside).count() as f32 / first.len() as f32;
+    let is_boundary = *case.in_set.start() < 1.0 && *case.in_set.end() > 0.0;
+    let roughness = roughness(&first, STRESS_WIDTH as usize);
+    let flat = first.iter().all(|&value| value.to_bits() == first[0].to_bits());
+
+    let mut problems = Vec::new();
+    if !frame.uses_high_precision() {
+        problems.push("rendered in f64 instead of high precision".to_string());
+    }
+    if let Some(value) = first.iter().find(|value| !value.is_finite() || **value < 0.0 || **value > case.iterations as f32) {
+        problems.push(format!("value {} outside 0..={}", value, case.iterations));
+    }
+    if !case.in_set.contains(&in_set) {
+        problems.push(format!("{:.1}% in the set, expected {:.0}%..={:.0}%",
+            in_set * 100.0, case.in_set.start() * 100.0, case.in_set.end() * 100.0));
+    }
+    if is_boundary && flat {
+        problems.push("a boundary view came out one flat value".to_string());
+    }
+    if roughness > MAX_ROUGHNESS {
+        problems.push(format!("roughness {:.3} over {}, the frame looks like noise", roughness, MAX_ROUGHNESS));
+    }
+    if first.iter().zip(&second).any(|(a, b)| a.to_bits() != b.to_bits()) {
+        problems.push("two renders of the same view differ".to_string());
+    }
+    problems
+}
+
+// Mean difference between horizontally neighboring values as a fraction of
+// the frame's value range, near 0 for smooth escape bands and about a third
+// for uncorrelated noise
+fn roughness(values: &[f32], width: usize) -> f32 {
+    let (min, max) = values.iter().fold((f32::MAX, f32::MIN), |(min, max), &value| (min.min(value), max.max(value)));
+    if max <= min {
+        return 0.0;
+    }
+    let (sum, count) = values.chunks(width)
+        .flat_map(|row| row.windows(2))
+        .fold((0.0, 0), |(sum, count), pair| (sum + (pair[1] - pair[0]).abs(), count + 1));
+    sum / count as f32 / (max - min)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn high_precision_stress_cases_pass() {
+        for case in &STRESS_CASES {
+            assert_eq!(check(case), Vec::<String>::new(), "{}", case.name);
+        }
+    }
+
+    #[test]
+    fn roughness_tells_noise_from_bands() {
+        let bands: Vec<f32> = (0..64 * 8).map(|i| (i % 64) as f32).collect();
+        let noise: Vec<f32> = (0..64 * 8u32).map(|i| (i.wrapping_mul(2_654_435_761) >> 24) as f32).collect();
+        assert!(roughness(&bands, 64) < MAX_ROUGHNESS);
+        assert!(roughness(&noise, 64) > MAX_ROUGHNESS);
+        assert_eq!(roughness(&[3.0; 64], 8), 0.0);
+    }
+
+    #[test]
+    fn stress_cases_catch_flat_boundary_views() {
+        // The cardioid's interior at its iteration cap is one flat value, fine
+        // there but a failure for a case expecting a boundary
+        let case = StressCase { name: "flat", center: (-0.1, 0.1), zoom: 4e14, iterations: 20, in_set: 0.0..=0.5 };
+        let problems = check(&case);
+        assert!(problems.iter().any(|problem| problem.contains("flat")), "{:?}", problems);
+    }
+}
@@ -0,0 +1,148 @@
+use rayon::prelude::*;
+use crate::mandelbrot::MandelbrotFrame;
+use crate::pixel_format::{argb_u32_to_rgba8, rgba8_to_argb_u32};
+
+// Frames of history a pixel can carry, bounding how long stale colors linger
+const MAX_HISTORY_WEIGHT: f32 = 8.0;
+
+// Pan between frames, in output pixels, past which the history is dropped
+const MAX_SHIFT: f64 = 16.0;
+
+// Relative difference in view span still treated as the same zoom
+const SPAN_TOLERANCE: f64 = 1e-9;
+
+// Temporal anti-aliasing for slow pans. Each frame samples the plane at
+// slightly different sub-pixel positions, so blending a frame with the
+// reprojected history of earlier ones builds up a supersampled image over
+// time. History is reprojected to the nearest integer pixel and weighted down
+// by how far that is from the sample it came from.
+pub struct TemporalAccumulator {
+    width: usize,
+    height: usize,
+    history: Vec<[f32; 4]>,  // Accumulated RGBA per output pixel
+    weights: Vec<f32>,       // Frames' worth of samples in each history pixel
+    view: Option<MandelbrotFrame>,  // View the history is aligned to
+}
+
+impl TemporalAccumulator {
+    pub fn new(width: u32, height: u32) -> Self {
+        TemporalAccumulator {
+            width: width as usize,
+            height: height as usize,
+            history: Vec::new(),
+            weights: Vec::new(),
+            view: None,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.view = None;
+    }
+
+    // Blends the frame's output pixels, colored for the given view, with the
+    // history and leaves the result in both. Zooming, rotating or panning
+    // further than MAX_SHIFT starts the history over from this frame.
+    pub fn blend(&mut self, view: &MandelbrotFrame, pixels: &mut [u32]) {
+        let current = argb_u32_to_rgba8(pixels);
+        let shift = self.view.as_ref().and_then(|previous| self.shift_from(previous, view));
+        let Some((shift_x, shift_y)) = shift else {
+            self.history = current.chunks_exact(4).map(|rgba| [0, 1, 2, 3].map(|c| rgba[c] as f32)).collect();
+            self.weights = vec![1.0; self.width * self.height];
+            self.view = Some(Self::view_of(view));
+            return;
+        };
+
+        let (step_x, step_y) = (shift_x.round(), shift_y.round());
+        let (width, height) = (self.width as isize, self.height as isize);
+        let (history, weights) = (&self.history, &self.weights);
+        let subpixel = ((1.0 - (shift_x - step_x).abs()) * (1.0 - (shift_y - step_y).abs())) as f32;
+
+        let (blended, blended_weights): (Vec<[f32; 4]>, Vec<f32>) = current.par_chunks_exact(4)
+            .enumerate()
+            .map(|(i, rgba)| {
+                let sample = [0, 1, 2, 3].map(|c| rgba[c] as f32);
+                let source_x = (i % self.width) as isize + step_x as isize;
+                let source_y = (i / self.width) as isize + step_y as isize;
+                if source_x < 0 || source_y < 0 || source_x >= width || source_y >= height {
+                    return (sample, 1.0);
+                }
+
+                let source = source_y as usize * self.width + source_x as usize;
+                let weight = weights[source].min(MAX_HISTORY_WEIGHT) * subpixel;
+                let total = weight + 1.0;
+                let color = [0, 1, 2, 3].map(|c| (history[source][c] * weight + sample[c]) / total);
+                (color, total)
+            })
+            .unzip();
+
+        let bytes: Vec<u8> = blended.iter().flatten().map(|&channel| channel.round() as u8).collect();
+        pixels.copy_from_slice(&rgba8_to_argb_u32(&bytes));
+        self.history = blended;
+        self.weights = blended_weights;
+        self.view = Some(Self::view_of(view));
+    }
+
+    // Output pixels the history has to move by to line up with the view, None
+    // if it can't be reused
+    fn shift_from(&self, previous: &MandelbrotFrame, view: &MandelbrotFrame) -> Option<(f64, f64)> {
+        // Spans differ in their last bits once the view has moved
+        let same_span = |a: f64, b: f64| (a - b).abs() <= a.abs() * SPAN_TOLERANCE;
+        let same_scale = same_span(previous.x_max - previous.x_min, view.x_max - view.x_min)
+            && same_span(previous.y_max - previous.y_min, view.y_max - view.y_min)
+            && previous.rotation_radians == view.rotation_radians;
+        if !same_scale || self.history.len() != self.width * self.height {
+            return None;
+        }
+
+        // Where the sample of the view's top-left pixel lies on the previous
+        // frame, in output pixels from the top-left corner of each. A frame's
+        // samples sit sample_offset into pixels sample_step output pixels wide,
+        // so frames of different sample steps line up too.
+        let corner = view.pixel_to_complex(0, 0);
+        let (x, y) = previous.complex_to_pixel(corner.0, corner.1);
+        let output_position = |pixel: f64, offset: f64, output: usize, frame: u32| (pixel + offset) * output as f64 / frame as f64;
+        let shift_x = output_position(x as f64, previous.sample_offset.0, self.width, previous.width) - output_position(0.0, view.sample_offset.0, self.width, view.width);
+        let shift_y = output_position(y as f64, previous.sample_offset.1, self.height, previous.height) - output_position(0.0, view.sample_offset.1, self.height, view.height);
+        (shift_x.abs() <= MAX_SHIFT && shift_y.abs() <= MAX_SHIFT).then_some((shift_x, shift_y))
+    }
+
+    // Copy of the parts of the view that place it on the plane
+    fn view_of(view: &MandelbrotFrame) -> MandelbrotFrame {
+        let mut copy = MandelbrotFrame::new(view.width, view.height);
+        copy.x_min = view.x_min;
+        copy.x_max = view.x_max;
+        copy.y_min = view.y_min;
+        copy.y_max = view.y_max;
+        copy.rotation_radians = view.rotation_radians;
+        copy.sample_offset = view.sample_offset;
+        copy
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn view(width: u32, height: u32, center_x: f64) -> MandelbrotFrame {
+        let mut frame = MandelbrotFrame::new(width, height);
+        frame.set_view(center_x, 0.0, 1.0);
+        frame
+    }
+
+    #[test]
+    fn shifts_are_unbiased_across_sample_steps() {
+        let mut accumulator = TemporalAccumulator::new(100, 80);
+        accumulator.blend(&view(100, 80, -0.5), &mut vec![0; 100 * 80]);
+
+        // The same view at half resolution, as a moving frame renders it
+        let (x, y) = accumulator.shift_from(&view(100, 80, -0.5), &view(50, 40, -0.5)).unwrap();
+        assert!(x.abs() < 1e-4 && y.abs() < 1e-4, "{} {}", x, y);
+        let (x, y) = accumulator.shift_from(&view(50, 40, -0.5), &view(100, 80, -0.5)).unwrap();
+        assert!(x.abs() < 1e-4 && y.abs() < 1e-4, "{} {}", x, y);
+
+        // Panned right by three output pixels
+        let pixel = view(100, 80, -0.5).visible_half_size().0 * 2.0 / 100.0;
+        let (x, _) = accumulator.shift_from(&view(100, 80, -0.5), &view(50, 40, -0.5 + 3.0 * pixel)).unwrap();
+        assert!((x - 3.0).abs() < 1e-4, "{}", x);
+    }
+}
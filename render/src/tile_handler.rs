@@ -0,0 +1,395 @@
+use std::collections::{HashMap, VecDeque};
+use std::fs::{self, File};
+use std::io::{Seek, SeekFrom, Write};
+use crate::error::{Result, RustybrotError};
+use crate::export_handler::ExportHandler;
+use crate::frame_handler::FrameHandler;
+use crate::mandelbrot::{AntiAliasing, AspectPolicy, Formula, FractalKind, MandelbrotFrame, SamplePattern, StandardFloat, VIEW_HALF_WIDTH};
+use crate::pixel_format::unpremultiply;
+use crate::quality::QualityProfile;
+
+pub const TILE_SIZE: u32 = 256;
+pub const DEFAULT_TILE_CACHE: usize = 64;
+// Side of the tiles moving viewer frames are assembled from, small so little
+// is calculated beyond the frame's edges
+pub const VIEW_TILE_SIZE: u32 = 64;
+// Deepest pyramid written, its last level already a billion tiles
+pub const MAX_PYRAMID_LEVELS: u32 = 16;
+
+// Everything the iteration buffers of a tile depend on, coloring aside. Floats
+// are keyed by their bits, tiles on the same grid reproduce them exactly.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct TileKey {
+    x_min: u64,
+    y_min: u64,
+    size: u64,
+    pixels: u32,
+    max_iterations: u32,
+    julia: Option<(u64, u64)>,
+    formula: Formula,
+    power: u32,
+    initial_z: (u64, u64),
+    sample_offset: (u64, u64),
+    anti_aliasing: (u32, SamplePattern, Option<u32>),
+    high_precision: bool,
+    standard_float: StandardFloat,
+    escape_smoothing: u32,
+    capped_lookahead: bool,
+}
+
+impl TileKey {
+    fn new(frame: &MandelbrotFrame, size: f64, anti_aliasing: AntiAliasing) -> Self {
+        TileKey {
+            x_min: frame.x_min.to_bits(),
+            y_min: frame.y_min.to_bits(),
+            size: size.to_bits(),
+            pixels: frame.width,
+            max_iterations: frame.max_iterations,
+            julia: match frame.kind {
+                FractalKind::Mandelbrot => None,
+                FractalKind::Julia { cx, cy } => Some((cx.to_bits(), cy.to_bits())),
+            },
+            formula: frame.formula,
+            power: frame.power,
+            initial_z: (frame.initial_z.0.to_bits(), frame.initial_z.1.to_bits()),
+            sample_offset: (frame.sample_offset.0.to_bits(), frame.sample_offset.1.to_bits()),
+            anti_aliasing: (anti_aliasing.factor, anti_aliasing.pattern, anti_aliasing.boundary_radius),
+            high_precision: !frame.disable_high_precision,
+            standard_float: frame.standard_float,
+            escape_smoothing: frame.escape_smoothing.to_bits(),
+            capped_lookahead: frame.capped_lookahead,
+        }
+    }
+}
+
+// Least recently used tile iteration buffers, one per anti-aliasing sample
+pub struct TileCache {
+    capacity: usize,
+    entries: HashMap<TileKey, Vec<Vec<f32>>>,
+    order: VecDeque<TileKey>,  // Least recently used first
+    computed_tiles: usize,  // View tiles calculated rather than taken from the cache
+}
+
+impl TileCache {
+    pub fn new(capacity: usize) -> Self {
+        TileCache {
+            capacity,
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+            computed_tiles: 0,
+        }
+    }
+
+    // Calculates a frame by assembling it from VIEW_TILE_SIZE tiles on a grid
+    // fixed to the plane, so panning back over an area takes its tiles from
+    // the cache. The grid's pixel size is the frame's rounded to 32 bits of
+    // mantissa, and the frame snaps to the nearest grid pixel, so a frame is
+    // off by at most half a pixel. Returns false, leaving the buffer alone,
+    // for frames the grid can't reproduce: high precision, rotated, log-polar,
+    // custom recurrences and ones confirming interior for the next frame.
+    pub fn calculate_view(&mut self, frame: &MandelbrotFrame, out: &mut [f32]) -> bool {
+        let pixel_width = (frame.x_max - frame.x_min) / frame.width as f64;
+        let pixel_height = (frame.y_max - frame.y_min) / frame.height as f64;
+        if frame.uses_high_precision() || frame.rotation_radians != 0.0 || frame.log_polar_decades.is_some()
+            || frame.recurrence.is_some() || frame.periodicity.is_some()
+            || (pixel_width - pixel_height).abs() > pixel_width * 1e-9 || out.len() != (frame.width * frame.height) as usize {
+            return false;
+        }
+
+        let pixel_size = f64::from_bits((pixel_width.to_bits() + (1 << 19)) & !((1 << 20) - 1));
+        let (left, top) = ((frame.x_min / pixel_size).round(), (frame.y_min / pixel_size).round());
+        if left.abs() > 2f64.powi(52) || top.abs() > 2f64.powi(52) {
+            return false;
+        }
+        let (left, top) = (left as i64, top as i64);
+
+        let tile_size = VIEW_TILE_SIZE as i64;
+        let tile_span = VIEW_TILE_SIZE as f64 * pixel_size;
+        let anti_aliasing = AntiAliasing { factor: 1, pattern: SamplePattern::Grid, boundary_radius: None };
+        for tile_y in top.div_euclid(tile_size)..=(top + frame.height as i64 - 1).div_euclid(tile_size) {
+            for tile_x in left.div_euclid(tile_size)..=(left + frame.width as i64 - 1).div_euclid(tile_size) {
+                let mut tile = MandelbrotFrame::new(VIEW_TILE_SIZE, VIEW_TILE_SIZE);
+                tile.aspect_policy = AspectPolicy::Stretch;
+                tile.x_min = (tile_x * tile_size) as f64 * pixel_size;
+                tile.x_max = tile.x_min + tile_span;
+                tile.y_min = (tile_y * tile_size) as f64 * pixel_size;
+                tile.y_max = tile.y_min + tile_span;
+                tile.max_iterations = frame.max_iterations;
+                tile.kind = frame.kind;
+                tile.formula = frame.formula;
+                tile.power = frame.power;
+                tile.initial_z = frame.initial_z;
+                tile.sample_offset = frame.sample_offset;
+                tile.standard_float = frame.standard_float;
+                tile.escape_smoothing = frame.escape_smoothing;
+                tile.capped_lookahead = frame.capped_lookahead;
+                tile.sequential = frame.sequential;
+                // The frame itself was checked to be within f64, a tile's smaller extent says nothing more
+                tile.disable_high_precision = true;
+
+                let key = TileKey::new(&tile, tile_span, anti_aliasing);
+                let iterations = match self.get(&key) {
+                    Some(sample_iterations) => sample_iterations[0].clone(),
+                    None => {
+                        self.computed_tiles += 1;
+                        let iterations = tile.calculate();
+                        self.insert(key, vec![iterations.clone()]);
+                        iterations
+                    }
+                };
+
+                // Copy the rows and columns of the tile the frame overlaps
+                let x_range = (tile_x * tile_size).max(left)..((tile_x + 1) * tile_size).min(left + frame.width as i64);
+                for y in (tile_y * tile_size).max(top)..((tile_y + 1) * tile_size).min(top + frame.height as i64) {
+                    let source = ((y - tile_y * tile_size) * tile_size + x_range.start - tile_x * tile_size) as usize;
+                    let target = ((y - top) * frame.width as i64 + x_range.start - left) as usize;
+                    let len = (x_range.end - x_range.start) as usize;
+                    out[target..target + len].copy_from_slice(&iterations[source..source + len]);
+                }
+            }
+        }
+        frame.mark_letterbox_bars(out);
+        true
+    }
+
+    fn get(&mut self, key: &TileKey) -> Option<&Vec<Vec<f32>>> {
+        let position = self.order.iter().position(|entry| entry == key)?;
+        self.order.remove(position);
+        self.order.push_back(*key);
+        self.entries.get(key)
+    }
+
+    fn insert(&mut self, key: TileKey, sample_iterations: Vec<Vec<f32>>) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() >= self.capacity {
+            if let Some(evicted) = self.order.pop_front() {
+                self.entries.remove(&evicted);
+            }
+        }
+        self.order.push_back(key);
+        self.entries.insert(key, sample_iterations);
+    }
+}
+
+// Renders square tiles of the complex plane, individually or as a slippy map
+// pyramid where level z splits the extent into 2^z x 2^z tiles addressed z/x/y.
+// Recently rendered tiles are cached, so revisited tiles are only recolored.
+pub struct TileHandler {
+    frame_handler: FrameHandler,
+    export_handler: ExportHandler,
+    quality: QualityProfile,
+    cache: TileCache,
+    computed_tiles: usize,  // Tiles calculated rather than taken from the cache
+    tile_size: u32,  // Side of a tile in pixels
+}
+
+impl TileHandler {
+    pub fn new(quality: QualityProfile, cache_capacity: usize) -> Self {
+        TileHandler {
+            frame_handler: FrameHandler::new(TILE_SIZE, TILE_SIZE),
+            export_handler: ExportHandler::new(TILE_SIZE, TILE_SIZE),
+            quality,
+            cache: TileCache::new(cache_capacity),
+            computed_tiles: 0,
+            tile_size: TILE_SIZE,
+        }
+    }
+
+    pub fn set_tile_size(&mut self, tile_size: u32) {
+        self.tile_size = tile_size;
+        self.frame_handler = FrameHandler::new(tile_size, tile_size);
+        self.export_handler = ExportHandler::new(tile_size, tile_size);
+    }
+
+    // Renders the tile whose top-left corner is (x_min, y_min) with the given side length
+    pub fn render_tile(&mut self, x_min: f64, y_min: f64, size: f64) -> &FrameHandler {
+        let mut frame = MandelbrotFrame::new(self.tile_size, self.tile_size);
+
+        // Iterations follow the zoom a full viewer window would need for this pixel scale
+        let settings = self.quality.apply(&mut frame, 2.0 * VIEW_HALF_WIDTH / size);
+        frame.x_min = x_min;
+        frame.x_max = x_min + size;
+        frame.y_min = y_min;
+        frame.y_max = y_min + size;
+
+        let anti_aliasing = settings.anti_aliasing;
+        let key = TileKey::new(&frame, size, anti_aliasing);
+        let sample_iterations = match self.cache.get(&key) {
+            Some(sample_iterations) => sample_iterations.clone(),
+            None => {
+                self.computed_tiles += 1;
+                let sample_iterations = if anti_aliasing.factor > 1 {
+                    frame.calculate_supersampled(&anti_aliasing)
+                } else {
+                    vec![frame.calculate()]
+                };
+                self.cache.insert(key, sample_iterations.clone());
+                sample_iterations
+            }
+        };
+
+        if sample_iterations.len() > 1 {
+            self.frame_handler.render_supersampled(&sample_iterations, frame.max_iterations);
+        } else {
+            self.frame_handler.render_frame(&sample_iterations[0], frame.max_iterations, 1);
+        }
+
+        &self.frame_handler
+    }
+
+    // Writes out_dir/z/x/y.png for every level below `levels`, level 0 being a
+    // single tile covering the square of half-size `half_size` around the center
+    pub fn write_pyramid(&mut self, out_dir: &str, center: (f64, f64), half_size: f64, levels: u32) -> Result<()> {
+        let x_origin = center.0 - half_size;
+        let y_origin = center.1 - half_size;
+
+        if levels > MAX_PYRAMID_LEVELS {
+            return Err(RustybrotError::Config(format!("{} tile levels requested, at most {} are supported", levels, MAX_PYRAMID_LEVELS)));
+        }
+
+        for level in 0..levels {
+            let computed_before = self.computed_tiles;
+            let tiles_per_side = 1u32.checked_shl(level).expect("level count is bounded");
+            let tile_size = 2.0 * half_size / tiles_per_side as f64;
+
+            for x in 0..tiles_per_side {
+                let column_dir = format!("{}/{}/{}", out_dir, level, x);
+                fs::create_dir_all(&column_dir)?;
+
+                for y in 0..tiles_per_side {
+                    let x_min = x_origin + x as f64 * tile_size;
+                    let y_min = y_origin + y as f64 * tile_size;
+                    self.render_tile(x_min, y_min, tile_size);
+                    self.export_handler.export_png(self.frame_handler.get_draw_target(), &format!("{}/{}.png", column_dir, y))?;
+                }
+            }
+            let tiles = tiles_per_side as usize * tiles_per_side as usize;
+            let computed = self.computed_tiles - computed_before;
+            println!("Level {} done ({} tiles, {} from cache)", level, tiles, tiles - computed);
+        }
+
+        Ok(())
+    }
+
+    // Renders a width x height image of the square pixel grid spanning
+    // 2 * half_width of the real axis around the center, as a PAM file of
+    // straight alpha RGBA. The file is sized up front and each tile is written
+    // to its rows' offsets as it completes, so only one tile is ever held in
+    // memory however large the image. Tiles overhanging the right and bottom
+    // edges are cropped.
+    pub fn write_image(&mut self, path: &str, center: (f64, f64), half_width: f64, width: u32, height: u32) -> Result<()> {
+        let header = format!("P7\nWIDTH {}\nHEIGHT {}\nDEPTH 4\nMAXVAL 255\nTUPLTYPE RGB_ALPHA\nENDHDR\n", width, height);
+        let mut file = File::create(path)?;
+        file.write_all(header.as_bytes())?;
+        file.set_len(header.len() as u64 + width as u64 * height as u64 * 4)?;
+
+        let tile_size = self.tile_size;
+        let pixel_size = 2.0 * half_width / width as f64;
+        let x_origin = center.0 - half_width;
+        let y_origin = center.1 - pixel_size * height as f64 / 2.0;
+        let rows = height.div_ceil(tile_size);
+
+        for row in 0..rows {
+            let top = row * tile_size;
+            for left in (0..width).step_by(tile_size as usize) {
+                self.render_tile(x_origin + left as f64 * pixel_size, y_origin + top as f64 * pixel_size, tile_size as f64 * pixel_size);
+
+                let pixels = self.frame_handler.get_draw_target().get_data();
+                let tile_width = tile_size.min(width - left) as usize;
+                for y in 0..tile_size.min(height - top) {
+                    let line: Vec<u8> = pixels[(y * tile_size) as usize..][..tile_width].iter()
+                        .flat_map(|pixel| {
+                            let [a, r, g, b] = pixel.to_be_bytes();
+                            unpremultiply(&[r, g, b, a])
+                        })
+                        .collect();
+                    let offset = header.len() as u64 + ((top + y) as u64 * width as u64 + left as u64) * 4;
+                    file.seek(SeekFrom::Start(offset))?;
+                    file.write_all(&line)?;
+                }
+            }
+            println!("Tile row {} of {} done", row + 1, rows);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rendering_a_tile_again_takes_it_from_the_cache() {
+        let mut tiles = TileHandler::new(QualityProfile::from_name("draft").unwrap(), DEFAULT_TILE_CACHE);
+        tiles.set_tile_size(32);
+        tiles.render_tile(-2.0, -1.5, 3.0);
+        tiles.render_tile(-0.5, -1.5, 1.5);
+        tiles.render_tile(-2.0, -1.5, 3.0);
+        assert_eq!(tiles.computed_tiles, 2);
+    }
+
+    #[test]
+    fn panning_back_calculates_no_view_tiles() {
+        let mut cache = TileCache::new(DEFAULT_TILE_CACHE);
+        let mut frame = MandelbrotFrame::new(100, 75);
+        let mut out = vec![0.0; 100 * 75];
+        frame.set_view(-0.5, 0.0, 1.0);
+        assert!(cache.calculate_view(&frame, &mut out));
+        let first = out.clone();
+        let computed = cache.computed_tiles;
+        assert!(computed > 0);
+
+        // Panned into new tiles, then back
+        let pixel_size = (frame.x_max - frame.x_min) / 100.0;
+        frame.set_view(-0.5 + 40.0 * pixel_size, 0.0, 1.0);
+        assert!(cache.calculate_view(&frame, &mut out));
+        assert!(cache.computed_tiles > computed);
+        let computed = cache.computed_tiles;
+        frame.set_view(-0.5, 0.0, 1.0);
+        let mut again = vec![0.0; 100 * 75];
+        assert!(cache.calculate_view(&frame, &mut again));
+        assert_eq!(cache.computed_tiles, computed);
+        assert_eq!(again, first);
+    }
+
+    #[test]
+    fn frames_on_the_grid_match_their_direct_calculation() {
+        // Pixels 1/32 wide, with the frame's corner on a grid pixel
+        let mut frame = MandelbrotFrame::new(96, 80);
+        frame.x_min = -2.0;
+        frame.x_max = 1.0;
+        frame.y_min = -1.25;
+        frame.y_max = 1.25;
+        let mut tiled = vec![0.0; 96 * 80];
+        assert!(TileCache::new(DEFAULT_TILE_CACHE).calculate_view(&frame, &mut tiled));
+        let direct = frame.calculate();
+        let differing = direct.iter().zip(&tiled).filter(|(a, b)| (*a - *b).abs() > 1e-3).count();
+        assert_eq!(differing, 0);
+    }
+
+    #[test]
+    fn rotated_views_are_not_tiled() {
+        let mut cache = TileCache::new(DEFAULT_TILE_CACHE);
+        let mut frame = MandelbrotFrame::new(40, 30);
+        frame.set_view(-0.5, 0.0, 1.0);
+        frame.rotation_radians = 0.5;
+        assert!(!cache.calculate_view(&frame, &mut vec![0.0; 40 * 30]));
+        assert_eq!(cache.computed_tiles, 0);
+    }
+
+    #[test]
+    fn view_tiles_are_keyed_by_float_type() {
+        let mut cache = TileCache::new(DEFAULT_TILE_CACHE);
+        let mut frame = MandelbrotFrame::new(40, 30);
+        frame.set_view(-0.5, 0.0, 1.0);
+        let mut out = vec![0.0; 40 * 30];
+        assert!(cache.calculate_view(&frame, &mut out));
+        let computed = cache.computed_tiles;
+        frame.standard_float = StandardFloat::F32;
+        assert!(cache.calculate_view(&frame, &mut out));
+        assert_eq!(cache.computed_tiles, 2 * computed);
+    }
+}
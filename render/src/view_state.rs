@@ -0,0 +1,114 @@
+use crate::error::{Result, RustybrotError};
+use crate::mandelbrot::{FractalKind, BIGFLOAT_DIGITS};
+
+// Range a loaded zoom is clamped into. Below the minimum the set is a speck,
+// above the maximum BigFloat has no digits left to resolve pixels with.
+const MIN_ZOOM: f64 = 1e-3;
+const MAX_ZOOM_DIGITS: i32 = BIGFLOAT_DIGITS - 4;
+
+// Loaded iteration counts are clamped to this, which leaves room for the zoom
+// detail multiplier without overflowing
+const MAX_ITERATIONS: u32 = 10_000_000;
+
+// Where a view looks and how far it iterates, the part of a project, bookmark
+// or location that has to describe a renderable place
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ViewState {
+    pub center: (f64, f64),
+    pub zoom: f64,
+    pub iterations: u32,
+    pub kind: FractalKind,
+}
+
+impl Default for ViewState {
+    fn default() -> Self {
+        ViewState {
+            center: (-0.5, 0.0),
+            zoom: 1.0,
+            iterations: 100,
+            kind: FractalKind::Mandelbrot,
+        }
+    }
+}
+
+impl ViewState {
+    // Normalizes a view loaded from outside into one that can be rendered,
+    // clamping values that are merely out of range and refusing ones that
+    // don't describe a place at all
+    pub fn validate_and_clamp(self) -> Result<ViewState> {
+        if !(self.center.0.is_finite() && self.center.1.is_finite()) {
+            return Err(RustybrotError::Config(format!("Center ({}, {}) is not a point on the plane", self.center.0, self.center.1)));
+        }
+        if let FractalKind::Julia { cx, cy } = self.kind {
+            if !(cx.is_finite() && cy.is_finite()) {
+                return Err(RustybrotError::Config(format!("Julia constant ({}, {}) is not a point on the plane", cx, cy)));
+            }
+        }
+
+        Ok(ViewState {
+            zoom: clamp_zoom(self.zoom)?,
+            iterations: clamp_iterations(self.iterations),
+            ..self
+        })
+    }
+}
+
+// Zoom clamped into the renderable range, only a zoom that isn't a number is
+// beyond saving
+pub fn clamp_zoom(zoom: f64) -> Result<f64> {
+    if zoom.is_nan() {
+        return Err(RustybrotError::Config("Zoom is not a number".to_string()));
+    }
+    Ok(zoom.clamp(MIN_ZOOM, 10f64.powi(MAX_ZOOM_DIGITS)))
+}
+
+pub fn clamp_iterations(iterations: u32) -> u32 {
+    iterations.clamp(1, MAX_ITERATIONS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn view(center: (f64, f64), zoom: f64, iterations: u32) -> ViewState {
+        ViewState { center, zoom, iterations, kind: FractalKind::Mandelbrot }
+    }
+
+    #[test]
+    fn valid_views_are_unchanged() {
+        let valid = view((-0.75, 0.1), 250.0, 500);
+        assert_eq!(valid.validate_and_clamp().unwrap(), valid);
+    }
+
+    #[test]
+    fn centers_off_the_plane_are_rejected() {
+        assert!(view((f64::NAN, 0.0), 1.0, 100).validate_and_clamp().is_err());
+        assert!(view((0.0, f64::INFINITY), 1.0, 100).validate_and_clamp().is_err());
+    }
+
+    #[test]
+    fn julia_constants_off_the_plane_are_rejected() {
+        let julia = ViewState { kind: FractalKind::Julia { cx: f64::NAN, cy: 0.156 }, ..ViewState::default() };
+        assert!(julia.validate_and_clamp().is_err());
+    }
+
+    #[test]
+    fn zooms_that_are_not_numbers_are_rejected() {
+        assert!(view((0.0, 0.0), f64::NAN, 100).validate_and_clamp().is_err());
+    }
+
+    #[test]
+    fn zooms_are_clamped_into_range() {
+        assert_eq!(view((0.0, 0.0), -5.0, 100).validate_and_clamp().unwrap().zoom, MIN_ZOOM);
+        assert_eq!(view((0.0, 0.0), 0.0, 100).validate_and_clamp().unwrap().zoom, MIN_ZOOM);
+        let deepest = 10f64.powi(MAX_ZOOM_DIGITS);
+        assert_eq!(view((0.0, 0.0), 1e300, 100).validate_and_clamp().unwrap().zoom, deepest);
+        assert_eq!(view((0.0, 0.0), f64::INFINITY, 100).validate_and_clamp().unwrap().zoom, deepest);
+    }
+
+    #[test]
+    fn iterations_are_clamped_into_range() {
+        assert_eq!(view((0.0, 0.0), 1.0, 0).validate_and_clamp().unwrap().iterations, 1);
+        assert_eq!(view((0.0, 0.0), 1.0, u32::MAX).validate_and_clamp().unwrap().iterations, MAX_ITERATIONS);
+    }
+}
@@ -1,5 +1,10 @@
-use minifb::{Window, WindowOptions, Key};
+use minifb::{Window, WindowOptions, Key, MouseButton, MouseMode};
 use raqote::DrawTarget;
+use std::io;
+use std::time::Duration;
+use crate::error::Result;
+
+pub const DEFAULT_FPS_CAP: Option<u32> = Some(60);
 
 pub struct ViewerHandler {
     window: Window,
@@ -7,11 +12,13 @@ pub struct ViewerHandler {
     width: usize,
     height: usize,
     previous_keys: Vec<Key>,
+    mouse_was_down: bool,
+    fps_cap: Option<u32>,
 }
 
 impl ViewerHandler {
-    pub fn new(width: usize, height: usize, title: &str) -> Self {
-        let mut window = Window::new(
+    pub fn new(width: usize, height: usize, title: &str, fps_cap: Option<u32>) -> Result<Self> {
+        let window = Window::new(
             title,
             width,
             height,
@@ -21,18 +28,30 @@ impl ViewerHandler {
                 ..WindowOptions::default()
             },
         )
-        .expect("Failed to create window");
-
-        // Limit to max ~60 fps update rate
-        window.limit_update_rate(Some(std::time::Duration::from_micros(16600)));
+        .map_err(io::Error::other)?;
 
-        ViewerHandler {
+        let mut viewer = ViewerHandler {
             window,
             buffer: vec![0; width * height],
             width,
             height,
             previous_keys: Vec::new(),
-        }
+            mouse_was_down: false,
+            fps_cap,
+        };
+        viewer.set_fps_cap(fps_cap);
+        Ok(viewer)
+    }
+
+    // Limits how often update presents a frame, None presents as fast as possible
+    pub fn set_fps_cap(&mut self, fps_cap: Option<u32>) {
+        self.fps_cap = fps_cap;
+        let frame_time = fps_cap.map(|fps| Duration::from_micros(1_000_000 / fps.max(1) as u64));
+        self.window.limit_update_rate(frame_time);
+    }
+
+    pub fn fps_cap(&self) -> Option<u32> {
+        self.fps_cap
     }
 
     pub fn is_open(&self) -> bool {
@@ -43,6 +62,18 @@ impl ViewerHandler {
         self.window.is_key_down(key)
     }
 
+    // Whether any key other than the ignored ones or a mouse button is held,
+    // or the wheel scrolled, this frame
+    pub fn has_input_except(&self, ignored: &[Key]) -> bool {
+        self.window.get_keys().iter().any(|key| !ignored.contains(key))
+            || self.window.get_mouse_down(MouseButton::Left) || self.scroll() != 0.0
+    }
+
+    // Vertical scroll wheel movement this frame, positive away from the user
+    pub fn scroll(&self) -> f32 {
+        self.window.get_scroll_wheel().map_or(0.0, |(_, y)| y)
+    }
+
     pub fn is_key_pressed(&mut self, key: Key) -> bool {
         let is_down = self.window.is_key_down(key);
         let was_down = self.previous_keys.contains(&key);
@@ -60,9 +91,49 @@ impl ViewerHandler {
         is_pressed
     }
 
+    // Mouse position in render buffer pixels. minifb reports window pixels, which
+    // differ from the buffer whenever the window is scaled or resized.
+    pub fn mouse_position(&self) -> Option<(f32, f32)> {
+        let position = self.window.get_unscaled_mouse_pos(MouseMode::Discard)?;
+        Some(window_to_buffer(position, self.window.get_size(), (self.width, self.height)))
+    }
+
+    pub fn is_mouse_clicked(&mut self) -> bool {
+        let is_down = self.window.get_mouse_down(MouseButton::Left);
+        let is_clicked = is_down && !self.mouse_was_down;
+        self.mouse_was_down = is_down;
+        is_clicked
+    }
+
     pub fn update(&mut self, draw_target: &DrawTarget) -> bool {
+        // minifb takes the draw target's 0xAARRGGBB layout directly, see pixel_format
         let pixels = draw_target.get_data();
         self.buffer.copy_from_slice(pixels);
         self.window.update_with_buffer(&self.buffer, self.width, self.height).is_ok()
     }
-} 
\ No newline at end of file
+}
+
+fn window_to_buffer(position: (f32, f32), window_size: (usize, usize), buffer_size: (usize, usize)) -> (f32, f32) {
+    let scale_x = window_size.0.max(1) as f32 / buffer_size.0 as f32;
+    let scale_y = window_size.1.max(1) as f32 / buffer_size.1 as f32;
+    (position.0 / scale_x, position.1 / scale_y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn window_positions_map_to_buffer_pixels_under_scaling() {
+        // Scale::X2 doubles the window over an 800x600 buffer
+        assert_eq!(window_to_buffer((0.0, 0.0), (1600, 1200), (800, 600)), (0.0, 0.0));
+        assert_eq!(window_to_buffer((800.0, 600.0), (1600, 1200), (800, 600)), (400.0, 300.0));
+        assert_eq!(window_to_buffer((1600.0, 1200.0), (1600, 1200), (800, 600)), (800.0, 600.0));
+
+        // A window resized to another shape scales each axis separately
+        assert_eq!(window_to_buffer((500.0, 150.0), (1000, 300), (800, 600)), (400.0, 300.0));
+
+        // Unscaled windows pass positions through
+        assert_eq!(window_to_buffer((123.0, 45.0), (800, 600), (800, 600)), (123.0, 45.0));
+    }
+}